@@ -0,0 +1,3 @@
+-- Supports paging through a game's entries ordered by score, with the entry id as a tiebreak
+CREATE INDEX IF NOT EXISTS leaderboard_entries_game_score_id
+    ON leaderboard_entries (game_id, score, id);
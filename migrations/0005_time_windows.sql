@@ -0,0 +1,29 @@
+-- Track when entries were first submitted and last improved
+ALTER TABLE leaderboard_entries
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+-- Each game decides where its daily, weekly and monthly boards reset
+ALTER TABLE games
+    ADD COLUMN window_timezone TEXT NOT NULL DEFAULT 'UTC',
+    ADD COLUMN window_reset_hour INTEGER NOT NULL DEFAULT 0 CHECK (window_reset_hour BETWEEN 0 AND 23);
+
+CREATE TYPE LeaderboardWindow AS ENUM ('Daily', 'Weekly', 'Monthly', 'AllTime');
+
+-- Best entry of each user within each time window. All time bests stay in leaderboard_entries
+CREATE TABLE IF NOT EXISTS leaderboard_window_entries (
+    id SERIAL PRIMARY KEY,
+    game_id INTEGER NOT NULL REFERENCES games(id),
+    time_window LeaderboardWindow NOT NULL,
+    window_start TIMESTAMPTZ NOT NULL,
+    score FLOAT NOT NULL,
+    user_name TEXT NOT NULL,
+    user_id UUID NOT NULL,
+    free_data TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (game_id, time_window, window_start, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS leaderboard_window_entries_board_score_id
+    ON leaderboard_window_entries (game_id, time_window, window_start, score, id);
@@ -0,0 +1,24 @@
+-- Invalidated entries are hidden from every leaderboard but kept for audit
+ALTER TABLE leaderboard_entries
+    ADD COLUMN invalidated_at TIMESTAMPTZ,
+    ADD COLUMN invalidation_reason TEXT;
+ALTER TABLE leaderboard_window_entries
+    ADD COLUMN invalidated_at TIMESTAMPTZ,
+    ADD COLUMN invalidation_reason TEXT;
+ALTER TABLE season_entries
+    ADD COLUMN invalidated_at TIMESTAMPTZ,
+    ADD COLUMN invalidation_reason TEXT;
+
+-- Banned users can not submit scores and their entries are hidden. Bans without a game apply to every game
+CREATE TABLE IF NOT EXISTS user_bans (
+    id SERIAL PRIMARY KEY,
+    user_id UUID NOT NULL,
+    game_id INTEGER REFERENCES games(id),
+    reason TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_bans_one_per_game
+    ON user_bans (user_id, game_id) WHERE game_id IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS user_bans_one_global
+    ON user_bans (user_id) WHERE game_id IS NULL;
@@ -0,0 +1,15 @@
+CREATE TYPE ApiKeyRole AS ENUM ('Admin', 'GameOwner', 'Submitter');
+
+-- Bearer keys for the mutating routes. Only a SHA-256 hash of each key is stored
+CREATE TABLE IF NOT EXISTS api_keys (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    role ApiKeyRole NOT NULL,
+    -- Game the key is limited to. Game owner keys always have one, submitter keys may
+    game_id INTEGER REFERENCES games(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    revoked_at TIMESTAMPTZ,
+    CHECK (role <> 'GameOwner' OR game_id IS NOT NULL),
+    CHECK (role <> 'Admin' OR game_id IS NULL)
+);
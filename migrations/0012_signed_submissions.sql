@@ -0,0 +1,12 @@
+-- Secret each game's clients sign their score submissions with. Signatures are only checked when the game requires them
+ALTER TABLE games
+    ADD COLUMN signing_secret TEXT NOT NULL DEFAULT replace(gen_random_uuid()::TEXT || gen_random_uuid()::TEXT, '-', ''),
+    ADD COLUMN require_signed_submissions BOOLEAN NOT NULL DEFAULT false;
+
+-- Nonces of recently signed submissions, to reject replays. Rows older than the timestamp tolerance are purged
+CREATE TABLE IF NOT EXISTS submission_nonces (
+    game_id INTEGER NOT NULL REFERENCES games(id),
+    nonce TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (game_id, nonce)
+);
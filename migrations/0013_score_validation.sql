@@ -0,0 +1,8 @@
+-- Per game rules against obviously fake scores. Absent limits are not checked
+ALTER TABLE games
+    ADD COLUMN min_score FLOAT,
+    ADD COLUMN max_score FLOAT,
+    ADD COLUMN integer_scores BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN max_improvement FLOAT CHECK (max_improvement >= 0),
+    ADD COLUMN min_submission_interval_secs INTEGER CHECK (min_submission_interval_secs >= 0),
+    ADD CHECK (min_score <= max_score);
@@ -0,0 +1,14 @@
+-- Submissions made under an Idempotency-Key header, per API key, with the response to replay to their retries.
+-- The response is NULL while the first request is being handled. Rows older than the retention window are purged
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    api_key_id INTEGER NOT NULL REFERENCES api_keys(id),
+    key TEXT NOT NULL,
+    -- Not a foreign key, keys are claimed before the game is looked up
+    game_id INTEGER NOT NULL,
+    -- Hash of the request, retries with another body are refused
+    fingerprint TEXT NOT NULL,
+    response_status SMALLINT,
+    response_body TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (api_key_id, key)
+);
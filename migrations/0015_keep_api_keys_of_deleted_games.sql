@@ -0,0 +1,3 @@
+-- Deleting a game revokes its API keys rather than deleting them, so the keys stay on record,
+-- still naming the game they were limited to
+ALTER TABLE api_keys DROP CONSTRAINT IF EXISTS api_keys_game_id_fkey;
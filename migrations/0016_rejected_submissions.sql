@@ -0,0 +1,5 @@
+-- Submissions rejected by the game's score validation rules are kept too, along with the rule they broke
+CREATE TYPE ScoreValidationRule AS ENUM
+    ('Finite', 'MinScore', 'MaxScore', 'IntegerScores', 'MaxImprovement', 'MinSubmissionInterval');
+
+ALTER TABLE score_submissions ADD COLUMN rejected_by ScoreValidationRule;
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS todos (
+	id INTEGER PRIMARY KEY AUTOINCREMENT,
+	description TEXT NOT NULL
+);
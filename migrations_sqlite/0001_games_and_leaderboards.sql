@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS games (
+	id INTEGER PRIMARY KEY AUTOINCREMENT,
+	description TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS leaderboard_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    game_id INTEGER REFERENCES games(id),
+    score FLOAT,
+    user_name TEXT NOT NULL,
+    free_data TEXT NOT NULL
+);
@@ -0,0 +1,3 @@
+-- SQLite has no enum types, the GameScoreSortMode values are kept as text instead
+ALTER TABLE games ADD COLUMN score_sort_mode TEXT CHECK (score_sort_mode IN ('HigherIsBetter', 'LesserIsBetter'));
+UPDATE games SET score_sort_mode = 'HigherIsBetter' WHERE score_sort_mode IS NULL;
@@ -0,0 +1,8 @@
+-- Add a new column to the leaderboard_entries table for the uuid of the user. set everything to random ids.
+-- Added columns can only default to constants, the application always sets the uuid
+ALTER TABLE leaderboard_entries
+    ADD COLUMN user_id BLOB NOT NULL DEFAULT X'';
+UPDATE leaderboard_entries SET user_id = randomblob(16);
+
+CREATE UNIQUE INDEX IF NOT EXISTS leaderboard_entries_game_id_user_id
+    ON leaderboard_entries (game_id, user_id);
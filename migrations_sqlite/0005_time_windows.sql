@@ -0,0 +1,33 @@
+-- Track when entries were first submitted and last improved.
+-- Timestamps are RFC 3339 text. Added columns can only default to constants, the application always sets them
+ALTER TABLE leaderboard_entries
+    ADD COLUMN created_at TEXT NOT NULL DEFAULT '1970-01-01T00:00:00+00:00';
+ALTER TABLE leaderboard_entries
+    ADD COLUMN updated_at TEXT NOT NULL DEFAULT '1970-01-01T00:00:00+00:00';
+UPDATE leaderboard_entries SET
+    created_at = strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'),
+    updated_at = strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now');
+
+-- Each game decides where its daily, weekly and monthly boards reset
+ALTER TABLE games
+    ADD COLUMN window_timezone TEXT NOT NULL DEFAULT 'UTC';
+ALTER TABLE games
+    ADD COLUMN window_reset_hour INTEGER NOT NULL DEFAULT 0 CHECK (window_reset_hour BETWEEN 0 AND 23);
+
+-- Best entry of each user within each time window. All time bests stay in leaderboard_entries
+CREATE TABLE IF NOT EXISTS leaderboard_window_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    game_id INTEGER NOT NULL REFERENCES games(id),
+    time_window TEXT NOT NULL CHECK (time_window IN ('Daily', 'Weekly', 'Monthly', 'AllTime')),
+    window_start TEXT NOT NULL,
+    score FLOAT NOT NULL,
+    user_name TEXT NOT NULL,
+    user_id BLOB NOT NULL,
+    free_data TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    UNIQUE (game_id, time_window, window_start, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS leaderboard_window_entries_board_score_id
+    ON leaderboard_window_entries (game_id, time_window, window_start, score, id);
@@ -0,0 +1,32 @@
+CREATE TABLE IF NOT EXISTS seasons (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    game_id INTEGER NOT NULL REFERENCES games(id),
+    name TEXT NOT NULL,
+    started_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    ended_at TEXT
+);
+
+-- At most one open season per game
+CREATE UNIQUE INDEX IF NOT EXISTS seasons_one_open_per_game
+    ON seasons (game_id) WHERE ended_at IS NULL;
+
+-- Season an entry was submitted during, if any
+ALTER TABLE leaderboard_entries
+    ADD COLUMN season_id INTEGER REFERENCES seasons(id);
+
+-- Final standings of closed seasons. Rows are only ever written when a season closes
+CREATE TABLE IF NOT EXISTS season_entries (
+    season_id INTEGER NOT NULL REFERENCES seasons(id),
+    entry_id INTEGER NOT NULL,
+    game_id INTEGER NOT NULL REFERENCES games(id),
+    score FLOAT NOT NULL,
+    user_name TEXT NOT NULL,
+    user_id BLOB NOT NULL,
+    free_data TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (season_id, entry_id)
+);
+
+CREATE INDEX IF NOT EXISTS season_entries_season_score_id
+    ON season_entries (season_id, score, entry_id);
@@ -0,0 +1,15 @@
+-- Append only log of every score submitted, whether or not it improved an entry
+CREATE TABLE IF NOT EXISTS score_submissions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    game_id INTEGER NOT NULL REFERENCES games(id),
+    user_id BLOB NOT NULL,
+    user_name TEXT NOT NULL,
+    score FLOAT NOT NULL,
+    free_data TEXT NOT NULL,
+    accepted BOOLEAN NOT NULL,
+    season_id INTEGER REFERENCES seasons(id),
+    submitted_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS score_submissions_game_user_id
+    ON score_submissions (game_id, user_id, id);
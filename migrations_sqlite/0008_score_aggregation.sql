@@ -0,0 +1,6 @@
+-- How a new submission is merged into a user's stored entry
+ALTER TABLE games
+    ADD COLUMN score_aggregation TEXT NOT NULL DEFAULT 'Best'
+        CHECK (score_aggregation IN ('Best', 'Latest', 'Sum', 'AverageOfLastN', 'Count'));
+ALTER TABLE games
+    ADD COLUMN aggregation_last_n INTEGER NOT NULL DEFAULT 5 CHECK (aggregation_last_n >= 1);
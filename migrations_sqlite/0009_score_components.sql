@@ -0,0 +1,13 @@
+-- Games may declare tiebreakers, ranked after the score: [{"name": "time", "sort_mode": "LesserIsBetter"}]
+ALTER TABLE games
+    ADD COLUMN score_components TEXT NOT NULL DEFAULT '[]';
+
+-- Values of the game's score components, in the order the game declares them, as a JSON array
+ALTER TABLE leaderboard_entries
+    ADD COLUMN score_components TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE leaderboard_window_entries
+    ADD COLUMN score_components TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE season_entries
+    ADD COLUMN score_components TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE score_submissions
+    ADD COLUMN score_components TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,21 @@
+-- Invalidated entries are hidden from every leaderboard but kept for audit
+ALTER TABLE leaderboard_entries ADD COLUMN invalidated_at TEXT;
+ALTER TABLE leaderboard_entries ADD COLUMN invalidation_reason TEXT;
+ALTER TABLE leaderboard_window_entries ADD COLUMN invalidated_at TEXT;
+ALTER TABLE leaderboard_window_entries ADD COLUMN invalidation_reason TEXT;
+ALTER TABLE season_entries ADD COLUMN invalidated_at TEXT;
+ALTER TABLE season_entries ADD COLUMN invalidation_reason TEXT;
+
+-- Banned users can not submit scores and their entries are hidden. Bans without a game apply to every game
+CREATE TABLE IF NOT EXISTS user_bans (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id BLOB NOT NULL,
+    game_id INTEGER REFERENCES games(id),
+    reason TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_bans_one_per_game
+    ON user_bans (user_id, game_id) WHERE game_id IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS user_bans_one_global
+    ON user_bans (user_id) WHERE game_id IS NULL;
@@ -0,0 +1,13 @@
+-- Bearer keys for the mutating routes. Only a SHA-256 hash of each key is stored
+CREATE TABLE IF NOT EXISTS api_keys (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    role TEXT NOT NULL CHECK (role IN ('Admin', 'GameOwner', 'Submitter')),
+    -- Game the key is limited to. Game owner keys always have one, submitter keys may
+    game_id INTEGER REFERENCES games(id),
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    revoked_at TEXT,
+    CHECK (role <> 'GameOwner' OR game_id IS NOT NULL),
+    CHECK (role <> 'Admin' OR game_id IS NULL)
+);
@@ -0,0 +1,15 @@
+-- Secret each game's clients sign their score submissions with. Signatures are only checked when the game requires them.
+-- Added columns can only default to constants, so existing games get their random secrets afterwards
+ALTER TABLE games
+    ADD COLUMN signing_secret TEXT NOT NULL DEFAULT '';
+ALTER TABLE games
+    ADD COLUMN require_signed_submissions BOOLEAN NOT NULL DEFAULT false;
+UPDATE games SET signing_secret = lower(hex(randomblob(32)));
+
+-- Nonces of recently signed submissions, to reject replays. Rows older than the timestamp tolerance are purged
+CREATE TABLE IF NOT EXISTS submission_nonces (
+    game_id INTEGER NOT NULL REFERENCES games(id),
+    nonce TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    PRIMARY KEY (game_id, nonce)
+);
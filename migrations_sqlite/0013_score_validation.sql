@@ -0,0 +1,6 @@
+-- Per game rules against obviously fake scores. Absent limits are not checked
+ALTER TABLE games ADD COLUMN min_score FLOAT;
+ALTER TABLE games ADD COLUMN max_score FLOAT CHECK (min_score <= max_score);
+ALTER TABLE games ADD COLUMN integer_scores BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE games ADD COLUMN max_improvement FLOAT CHECK (max_improvement >= 0);
+ALTER TABLE games ADD COLUMN min_submission_interval_secs INTEGER CHECK (min_submission_interval_secs >= 0);
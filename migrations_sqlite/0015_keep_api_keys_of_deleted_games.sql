@@ -0,0 +1,22 @@
+-- Deleting a game revokes its API keys rather than deleting them, so the keys stay on record,
+-- still naming the game they were limited to.
+-- SQLite can not drop a foreign key, so the table is rebuilt without it. Stored idempotent responses are dropped
+-- first, as they reference the keys; at worst a retry in flight is handled afresh
+DELETE FROM idempotency_keys;
+
+CREATE TABLE api_keys_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    role TEXT NOT NULL CHECK (role IN ('Admin', 'GameOwner', 'Submitter')),
+    -- Game the key is limited to. Game owner keys always have one, submitter keys may
+    game_id INTEGER,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    revoked_at TEXT,
+    CHECK (role <> 'GameOwner' OR game_id IS NOT NULL),
+    CHECK (role <> 'Admin' OR game_id IS NULL)
+);
+INSERT INTO api_keys_new (id, name, key_hash, role, game_id, created_at, revoked_at)
+    SELECT id, name, key_hash, role, game_id, created_at, revoked_at FROM api_keys;
+DROP TABLE api_keys;
+ALTER TABLE api_keys_new RENAME TO api_keys;
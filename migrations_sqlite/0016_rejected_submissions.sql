@@ -0,0 +1,3 @@
+-- Submissions rejected by the game's score validation rules are kept too, along with the rule they broke
+ALTER TABLE score_submissions ADD COLUMN rejected_by TEXT
+    CHECK (rejected_by IN ('Finite', 'MinScore', 'MaxScore', 'IntegerScores', 'MaxImprovement', 'MinSubmissionInterval'));
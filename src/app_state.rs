@@ -1,5 +1,15 @@
-use sqlx::PgPool;
+use std::sync::Arc;
+
+use crate::leaderboard::rate_limit::RateLimiter;
+use crate::leaderboard::store::LeaderboardStore;
+use crate::metrics::Metrics;
+use crate::shutdown::Shutdown;
+
 #[derive(Clone)]
 pub struct AppState {
-    pub db: PgPool,
-}
\ No newline at end of file
+    /// Games and their leaderboards, along with the API keys, bans and submission nonces guarding them
+    pub store: Arc<dyn LeaderboardStore>,
+    pub shutdown: Shutdown,
+    pub metrics: Metrics,
+    pub rate_limiter: RateLimiter,
+}
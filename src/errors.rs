@@ -1,34 +1,285 @@
-use axum::http::StatusCode;
+use std::borrow::Cow;
+
+use askama::Template;
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER};
+use axum::http::{HeaderName, StatusCode};
+use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
+use tracing::error;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::hetero_req_resp::AcceptType;
+use crate::telemetry;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Headers of the IETF rate limit fields draft, telling the budget, what is left of it and when it refills
+pub const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+pub const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+pub const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+/// Machine readable reason of an error. Codes are stable, new ones may be added
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy,
+    utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    NotFound,
+    Conflict,
+    InvalidCursor,
+    InvalidTimezone,
+    InvalidResetHour,
+    InvalidAggregationLastN,
+    InvalidScoreComponents,
+    InvalidScoreRange,
+    InvalidMaxImprovement,
+    InvalidSubmissionInterval,
+    InvalidApiKeyGame,
+    /// The submitted score breaks one of the game's score validation rules
+    ScoreRejected,
+    MissingApiKey,
+    InvalidApiKey,
+    MissingSignature,
+    StaleSignature,
+    InvalidSignature,
+    ReplayedNonce,
+    Forbidden,
+    UserBanned,
+    EntryInvalidated,
+    /// A WebSocket message which is not valid JSON or not one of the known messages
+    InvalidMessage,
+    RateLimited,
+    InvalidIdempotencyKey,
+    /// An Idempotency-Key sent again with another request
+    IdempotencyKeyReused,
+    /// An Idempotency-Key sent again while its first request is still being handled
+    IdempotencyKeyInFlight,
+    Internal,
+}
 
+/// RFC 7807 problem details, sent as `application/problem+json`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone,
+    utoipa::ToSchema)]
+pub struct Problem {
+    /// Always `about:blank`, see `code` instead
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// Reason phrase of the status
+    pub title: String,
+    pub status: u16,
+    /// Human readable explanation, may change between releases
+    pub detail: String,
+    pub code: ErrorCode,
+    /// Extension members specific to the code, e.g. the `rule` and `limit` of a `score_rejected` problem
+    #[serde(flatten)]
+    #[schema(value_type = Object)]
+    pub extensions: Map<String, Value>,
+    /// Id of the failed request, as in its `X-Request-Id` header, for finding it in the logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug)]
 pub enum ApiError {
-    SQLError(sqlx::Error),
-    HTTPError(axum::http::Error),
+    NotFound,
+    Conflict { code: ErrorCode, detail: Cow<'static, str> },
+    /// A malformed or inconsistent request
+    Validation { code: ErrorCode, detail: Cow<'static, str> },
+    /// A well formed request the server refuses to process, with extension members explaining why
+    Unprocessable { code: ErrorCode, detail: Cow<'static, str>, extensions: Map<String, Value> },
+    Unauthorized { code: ErrorCode, detail: Cow<'static, str> },
+    Forbidden { code: ErrorCode, detail: Cow<'static, str> },
+    /// Spent a rate limit budget of `limit` requests
+    RateLimited { retry_after_secs: u64, limit: u32 },
+    /// Logged, but never shown to clients
+    Internal(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl ApiError {
+    pub const fn conflict(code: ErrorCode, detail: &'static str) -> Self {
+        Self::Conflict { code, detail: Cow::Borrowed(detail) }
+    }
+
+    pub const fn validation(code: ErrorCode, detail: &'static str) -> Self {
+        Self::Validation { code, detail: Cow::Borrowed(detail) }
+    }
+
+    pub const fn unauthorized(code: ErrorCode, detail: &'static str) -> Self {
+        Self::Unauthorized { code, detail: Cow::Borrowed(detail) }
+    }
+
+    pub const fn forbidden(code: ErrorCode, detail: &'static str) -> Self {
+        Self::Forbidden { code, detail: Cow::Borrowed(detail) }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Conflict { .. } => StatusCode::CONFLICT,
+            Self::Validation { .. } => StatusCode::BAD_REQUEST,
+            Self::Unprocessable { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+            Self::Forbidden { .. } => StatusCode::FORBIDDEN,
+            Self::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn problem(&self) -> Problem {
+        let status = self.status();
+        let (code, detail, extensions) = match self {
+            Self::NotFound => (ErrorCode::NotFound, "Not Found".into(), Map::new()),
+            Self::Unprocessable { code, detail, extensions } => (*code, detail.to_string(), extensions.clone()),
+            Self::Conflict { code, detail }
+            | Self::Validation { code, detail }
+            | Self::Unauthorized { code, detail }
+            | Self::Forbidden { code, detail } => (*code, detail.to_string(), Map::new()),
+            Self::RateLimited { retry_after_secs, .. } => (
+                ErrorCode::RateLimited,
+                format!("Too many requests, retry in {retry_after_secs} seconds"),
+                Map::new(),
+            ),
+            Self::Internal(_) => (ErrorCode::Internal, "Internal Server Error".into(), Map::new()),
+        };
+
+        Problem {
+            problem_type: "about:blank".into(),
+            title: status.canonical_reason().unwrap_or_default().into(),
+            status: status.as_u16(),
+            detail,
+            code,
+            extensions,
+            request_id: None,
+        }
+    }
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        match self {
-            Self::SQLError(e) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, format!("SQL error: {e}")).into_response()
-            }
-            Self::HTTPError(e) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("HTTP error: {e}"),
-            )
-                .into_response(),
+        if let Self::Internal(ref e) = self {
+            error!("Internal error: {e}");
+        }
+        let mut response = self.problem().into_response();
+        if let Self::RateLimited { retry_after_secs, limit } = self {
+            let headers = response.headers_mut();
+            headers.insert(RETRY_AFTER, retry_after_secs.into());
+            headers.insert(RATELIMIT_LIMIT, limit.into());
+            headers.insert(RATELIMIT_REMAINING, 0.into());
+            headers.insert(RATELIMIT_RESET, retry_after_secs.into());
         }
+        response
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let body = serde_json::to_string(&self).expect("Problems serialize to JSON");
+
+        let mut response = (status, [(CONTENT_TYPE, PROBLEM_JSON)], body).into_response();
+        // Lets `render_htmx_errors` show the problem as html instead
+        response.extensions_mut().insert(self);
+        response
     }
 }
 
 impl From<sqlx::Error> for ApiError {
     fn from(e: sqlx::Error) -> Self {
-        Self::SQLError(e)
+        Self::Internal(Box::new(e))
     }
 }
 
 impl From<axum::http::Error> for ApiError {
     fn from(e: axum::http::Error) -> Self {
-        Self::HTTPError(e)
+        Self::Internal(Box::new(e))
+    }
+}
+
+/// Middleware adding the request id to problems, so clients can quote it
+pub async fn attach_request_id(request: Request, next: Next) -> Response {
+    let request_id = telemetry::request_id(&request).map(String::from);
+    let response = next.run(request).await;
+    let Some(request_id) = request_id else {
+        return response;
+    };
+    let Some(problem) = response.extensions().get::<Problem>() else {
+        return response;
+    };
+
+    let problem = Problem { request_id: Some(request_id), ..problem.clone() };
+    let (mut parts, _) = response.into_parts();
+    let body = serde_json::to_string(&problem).expect("Problems serialize to JSON");
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.extensions.insert(problem);
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+pub struct ErrorTemplate {
+    pub code: String,
+    pub problem: Problem,
+}
+
+/// Middleware replacing the problem+json body of errors with an html fragment, for HTMX clients
+pub async fn render_htmx_errors(accept_type: AcceptType, request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let AcceptType::HTMX = accept_type else {
+        return response;
+    };
+    let Some(problem) = response.extensions().get::<Problem>().cloned() else {
+        return response;
+    };
+
+    let (mut parts, _) = response.into_parts();
+    let code = serde_json::to_value(problem.code).ok()
+        .and_then(|code| code.as_str().map(String::from))
+        .unwrap_or_default();
+    let mut html = ErrorTemplate { code, problem }.into_response();
+    parts.headers.remove(CONTENT_TYPE);
+    html.headers_mut().extend(parts.headers);
+    *html.status_mut() = parts.status;
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problems_have_stable_codes() {
+        let problem = serde_json::to_value(ApiError::validation(ErrorCode::InvalidCursor, "Invalid cursor").problem()).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "type": "about:blank",
+                "title": "Bad Request",
+                "status": 400,
+                "detail": "Invalid cursor",
+                "code": "invalid_cursor",
+            }),
+            problem,
+        );
+    }
+
+    #[test]
+    fn internal_errors_hide_their_cause() {
+        let error = ApiError::from(sqlx::Error::RowNotFound);
+        let problem = error.problem();
+        assert_eq!(500, problem.status);
+        assert_eq!(ErrorCode::Internal, problem.code);
+        assert!(!problem.detail.contains("no rows"));
+    }
+
+    #[test]
+    fn rate_limits_say_when_to_retry() {
+        let response = ApiError::RateLimited { retry_after_secs: 7, limit: 10 }.into_response();
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+        assert_eq!("7", response.headers()[RETRY_AFTER]);
+        assert_eq!("10", response.headers()[RATELIMIT_LIMIT]);
+        assert_eq!("0", response.headers()[RATELIMIT_REMAINING]);
+        assert_eq!("7", response.headers()[RATELIMIT_RESET]);
+        assert_eq!(PROBLEM_JSON, response.headers()[CONTENT_TYPE]);
     }
 }
@@ -1,4 +1,4 @@
-#![allow(dead_code)]
+#![allow(dead_code, unused_imports)]
 
 mod openapi;
 mod models;
@@ -6,6 +6,10 @@ mod hetero_req_resp;
 mod leaderboard;
 mod errors;
 mod app_state;
+mod pubsub;
+mod shutdown;
+mod metrics;
+mod telemetry;
 
 use std::fs;
 use crate::openapi::gen_my_openapi;
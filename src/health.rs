@@ -0,0 +1,63 @@
+//! Probes for orchestrators: liveness, readiness, and what build is running.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use tracing::error;
+use serde::Serialize;
+
+use crate::app_state::AppState;
+use crate::errors::ApiError;
+
+#[derive(Serialize, Debug)]
+pub struct Readiness {
+    pub ready: bool,
+    pub database_reachable: bool,
+    /// Versions of the migrations the database lacks
+    pub pending_migrations: Vec<i64>,
+    pub shutting_down: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// Commit the app was built from, `unknown` when built outside a git checkout without `GIT_SHA`
+    pub git_sha: &'static str,
+    /// Versions of the migrations applied to the database
+    pub migrations: Vec<i64>,
+}
+
+/// Liveness. Responds as long as the server does, without touching the database
+pub async fn healthz() -> impl IntoResponse {
+    "ok"
+}
+
+/// Readiness. Fails with a 503 when the database is unreachable or not fully migrated, or the server is shutting down
+pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let migrations = state.store.get_migrations().await
+        .inspect_err(|e| error!("Readiness check could not reach the database: {e:?}"))
+        .ok();
+    let shutting_down = state.shutdown.is_triggered();
+    let pending_migrations = migrations.map(|migrations| migrations.pending);
+    let ready = pending_migrations.as_ref().is_some_and(Vec::is_empty) && !shutting_down;
+
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(Readiness {
+        ready,
+        database_reachable: pending_migrations.is_some(),
+        pending_migrations: pending_migrations.unwrap_or_default(),
+        shutting_down,
+    }))
+}
+
+/// Version and commit of the running build, along with the migrations applied to its database
+pub async fn build_info(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let migrations = state.store.get_migrations().await?;
+
+    Ok(Json(BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: env!("GIT_SHA"),
+        migrations: migrations.applied,
+    }))
+}
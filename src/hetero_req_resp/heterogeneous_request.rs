@@ -6,7 +6,7 @@ use axum::http::{HeaderValue, StatusCode};
 use axum::{async_trait, Form, Json};
 use serde::de::DeserializeOwned;
 use std::fmt::Debug;
-use log::error;
+use tracing::error;
 
 const FORM_HEADER: &str = "application/x-www-form-urlencoded";
 const JSON_HEADER: &str = "application/json";
@@ -63,13 +63,14 @@ where
 }
 
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 enum ContentType {
     FORM,
     JSON,
 }
 
 fn characterize_content_type(content: Option<&HeaderValue>) -> Option<ContentType> {
-    let Some(content) = content else { return None };
+    let content = content?;
     let Ok(content_str) = content.to_str() else {
         error!("content header was not ascii! {:?}", content);
         return None;
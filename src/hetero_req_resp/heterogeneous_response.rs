@@ -4,9 +4,10 @@ use axum::http::header::ACCEPT;
 use axum::http::request::Parts;
 use axum::http::HeaderValue;
 use std::convert::Infallible;
-use log::error;
+use tracing::error;
 
 #[derive(Copy, Clone)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum AcceptType {
     HTMX,
     JSON,
@@ -25,7 +26,7 @@ impl<S> FromRequestParts<S> for AcceptType {
 
 fn characterize_accept_type(accept: Option<&HeaderValue>) -> Option<AcceptType> {
     use AcceptType::*;
-    let Some(accept) = accept else { return None };
+    let accept = accept?;
     let Ok(accept_str) = accept.to_str() else {
         error!("accept header was not ascii! {:?}", accept);
         return None;
@@ -0,0 +1,132 @@
+use std::cmp::Ordering;
+
+use super::models::ScoreAggregation;
+use super::ranking::Ranking;
+
+impl ScoreAggregation {
+    /// Score to store after merging a submission into the user's existing entry on a leaderboard.
+    /// `existing` and `submitted` are ranking keys: a score followed by its score components.
+    /// `previous` holds the scores of the user's most recent earlier submissions to that leaderboard, newest first,
+    /// and is only read when averaging.
+    ///
+    /// Returns `None` when the existing entry should be kept as it is. Otherwise the stored entry takes the
+    /// submission's score components along with the merged score.
+    pub fn merge(
+        &self,
+        ranking: &Ranking,
+        existing: Option<&[f64]>,
+        submitted: &[f64],
+        previous: &[f64],
+    ) -> Option<f64> {
+        let existing_score = existing.map(|existing| existing[0]);
+        let submitted_score = submitted[0];
+        match self {
+            ScoreAggregation::Best => match existing {
+                Some(existing) if ranking.compare(existing, submitted) != Ordering::Greater => None,
+                _ => Some(submitted_score),
+            },
+            ScoreAggregation::Latest => Some(submitted_score),
+            ScoreAggregation::Sum => Some(existing_score.unwrap_or(0.0) + submitted_score),
+            ScoreAggregation::AverageOfLastN => {
+                let total = previous.iter().sum::<f64>() + submitted_score;
+                Some(total / (previous.len() + 1) as f64)
+            }
+            ScoreAggregation::Count => Some(existing_score.unwrap_or(0.0) + 1.0),
+        }
+    }
+
+    /// What a submission adds to the stored score, for the aggregations which only ever add to it.
+    /// Saving the increment rather than the merged score keeps concurrent submissions from overwriting each other
+    pub fn increment(&self, submitted_score: f64) -> Option<f64> {
+        match self {
+            ScoreAggregation::Sum => Some(submitted_score),
+            ScoreAggregation::Count => Some(1.0),
+            ScoreAggregation::Best | ScoreAggregation::Latest | ScoreAggregation::AverageOfLastN => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::leaderboard::models::{Game, GameScoreSortMode, ScoreComponent};
+    use sqlx::types::Json;
+
+    fn ranking(score_sort_mode: GameScoreSortMode, component_sort_modes: &[GameScoreSortMode]) -> Ranking {
+        let score_components = component_sort_modes.iter()
+            .map(|sort_mode| ScoreComponent { name: "component".into(), sort_mode: *sort_mode })
+            .collect();
+        Ranking::for_game(&Game {
+            id: 0,
+            description: "".into(),
+            score_sort_mode,
+            score_components: Json(score_components),
+            score_aggregation: ScoreAggregation::Best,
+            aggregation_last_n: 5,
+            window_timezone: "UTC".into(),
+            window_reset_hour: 0,
+            require_signed_submissions: false,
+            min_score: None,
+            max_score: None,
+            integer_scores: false,
+            max_improvement: None,
+            min_submission_interval_secs: None,
+            signing_secret: "".into(),
+        })
+    }
+
+    const HIGHER: GameScoreSortMode = GameScoreSortMode::HigherIsBetter;
+    const LESSER: GameScoreSortMode = GameScoreSortMode::LesserIsBetter;
+
+    #[test]
+    fn best_keeps_better_or_equal_entries() {
+        let higher = ranking(HIGHER, &[]);
+        assert_eq!(None, ScoreAggregation::Best.merge(&higher, Some(&[10.0]), &[5.0], &[]));
+        assert_eq!(None, ScoreAggregation::Best.merge(&higher, Some(&[10.0]), &[10.0], &[]));
+        assert_eq!(Some(15.0), ScoreAggregation::Best.merge(&higher, Some(&[10.0]), &[15.0], &[]));
+        assert_eq!(Some(5.0), ScoreAggregation::Best.merge(&ranking(LESSER, &[]), Some(&[10.0]), &[5.0], &[]));
+        assert_eq!(Some(5.0), ScoreAggregation::Best.merge(&higher, None, &[5.0], &[]));
+    }
+
+    #[test]
+    fn best_breaks_ties_with_components() {
+        let ranking = ranking(HIGHER, &[LESSER]);
+        assert_eq!(Some(10.0), ScoreAggregation::Best.merge(&ranking, Some(&[10.0, 30.0]), &[10.0, 20.0], &[]));
+        assert_eq!(None, ScoreAggregation::Best.merge(&ranking, Some(&[10.0, 20.0]), &[10.0, 30.0], &[]));
+        assert_eq!(None, ScoreAggregation::Best.merge(&ranking, Some(&[10.0, 20.0]), &[10.0, 20.0], &[]));
+    }
+
+    #[test]
+    fn latest_always_replaces() {
+        assert_eq!(Some(5.0), ScoreAggregation::Latest.merge(&ranking(HIGHER, &[]), Some(&[10.0]), &[5.0], &[]));
+    }
+
+    #[test]
+    fn sum_accumulates() {
+        let ranking = ranking(HIGHER, &[]);
+        assert_eq!(Some(5.0), ScoreAggregation::Sum.merge(&ranking, None, &[5.0], &[]));
+        assert_eq!(Some(15.0), ScoreAggregation::Sum.merge(&ranking, Some(&[10.0]), &[5.0], &[]));
+    }
+
+    #[test]
+    fn average_includes_submitted_score() {
+        let ranking = ranking(HIGHER, &[]);
+        assert_eq!(Some(5.0), ScoreAggregation::AverageOfLastN.merge(&ranking, None, &[5.0], &[]));
+        assert_eq!(Some(4.0), ScoreAggregation::AverageOfLastN.merge(&ranking, Some(&[3.0]), &[6.0], &[2.0, 4.0]));
+    }
+
+    #[test]
+    fn count_ignores_score() {
+        let ranking = ranking(HIGHER, &[]);
+        assert_eq!(Some(1.0), ScoreAggregation::Count.merge(&ranking, None, &[99.0], &[]));
+        assert_eq!(Some(4.0), ScoreAggregation::Count.merge(&ranking, Some(&[3.0]), &[99.0], &[]));
+    }
+
+    #[test]
+    fn only_sum_and_count_increment() {
+        assert_eq!(Some(5.0), ScoreAggregation::Sum.increment(5.0));
+        assert_eq!(Some(1.0), ScoreAggregation::Count.increment(5.0));
+        assert_eq!(None, ScoreAggregation::Best.increment(5.0));
+        assert_eq!(None, ScoreAggregation::AverageOfLastN.increment(5.0));
+    }
+}
@@ -0,0 +1,166 @@
+use askama_axum::{IntoResponse, Response};
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use axum::http::request::Parts;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use super::models::{ApiKey, ApiKeyCreated, ApiKeyNew, ApiKeyRole};
+use super::store::LeaderboardStore;
+use crate::app_state::AppState;
+use crate::errors::{ApiError, ErrorCode};
+
+const KEY_PREFIX: &str = "lb_";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ApiKeyRejection {
+    #[error("Expected an `Authorization: Bearer <key>` header")]
+    Missing,
+    #[error("Unknown or revoked API key")]
+    Invalid,
+    #[error("Could not look up the API key")]
+    Store(ApiError),
+}
+
+impl From<ApiKeyRejection> for ApiError {
+    fn from(rejection: ApiKeyRejection) -> Self {
+        match rejection {
+            ApiKeyRejection::Missing => ApiError::Unauthorized {
+                code: ErrorCode::MissingApiKey,
+                detail: rejection.to_string().into(),
+            },
+            ApiKeyRejection::Invalid => ApiError::Unauthorized {
+                code: ErrorCode::InvalidApiKey,
+                detail: rejection.to_string().into(),
+            },
+            ApiKeyRejection::Store(e) => e,
+        }
+    }
+}
+
+impl IntoResponse for ApiKeyRejection {
+    fn into_response(self) -> Response {
+        let error = ApiError::from(self);
+        match error {
+            ApiError::Unauthorized { .. } => ([(WWW_AUTHENTICATE, "Bearer")], error).into_response(),
+            _ => error.into_response(),
+        }
+    }
+}
+
+/// Authenticates the request's bearer key. Handlers still decide what the key's role allows
+#[async_trait]
+impl FromRequestParts<AppState> for ApiKey {
+    type Rejection = ApiKeyRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let key = parts.headers.get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .ok_or(ApiKeyRejection::Missing)?;
+
+        state.store.get_api_key_by_hash(&hash_key(key.trim()))
+            .await
+            .map_err(ApiKeyRejection::Store)?
+            .ok_or(ApiKeyRejection::Invalid)
+    }
+}
+
+impl ApiKey {
+    pub fn is_admin(&self) -> bool {
+        self.role == ApiKeyRole::Admin
+    }
+
+    /// Whether the key may change, moderate and run seasons of the game
+    pub fn can_manage_game(&self, game_id: i32) -> bool {
+        match self.role {
+            ApiKeyRole::Admin => true,
+            ApiKeyRole::GameOwner => self.game_id == Some(game_id),
+            ApiKeyRole::Submitter => false,
+        }
+    }
+
+    /// Whether the key may submit scores to the game
+    pub fn can_submit_to_game(&self, game_id: i32) -> bool {
+        match self.role {
+            ApiKeyRole::Submitter => self.game_id.is_none_or(|key_game_id| key_game_id == game_id),
+            _ => self.can_manage_game(game_id),
+        }
+    }
+}
+
+pub fn generate_key() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{KEY_PREFIX}{}", hex::encode(bytes))
+}
+
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Stores a new key, returning it in plain text along with its details
+pub async fn insert_api_key(store: &dyn LeaderboardStore, request: &ApiKeyNew) -> Result<ApiKeyCreated, ApiError> {
+    let key = generate_key();
+    let api_key = store.create_api_key(request, &hash_key(&key)).await?;
+
+    Ok(ApiKeyCreated { api_key, key })
+}
+
+/// Makes sure a configured admin key can be used, so the first keys can be minted
+pub async fn ensure_admin_key(store: &dyn LeaderboardStore, key: &str) -> Result<(), ApiError> {
+    store.ensure_admin_key(&hash_key(key)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(role: ApiKeyRole, game_id: Option<i32>) -> ApiKey {
+        ApiKey { id: 0, name: "".into(), role, game_id, created_at: Default::default(), revoked_at: None }
+    }
+
+    #[test]
+    fn generated_keys_are_unique_and_prefixed() {
+        let (a, b) = (generate_key(), generate_key());
+        assert_ne!(a, b);
+        assert!(a.starts_with(KEY_PREFIX));
+        assert_eq!(KEY_PREFIX.len() + 64, a.len());
+    }
+
+    #[test]
+    fn hashes_are_hex_sha256() {
+        assert_eq!("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824", hash_key("hello"));
+    }
+
+    #[test]
+    fn admins_may_do_everything() {
+        let admin = key(ApiKeyRole::Admin, None);
+        assert!(admin.is_admin());
+        assert!(admin.can_manage_game(1));
+        assert!(admin.can_submit_to_game(1));
+    }
+
+    #[test]
+    fn game_owners_are_limited_to_their_game() {
+        let owner = key(ApiKeyRole::GameOwner, Some(1));
+        assert!(!owner.is_admin());
+        assert!(owner.can_manage_game(1));
+        assert!(owner.can_submit_to_game(1));
+        assert!(!owner.can_manage_game(2));
+        assert!(!owner.can_submit_to_game(2));
+    }
+
+    #[test]
+    fn submitters_only_submit() {
+        let submitter = key(ApiKeyRole::Submitter, None);
+        assert!(!submitter.can_manage_game(1));
+        assert!(submitter.can_submit_to_game(1));
+        assert!(submitter.can_submit_to_game(2));
+
+        let game_submitter = key(ApiKeyRole::Submitter, Some(1));
+        assert!(game_submitter.can_submit_to_game(1));
+        assert!(!game_submitter.can_submit_to_game(2));
+    }
+}
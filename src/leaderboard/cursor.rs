@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+
+use super::models::LeaderboardEntry;
+
+/// Keyset position of a leaderboard entry, used to page through a game's entries stably
+/// while new scores keep arriving.
+///
+/// Encoded as an opaque, url-safe hex string so clients never depend on its contents.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EntryCursor {
+    /// The entry's score followed by its score components
+    pub keys: Vec<f64>,
+    /// When the entry reached its score, breaking ties before the id
+    pub updated_at: DateTime<Utc>,
+    pub id: i32,
+}
+
+impl EntryCursor {
+    pub fn of(entry: &LeaderboardEntry) -> Self {
+        Self { keys: entry.ranking_keys(), updated_at: entry.updated_at, id: entry.id }
+    }
+
+    pub fn encode(&self) -> String {
+        let keys = self.keys.iter()
+            .map(|key| format!("{:016x}", key.to_bits()))
+            .collect::<String>();
+        format!(
+            "{:08x}{:016x}{:08x}{}",
+            self.id as u32,
+            self.updated_at.timestamp() as u64,
+            self.updated_at.timestamp_subsec_nanos(),
+            keys,
+        )
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        if cursor.len() < 48 || !(cursor.len() - 32).is_multiple_of(16) || !cursor.is_ascii() {
+            return None;
+        }
+        let (id, rest) = cursor.split_at(8);
+        let (seconds, rest) = rest.split_at(16);
+        let (nanos, keys) = rest.split_at(8);
+        let id = u32::from_str_radix(id, 16).ok()? as i32;
+        let seconds = u64::from_str_radix(seconds, 16).ok()? as i64;
+        let nanos = u32::from_str_radix(nanos, 16).ok()?;
+        let updated_at = DateTime::from_timestamp(seconds, nanos)?;
+        let keys = (0..keys.len()).step_by(16)
+            .map(|i| u64::from_str_radix(&keys[i..i + 16], 16).ok().map(f64::from_bits))
+            .collect::<Option<Vec<_>>>()?;
+        if keys.iter().any(|key| key.is_nan()) {
+            return None;
+        }
+
+        Some(Self { keys, updated_at, id })
+    }
+
+    pub fn score(&self) -> f64 {
+        self.keys[0]
+    }
+
+    pub fn components(&self) -> &[f64] {
+        &self.keys[1..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        for cursor in [
+            EntryCursor { keys: vec![12.5], updated_at: Utc::now(), id: 3 },
+            EntryCursor { keys: vec![-0.001, 4.0, -9.5], updated_at: DateTime::UNIX_EPOCH, id: i32::MAX },
+            EntryCursor { keys: vec![f64::MAX], updated_at: DateTime::from_timestamp(-1, 999).unwrap(), id: 0 },
+        ] {
+            assert_eq!(Some(cursor.clone()), EntryCursor::decode(&cursor.encode()));
+        }
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(None, EntryCursor::decode(""));
+        assert_eq!(None, EntryCursor::decode("not a cursor"));
+        assert_eq!(None, EntryCursor::decode("zzzzzzzzzzzzzzzzzzzzzzzz"));
+        assert_eq!(None, EntryCursor::decode("0000000100000000000000000"));
+        assert_eq!(None, EntryCursor::decode(&format!("00000001{}ffffffff{}", "0".repeat(16), "0".repeat(16))));
+    }
+}
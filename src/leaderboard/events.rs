@@ -0,0 +1,246 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use rand::RngCore;
+use sqlx::PgPool;
+use std::convert::Infallible;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+use super::models::LeaderboardUpdate;
+use crate::pubsub;
+
+/// Postgres channel the updates are published on
+pub const NOTIFY_CHANNEL: &str = "leaderboard_updates";
+
+/// How many updates a subscriber may fall behind before it has to resync
+const CHANNEL_CAPACITY: usize = 64;
+/// How many past updates are kept for clients reconnecting with a `Last-Event-ID`
+const REPLAY_CAPACITY: usize = 256;
+
+/// Update along with its event id. Ids increase, but only mean something to the instance which streamed them:
+/// every instance numbers the updates it hears on its own, from a random offset, so a `Last-Event-ID` given by
+/// another instance or a previous run is unknown, and the client is asked to resync
+#[derive(Debug, Clone)]
+pub struct StreamedUpdate {
+    pub id: u64,
+    pub update: LeaderboardUpdate,
+}
+
+/// What the streams send to their subscribers
+#[derive(Debug, Clone)]
+// Nearly every message is an update, boxing them would only add allocations
+#[allow(clippy::large_enum_variant)]
+pub enum StreamMessage {
+    Update(StreamedUpdate),
+    /// Updates were missed, the leaderboards should be fetched again
+    Resync,
+}
+
+/// Broadcasts leaderboard updates to the streams, keeping the latest ones for replay
+#[derive(Clone)]
+pub struct LeaderboardStream {
+    tx: Sender<StreamMessage>,
+    log: Arc<Mutex<ReplayLog>>,
+    /// Where updates are published to every instance, once listening
+    db: Option<PgPool>,
+}
+
+struct ReplayLog {
+    next_id: u64,
+    updates: VecDeque<StreamedUpdate>,
+}
+
+/// Updates missed since a `Last-Event-ID`, followed by the live ones
+pub struct Subscription {
+    /// None when the missed updates are no longer kept, and the client has to resync
+    pub missed: Option<Vec<StreamedUpdate>>,
+    pub rx: Receiver<StreamMessage>,
+}
+
+impl Default for LeaderboardStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LeaderboardStream {
+    pub fn new() -> Self {
+        let (tx, _rx) = broadcast::channel(CHANNEL_CAPACITY);
+        // Far apart offsets keep the ids of other instances and previous runs from being mistaken for our own
+        let next_id = u64::from(rand::thread_rng().next_u32()) << 32;
+        Self { tx, log: Arc::new(Mutex::new(ReplayLog { next_id, updates: VecDeque::new() })), db: None }
+    }
+
+    /// Sends the update to the streams of every instance, see [`pubsub`].
+    /// Without Postgres to listen on there are no other instances, and the update is streamed on this one
+    pub async fn publish(&self, mut update: LeaderboardUpdate) {
+        let Some(db) = &self.db else {
+            return self.send(update);
+        };
+        if serde_json::to_string(&update).map_or(0, |json| json.len()) >= pubsub::MAX_PAYLOAD_LEN {
+            // Too big to notify, subscribers can still fetch the entry by its id
+            update.entry = None;
+        }
+        pubsub::publish_or_send(db, NOTIFY_CHANNEL, update, |update| self.send(update)).await;
+    }
+
+    /// Streams the updates published by every instance, including this one, publishing through `db` from then on
+    pub async fn listen(&mut self, db: &PgPool) -> Result<(), sqlx::Error> {
+        let (stream, resyncing) = (self.clone(), self.clone());
+        pubsub::listen(db, NOTIFY_CHANNEL, move |update| stream.send(update), move || resyncing.resync()).await?;
+        self.db = Some(db.clone());
+
+        Ok(())
+    }
+
+    /// Streams the update on this instance only
+    pub fn send(&self, update: LeaderboardUpdate) {
+        let mut log = self.log.lock().unwrap();
+        let streamed = StreamedUpdate { id: log.next_id, update };
+        log.next_id += 1;
+        if log.updates.len() == REPLAY_CAPACITY {
+            log.updates.pop_front();
+        }
+        log.updates.push_back(streamed.clone());
+        // Sent under the lock, so subscribers get every update either replayed or live, never both
+        let _ = self.tx.send(StreamMessage::Update(streamed));
+    }
+
+    /// Tells the subscribers on this instance that updates were missed. Clients reconnecting with the id of an
+    /// earlier event resync too, as the id skipped here stands for the missed updates
+    pub fn resync(&self) {
+        let mut log = self.log.lock().unwrap();
+        log.next_id += 1;
+        log.updates.clear();
+        let _ = self.tx.send(StreamMessage::Resync);
+    }
+
+    /// Open subscriptions on this instance
+    pub fn subscriber_count(&self) -> usize {
+        self.tx.receiver_count()
+    }
+
+    pub fn subscribe(&self, last_event_id: Option<u64>) -> Subscription {
+        let log = self.log.lock().unwrap();
+        let rx = self.tx.subscribe();
+        let missed = match last_event_id {
+            None => Some(vec![]),
+            Some(last_event_id) => log.missed_since(last_event_id),
+        };
+
+        Subscription { missed, rx }
+    }
+}
+
+impl ReplayLog {
+    fn missed_since(&self, last_event_id: u64) -> Option<Vec<StreamedUpdate>> {
+        let oldest_id = self.updates.front().map_or(self.next_id, |oldest| oldest.id);
+        if last_event_id >= self.next_id || last_event_id + 1 < oldest_id {
+            return None;
+        }
+
+        Some(self.updates.iter().filter(|streamed| streamed.id > last_event_id).cloned().collect())
+    }
+}
+
+/// Id of the last event a reconnecting client got, from the `Last-Event-ID` header
+pub struct LastEventId(pub Option<u64>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for LastEventId
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let last_event_id = parts.headers.get("last-event-id")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+
+        Ok(Self(last_event_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MutationKind;
+
+    fn update(game_id: i32) -> LeaderboardUpdate {
+        LeaderboardUpdate::game(MutationKind::Update, game_id)
+    }
+
+    fn ids(subscription: &Subscription) -> Option<Vec<i32>> {
+        subscription.missed.as_ref().map(|missed| missed.iter().map(|streamed| streamed.update.game_id).collect())
+    }
+
+    #[test]
+    fn ids_increase() {
+        let stream = LeaderboardStream::new();
+        let mut rx = stream.subscribe(None).rx;
+        stream.send(update(1));
+        stream.send(update(2));
+
+        let (StreamMessage::Update(first), StreamMessage::Update(second)) = (rx.try_recv().unwrap(), rx.try_recv().unwrap()) else {
+            panic!("Expected two updates");
+        };
+        assert!(first.id < second.id);
+    }
+
+    #[test]
+    fn replays_updates_after_the_last_event() {
+        let stream = LeaderboardStream::new();
+        stream.send(update(1));
+        let last_event_id = stream.log.lock().unwrap().updates[0].id;
+        stream.send(update(2));
+        stream.send(update(3));
+
+        assert_eq!(Some(vec![2, 3]), ids(&stream.subscribe(Some(last_event_id))));
+        assert_eq!(Some(vec![]), ids(&stream.subscribe(None)));
+    }
+
+    #[test]
+    fn resyncs_on_ids_of_another_instance() {
+        let (stream, other_stream) = (LeaderboardStream::new(), LeaderboardStream::new());
+        stream.send(update(1));
+        other_stream.send(update(1));
+        other_stream.send(update(2));
+        let other_id = other_stream.log.lock().unwrap().updates[0].id;
+
+        assert_eq!(None, ids(&stream.subscribe(Some(other_id))));
+    }
+
+    #[test]
+    fn resyncs_when_updates_are_gone() {
+        let stream = LeaderboardStream::new();
+        stream.send(update(0));
+        let first_id = stream.log.lock().unwrap().updates[0].id;
+        for game_id in 1..=REPLAY_CAPACITY as i32 {
+            stream.send(update(game_id));
+        }
+
+        assert_eq!(None, ids(&stream.subscribe(Some(first_id - 1))));
+        assert_eq!(REPLAY_CAPACITY, stream.subscribe(Some(first_id)).missed.unwrap().len());
+        assert_eq!(None, ids(&stream.subscribe(Some(u64::MAX - 1))));
+    }
+
+    #[test]
+    fn resyncs_after_missing_updates() {
+        let stream = LeaderboardStream::new();
+        stream.send(update(1));
+        let last_event_id = stream.log.lock().unwrap().updates[0].id;
+        let mut rx = stream.subscribe(None).rx;
+
+        stream.resync();
+        stream.send(update(2));
+
+        assert!(matches!(rx.try_recv(), Ok(StreamMessage::Resync)));
+        assert_eq!(None, ids(&stream.subscribe(Some(last_event_id))));
+        let resumed_id = stream.log.lock().unwrap().updates[0].id;
+        assert_eq!(Some(vec![]), ids(&stream.subscribe(Some(resumed_id))));
+    }
+}
@@ -0,0 +1,180 @@
+//! Idempotent score submissions. A submission sent with an `Idempotency-Key` header claims the key for its API key,
+//! and its response is kept for [`RETENTION_SECS`], replayed to retries instead of submitting the score again.
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{HeaderName, StatusCode};
+use sha2::{Digest, Sha256};
+use tracing::error;
+
+use super::models::{LeaderboardEntry, LeaderboardEntryNew};
+use super::store::LeaderboardStore;
+use crate::errors::{ApiError, ErrorCode, Problem};
+
+pub const IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+/// Set on replayed responses
+pub const IDEMPOTENT_REPLAYED_HEADER: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// How long responses are kept for replaying, in seconds
+pub const RETENTION_SECS: i64 = 24 * 60 * 60;
+
+/// How long a claim without a response holds the key, in seconds. A request dropped mid-submission never
+/// finishes its claim, so retries take the key over once the lease is up
+pub const IN_FLIGHT_LEASE_SECS: i64 = 60;
+
+const MAX_KEY_LEN: usize = 255;
+
+const IN_FLIGHT: ApiError = ApiError::conflict(
+    ErrorCode::IdempotencyKeyInFlight,
+    "A request with this Idempotency-Key is still being handled, retry later",
+);
+
+/// The `Idempotency-Key` header, 1 to 255 visible ASCII characters, when sent
+#[derive(Debug, PartialEq)]
+pub struct IdempotencyKey(pub Option<String>);
+
+/// Rejects requests with a malformed key
+#[async_trait]
+impl<S> FromRequestParts<S> for IdempotencyKey
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        const MALFORMED: ApiError = ApiError::validation(
+            ErrorCode::InvalidIdempotencyKey,
+            "Idempotency-Key must be 1 to 255 visible ASCII characters",
+        );
+        let Some(key) = parts.headers.get(IDEMPOTENCY_KEY_HEADER) else {
+            return Ok(Self(None));
+        };
+        let key = key.to_str().map_err(|_| MALFORMED)?;
+
+        if is_valid_key(key) { Ok(Self(Some(key.to_string()))) } else { Err(MALFORMED) }
+    }
+}
+
+fn is_valid_key(key: &str) -> bool {
+    (1..=MAX_KEY_LEN).contains(&key.len()) && key.chars().all(|c| c.is_ascii_graphic())
+}
+
+/// Hex SHA-256 of the game and the submitted entry, telling retries apart from other requests reusing the key
+pub fn fingerprint(game_id: i32, request: &LeaderboardEntryNew) -> String {
+    let request = serde_json::to_string(request).expect("Submissions serialize to JSON");
+    hex::encode(Sha256::digest(format!("{game_id}\n{request}").as_bytes()))
+}
+
+/// A kept response, replayed to retries
+#[derive(Debug)]
+pub enum Replay {
+    /// The user's entry, or the better entry conflicting with the submission
+    Entry(StatusCode, LeaderboardEntry),
+    /// A server error, raised when the score may already have been saved
+    Problem(Problem),
+}
+
+/// Claims the key for the submission. Returns the response to replay instead,
+/// when an earlier request with the same fingerprint already claimed it
+pub async fn claim(
+    store: &dyn LeaderboardStore,
+    api_key_id: i32,
+    key: &str,
+    game_id: i32,
+    fingerprint: &str,
+) -> Result<Option<Replay>, ApiError> {
+    let Some(earlier) = store.claim_idempotency_key(api_key_id, key, game_id, fingerprint).await? else {
+        return Ok(None);
+    };
+    if earlier.fingerprint != fingerprint {
+        return Err(ApiError::Unprocessable {
+            code: ErrorCode::IdempotencyKeyReused,
+            detail: "Idempotency-Key was already used with another request".into(),
+            extensions: Default::default(),
+        });
+    }
+    let (status, body) = earlier.response.ok_or(IN_FLIGHT)?;
+    let status = StatusCode::from_u16(status).map_err(|e| ApiError::Internal(Box::new(e)))?;
+    let replay = if status.is_server_error() {
+        Replay::Problem(serde_json::from_str(&body).map_err(|e| ApiError::Internal(Box::new(e)))?)
+    } else {
+        Replay::Entry(status, serde_json::from_str(&body).map_err(|e| ApiError::Internal(Box::new(e)))?)
+    };
+
+    Ok(Some(replay))
+}
+
+/// Keeps the response to the submission for its retries. Client errors are raised before the score reaches a
+/// leaderboard or its signature's nonce is used, at most leaving a rejected submission in the user's history, so they
+/// release the key instead, letting the submission be retried once fixed. Server errors may come after the score was
+/// saved, so they are kept like any other response.
+/// Failures are only logged, the submission is already handled
+pub async fn finish(
+    store: &dyn LeaderboardStore,
+    api_key_id: i32,
+    key: &str,
+    response: Result<(StatusCode, &LeaderboardEntry), &ApiError>,
+) {
+    let finished = match response {
+        Ok((status, entry)) => {
+            let body = serde_json::to_string(entry).expect("Entries serialize to JSON");
+            store.complete_idempotency_key(api_key_id, key, status.as_u16(), &body).await
+        }
+        Err(e) if e.status().is_client_error() => store.release_idempotency_key(api_key_id, key).await,
+        Err(e) => {
+            let body = serde_json::to_string(&e.problem()).expect("Problems serialize to JSON");
+            store.complete_idempotency_key(api_key_id, key, e.status().as_u16(), &body).await
+        }
+    };
+    if let Err(e) = finished {
+        error!("Could not finish the request with Idempotency-Key {key}: {e:?}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::leaderboard::store::MemoryStore;
+
+    #[test]
+    fn keys_are_visible_ascii() {
+        assert!(is_valid_key("retry-1"));
+        assert!(is_valid_key(&"k".repeat(MAX_KEY_LEN)));
+        assert!(!is_valid_key(""));
+        assert!(!is_valid_key(&"k".repeat(MAX_KEY_LEN + 1)));
+        assert!(!is_valid_key("two words"));
+    }
+
+    #[test]
+    fn fingerprints_cover_the_game_and_the_entry() {
+        let entry = |score| LeaderboardEntryNew {
+            score,
+            score_components: None,
+            user_name: "player".into(),
+            user_id: None,
+            free_data: None,
+        };
+
+        assert_eq!(fingerprint(1, &entry(1.0)), fingerprint(1, &entry(1.0)));
+        assert_ne!(fingerprint(1, &entry(1.0)), fingerprint(2, &entry(1.0)));
+        assert_ne!(fingerprint(1, &entry(1.0)), fingerprint(1, &entry(2.0)));
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_kept_and_client_errors_release_the_key() {
+        let store = MemoryStore::new();
+        claim(&store, 1, "retry", 1, "first").await.unwrap();
+        let internal = ApiError::Internal("saved, then failed".into());
+        finish(&store, 1, "retry", Err(&internal)).await;
+
+        let replay = claim(&store, 1, "retry", 1, "first").await.unwrap();
+        assert!(matches!(replay, Some(Replay::Problem(Problem { status: 500, .. }))));
+
+        claim(&store, 1, "fixed", 1, "first").await.unwrap();
+        let invalid = ApiError::validation(ErrorCode::InvalidIdempotencyKey, "rejected");
+        finish(&store, 1, "fixed", Err(&invalid)).await;
+
+        assert!(claim(&store, 1, "fixed", 1, "first").await.unwrap().is_none());
+    }
+}
@@ -1,3 +1,15 @@
+pub mod aggregation;
+pub mod auth;
+pub mod cursor;
+pub mod events;
+pub mod idempotency;
 pub mod models;
+pub mod rate_limit;
+pub mod ranking;
 pub mod routes;
+pub mod signing;
+pub mod store;
 pub mod templates;
+pub mod validation;
+pub mod windows;
+pub mod ws;
@@ -1,8 +1,9 @@
 use crate::models::MutationKind;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::types::Uuid;
+use sqlx::types::{Json, Uuid};
 
-#[derive(Serialize, Deserialize, Debug, PartialEq,
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy,
     sqlx::Type,
     utoipa::ToSchema)]
 #[sqlx(rename_all = "PascalCase")]
@@ -12,13 +13,123 @@ pub enum GameScoreSortMode {
     LesserIsBetter,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq,
+impl GameScoreSortMode {
+    pub fn is_better_or_equal(&self, score: f64, than: f64) -> bool {
+        match self {
+            GameScoreSortMode::HigherIsBetter => score >= than,
+            GameScoreSortMode::LesserIsBetter => score <= than,
+        }
+    }
+
+    /// SQL ordering keyword which places the best scores first
+    pub fn sql_ordering(&self) -> &'static str {
+        match self {
+            GameScoreSortMode::HigherIsBetter => "DESC",
+            GameScoreSortMode::LesserIsBetter => "ASC",
+        }
+    }
+
+    /// SQL ordering keyword which places the worst scores first
+    pub fn sql_reverse_ordering(&self) -> &'static str {
+        match self {
+            GameScoreSortMode::HigherIsBetter => "ASC",
+            GameScoreSortMode::LesserIsBetter => "DESC",
+        }
+    }
+
+    /// SQL comparison operator matching scores which rank after the right hand side
+    pub fn sql_worse_than(&self) -> &'static str {
+        match self {
+            GameScoreSortMode::HigherIsBetter => "<",
+            GameScoreSortMode::LesserIsBetter => ">",
+        }
+    }
+
+    /// SQL comparison operator matching scores which rank before the right hand side
+    pub fn sql_better_than(&self) -> &'static str {
+        match self {
+            GameScoreSortMode::HigherIsBetter => ">",
+            GameScoreSortMode::LesserIsBetter => "<",
+        }
+    }
+}
+
+/// Secondary value of an entry, used to rank entries with equal scores
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone,
+    utoipa::ToSchema)]
+pub struct ScoreComponent {
+    pub name: String,
+    pub sort_mode: GameScoreSortMode,
+}
+
+/// How a new submission is merged into the user's stored entry
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default,
+    sqlx::Type,
+    utoipa::ToSchema)]
+#[sqlx(rename_all = "PascalCase")]
+#[sqlx(type_name = "ScoreAggregation")]
+pub enum ScoreAggregation {
+    /// Keep the best score, as decided by the score sort mode
+    #[default]
+    Best,
+    /// Keep the most recent score
+    Latest,
+    /// Add up every score
+    Sum,
+    /// Average the most recent `aggregation_last_n` scores
+    AverageOfLastN,
+    /// Count submissions, ignoring their scores
+    Count,
+}
+
+/// Span of time covered by a leaderboard. Timed windows reset based on the game's window settings
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default,
+    sqlx::Type,
+    utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "PascalCase")]
+#[sqlx(type_name = "LeaderboardWindow")]
+pub enum LeaderboardWindow {
+    Daily,
+    Weekly,
+    Monthly,
+    #[default]
+    AllTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone,
     sqlx::FromRow,
     utoipa::ToSchema)]
 pub struct Game {
     pub id: i32,
     pub description: String,
     pub score_sort_mode: GameScoreSortMode,
+    /// Tiebreakers for equal scores, in order. Entries still tied are ranked by which reached its score first
+    #[schema(value_type = Vec<ScoreComponent>)]
+    pub score_components: Json<Vec<ScoreComponent>>,
+    pub score_aggregation: ScoreAggregation,
+    /// Number of recent submissions averaged by the AverageOfLastN aggregation
+    pub aggregation_last_n: i32,
+    /// IANA timezone in which daily, weekly and monthly leaderboards reset
+    pub window_timezone: String,
+    /// Local hour of the day, 0-23, at which daily, weekly and monthly leaderboards reset
+    pub window_reset_hour: i32,
+    /// Whether score submissions must be signed with the game's signing secret
+    pub require_signed_submissions: bool,
+    /// Lowest accepted score
+    pub min_score: Option<f64>,
+    /// Highest accepted score
+    pub max_score: Option<f64>,
+    /// Whether scores must be whole numbers
+    pub integer_scores: bool,
+    /// Most a submission may beat the user's current all time score by. With the Sum, AverageOfLastN and Count
+    /// score aggregations, which do not keep one of the user's scores, the user's last submitted score instead
+    pub max_improvement: Option<f64>,
+    /// Least time between two submissions of a user
+    pub min_submission_interval_secs: Option<i32>,
+    /// Never serialized, see the signing secret routes
+    #[serde(skip)]
+    pub signing_secret: String,
 }
 
 #[derive(Serialize, Deserialize, Debug,
@@ -27,18 +138,164 @@ pub struct Game {
 pub struct GameNew {
     pub description: String,
     pub score_sort_mode: Option<GameScoreSortMode>,
+    /// Defaults to no tiebreakers
+    pub score_components: Option<Vec<ScoreComponent>>,
+    /// Defaults to Best
+    pub score_aggregation: Option<ScoreAggregation>,
+    /// Defaults to 5
+    pub aggregation_last_n: Option<i32>,
+    /// Defaults to UTC
+    pub window_timezone: Option<String>,
+    /// Defaults to midnight
+    pub window_reset_hour: Option<i32>,
+    /// Defaults to false
+    pub require_signed_submissions: Option<bool>,
+    /// Defaults to no minimum
+    pub min_score: Option<f64>,
+    /// Defaults to no maximum
+    pub max_score: Option<f64>,
+    /// Defaults to false
+    pub integer_scores: Option<bool>,
+    /// Defaults to no limit
+    pub max_improvement: Option<f64>,
+    /// Defaults to no limit
+    pub min_submission_interval_secs: Option<i32>,
+}
+
+/// Changes to a game. Absent fields are left as they are.
+///
+/// Flipping `score_sort_mode` keeps every stored score as it is: existing entries are re-ranked in the new direction,
+/// and a kept best is only replaced once a new submission beats it in the new direction.
+#[derive(Serialize, Deserialize, Debug,
+    utoipa::ToSchema)]
+pub struct GameUpdate {
+    pub description: Option<String>,
+    pub score_sort_mode: Option<GameScoreSortMode>,
+    pub require_signed_submissions: Option<bool>,
 }
 
+/// Secret a game's clients sign their score submissions with
 #[derive(Serialize, Deserialize, Debug,
+    utoipa::ToSchema)]
+pub struct GameSigningSecret {
+    pub game_id: i32,
+    pub signing_secret: String,
+}
+
+/// A competitive season of a game. Closing a season archives its standings and empties the live leaderboard
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone,
+    sqlx::FromRow,
+    utoipa::ToSchema)]
+pub struct Season {
+    pub id: i32,
+    pub game_id: i32,
+    pub name: String,
+    pub started_at: DateTime<Utc>,
+    /// Absent while the season is open
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+    utoipa::ToSchema)]
+pub struct SeasonNew {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone,
     sqlx::FromRow,
     utoipa::ToSchema)]
 pub struct LeaderboardEntry {
     pub id: i32,
     pub score: f64,
+    /// Values of the game's score components, in the order the game declares them
+    pub score_components: Vec<f64>,
     pub game_id: i32,
     pub user_name: String,
     pub user_id: Uuid,
     pub free_data: String,
+    pub created_at: DateTime<Utc>,
+    /// Last time the score was improved
+    pub updated_at: DateTime<Utc>,
+    /// Season the entry was submitted during, if any
+    pub season_id: Option<i32>,
+    /// Set while the entry is hidden from the leaderboard by a moderator
+    pub invalidated_at: Option<DateTime<Utc>>,
+    pub invalidation_reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+    utoipa::ToSchema)]
+pub struct EntryInvalidation {
+    pub reason: Option<String>,
+}
+
+/// Bars a user from submitting scores and hides their entries, on one game or on every game
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone,
+    sqlx::FromRow,
+    utoipa::ToSchema)]
+pub struct UserBan {
+    pub id: i32,
+    pub user_id: Uuid,
+    /// Absent for bans from every game
+    pub game_id: Option<i32>,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+    utoipa::ToSchema)]
+pub struct UserBanNew {
+    pub user_id: Uuid,
+    /// Defaults to banning from every game
+    pub game_id: Option<i32>,
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default,
+    utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserBansQuery {
+    /// Only list bans of this user
+    pub user_id: Option<Uuid>,
+}
+
+/// A leaderboard entry along with where it stands on its game's leaderboard
+#[derive(Serialize, Deserialize, Debug,
+    utoipa::ToSchema)]
+pub struct LeaderboardEntryStanding {
+    #[serde(flatten)]
+    pub entry: LeaderboardEntry,
+    /// 1-based position on the leaderboard. Equal scores are ranked by the score components,
+    /// then by updated_at, when the entry reached its score, then by entry id
+    pub rank: i64,
+    /// Number of entries on the leaderboard
+    pub total: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+    utoipa::ToSchema)]
+pub struct RankedLeaderboardEntry {
+    #[serde(flatten)]
+    pub entry: LeaderboardEntry,
+    /// 1-based position on the leaderboard
+    pub rank: i64,
+}
+
+#[derive(Deserialize, Debug, Default,
+    utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NeighborhoodQuery {
+    /// Number of entries to include on each side of the user's entry. Defaults to 5, at most 50
+    pub radius: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+    utoipa::ToSchema)]
+pub struct LeaderboardNeighborhood {
+    /// The user's entry surrounded by up to `radius` entries on each side, best first
+    pub entries: Vec<RankedLeaderboardEntry>,
+    /// Number of entries on the leaderboard
+    pub total: i64,
 }
 
 #[derive(Serialize, Deserialize, Debug,
@@ -46,15 +303,214 @@ pub struct LeaderboardEntry {
     utoipa::ToSchema)]
 pub struct LeaderboardEntryNew {
     pub score: f64,
+    /// One value per score component of the game, in the order the game declares them
+    pub score_components: Option<Vec<f64>>,
     pub user_name: String,
     /// When not provided, will be assigned a random unique id
     pub user_id: Option<Uuid>,
     pub free_data: Option<String>,
 }
 
+#[derive(Deserialize, Debug, Default,
+    utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LeaderboardEntriesQuery {
+    /// Maximum number of entries to return. Defaults to 10, at most 100
+    pub limit: Option<i64>,
+    /// Number of entries to skip, counted from the cursor position when one is provided
+    pub offset: Option<i64>,
+    /// Opaque `next_cursor` returned by a previous page
+    pub cursor: Option<String>,
+    /// Time window of the leaderboard. Defaults to all time
+    pub window: Option<LeaderboardWindow>,
+    /// Season to read the final standings of, instead of the live leaderboard. Takes precedence over `window`
+    pub season: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+    utoipa::ToSchema)]
+pub struct LeaderboardEntriesPage {
+    pub entries: Vec<LeaderboardEntry>,
+    /// Pass as `cursor` to fetch the following page. Absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// A single score submission, kept whether or not it improved any of the user's entries, or was rejected
+#[derive(Serialize, Deserialize, Debug, Clone,
+    sqlx::FromRow,
+    utoipa::ToSchema)]
+pub struct ScoreSubmission {
+    pub id: i64,
+    pub game_id: i32,
+    pub user_id: Uuid,
+    pub user_name: String,
+    pub score: f64,
+    pub score_components: Vec<f64>,
+    pub free_data: String,
+    /// Whether the submission changed at least one of the user's entries
+    pub accepted: bool,
+    /// The rule the score broke, when it was rejected by the game's score validation rules
+    pub rejected_by: Option<ScoreValidationRule>,
+    /// Season open when the score was submitted, if any
+    pub season_id: Option<i32>,
+    pub submitted_at: DateTime<Utc>,
+}
+
+/// Aggregates over every submission of a user to a game
+#[derive(Serialize, Deserialize, Debug,
+    sqlx::FromRow,
+    utoipa::ToSchema)]
+pub struct SubmissionStats {
+    pub count: i64,
+    pub accepted_count: i64,
+    /// Absent when there are no submissions
+    pub min_score: Option<f64>,
+    /// Absent when there are no submissions
+    pub max_score: Option<f64>,
+    /// Absent when there are no submissions
+    pub avg_score: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Default,
+    utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SubmissionHistoryQuery {
+    /// Maximum number of submissions to return. Defaults to 10, at most 100
+    pub limit: Option<i64>,
+    /// Number of submissions to skip
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+    utoipa::ToSchema)]
+pub struct SubmissionHistory {
+    /// Newest submissions first
+    pub submissions: Vec<ScoreSubmission>,
+    pub stats: SubmissionStats,
+}
+
+/// Score validation rule of a game
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy,
+    sqlx::Type,
+    utoipa::ToSchema)]
+#[sqlx(rename_all = "PascalCase")]
+#[sqlx(type_name = "ScoreValidationRule")]
+pub enum ScoreValidationRule {
+    /// Scores and score components must be finite numbers
+    Finite,
+    MinScore,
+    MaxScore,
+    IntegerScores,
+    MaxImprovement,
+    MinSubmissionInterval,
+}
+
+/// Why a submitted score was rejected
+#[derive(Serialize, Deserialize, Debug, PartialEq,
+    utoipa::ToSchema)]
+pub struct ScoreValidationFailure {
+    pub rule: ScoreValidationRule,
+    /// The game's limit for the rule, if it has one
+    pub limit: Option<f64>,
+    pub message: String,
+}
+
+/// What an API key may do. Admins may do everything, game owners may manage and moderate their game,
+/// and submitters may only submit scores
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy,
+    sqlx::Type,
+    utoipa::ToSchema)]
+#[sqlx(rename_all = "PascalCase")]
+#[sqlx(type_name = "ApiKeyRole")]
+pub enum ApiKeyRole {
+    Admin,
+    GameOwner,
+    Submitter,
+}
+
+/// A bearer key for the mutating routes. The key itself is only shown once, when it is minted
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone,
+    sqlx::FromRow,
+    utoipa::ToSchema)]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub role: ApiKeyRole,
+    /// Game the key is limited to, possibly deleted since. Game owner keys always have one, submitter keys without one
+    /// may submit to every game
+    pub game_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    /// Revoked keys are rejected
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+    utoipa::ToSchema)]
+pub struct ApiKeyNew {
+    pub name: String,
+    pub role: ApiKeyRole,
+    /// Required for game owners, not allowed for admins
+    pub game_id: Option<i32>,
+}
+
+/// A newly minted API key
+#[derive(Serialize, Deserialize, Debug,
+    utoipa::ToSchema)]
+pub struct ApiKeyCreated {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    /// Send as `Authorization: Bearer <key>`. It can not be retrieved again
+    pub key: String,
+}
+
+/// Event of the leaderboard streams
 #[derive(Serialize, Deserialize, Debug, Clone,
     utoipa::ToSchema)]
 pub struct LeaderboardUpdate {
     pub mutation_kind: MutationKind,
+    pub target: LeaderboardUpdateTarget,
+    /// Id of the game or of the leaderboard entry
     pub id: i32,
+    /// Game the game or entry belongs to
+    pub game_id: i32,
+    /// The all time entry as it is after the update, for entry updates. Left out when too big to publish
+    pub entry: Option<LeaderboardEntry>,
+    /// 1-based all time rank of the entry after the update, absent when it is not on the leaderboard
+    pub rank: Option<i64>,
+    /// 1-based all time rank of the entry before the update, absent when it was not on the leaderboard
+    pub previous_rank: Option<i64>,
+}
+
+impl LeaderboardUpdate {
+    pub fn game(mutation_kind: MutationKind, game_id: i32) -> Self {
+        Self {
+            mutation_kind,
+            target: LeaderboardUpdateTarget::Game,
+            id: game_id,
+            game_id,
+            entry: None,
+            rank: None,
+            previous_rank: None,
+        }
+    }
+
+    pub fn entry(mutation_kind: MutationKind, entry: LeaderboardEntry, previous_rank: Option<i64>, rank: Option<i64>) -> Self {
+        Self {
+            mutation_kind,
+            target: LeaderboardUpdateTarget::Entry,
+            id: entry.id,
+            game_id: entry.game_id,
+            entry: Some(entry),
+            rank,
+            previous_rank,
+        }
+    }
+}
+
+/// What a [`LeaderboardUpdate`] is about
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy,
+    utoipa::ToSchema)]
+pub enum LeaderboardUpdateTarget {
+    Game,
+    Entry,
 }
@@ -0,0 +1,161 @@
+use std::cmp::Ordering;
+
+use super::models::{Game, GameScoreSortMode, LeaderboardEntry};
+
+/// Lexicographic order of a game's entries: by score, then by each of the game's score components in turn.
+/// Any remaining ties are broken by when each entry reached its current score, then by entry id,
+/// so the entry which reached the score first ranks first.
+///
+/// Keys are the score followed by the score components, in the order the game declares them.
+pub struct Ranking {
+    sort_modes: Vec<GameScoreSortMode>,
+}
+
+impl Ranking {
+    pub fn for_game(game: &Game) -> Self {
+        let sort_modes = std::iter::once(game.score_sort_mode)
+            .chain(game.score_components.iter().map(|component| component.sort_mode))
+            .collect();
+        Self { sort_modes }
+    }
+
+    /// Number of score components an entry needs, after its score
+    pub fn component_count(&self) -> usize {
+        self.sort_modes.len() - 1
+    }
+
+    /// [`Ordering::Less`] when keys `a` rank before keys `b`
+    pub fn compare(&self, a: &[f64], b: &[f64]) -> Ordering {
+        self.sort_modes.iter()
+            .zip(a.iter().zip(b))
+            .map(|(sort_mode, (a, b))| match sort_mode {
+                GameScoreSortMode::HigherIsBetter => b.total_cmp(a),
+                GameScoreSortMode::LesserIsBetter => a.total_cmp(b),
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// SQL `ORDER BY` list placing the best entries first
+    pub fn order_by_sql(&self) -> String {
+        self.key_columns()
+            .map(|(column, sort_mode)| format!("{column} {}", sort_mode.sql_ordering()))
+            .chain(["updated_at ASC".to_string(), "id ASC".to_string()])
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// SQL `ORDER BY` list placing the worst entries first
+    pub fn reverse_order_by_sql(&self) -> String {
+        self.key_columns()
+            .map(|(column, sort_mode)| format!("{column} {}", sort_mode.sql_reverse_ordering()))
+            .chain(["updated_at DESC".to_string(), "id DESC".to_string()])
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// SQL condition matching entries which rank before a reference entry.
+    /// The reference score, score components, updated_at and id are bound at the given parameter numbers
+    pub fn ranks_before_sql(&self, score_param: usize, components_param: usize, updated_at_param: usize, id_param: usize) -> String {
+        self.compare_sql([score_param, components_param, updated_at_param, id_param], GameScoreSortMode::sql_better_than, "<")
+    }
+
+    /// SQL condition matching entries which rank after a reference entry.
+    /// The reference score, score components, updated_at and id are bound at the given parameter numbers
+    pub fn ranks_after_sql(&self, score_param: usize, components_param: usize, updated_at_param: usize, id_param: usize) -> String {
+        self.compare_sql([score_param, components_param, updated_at_param, id_param], GameScoreSortMode::sql_worse_than, ">")
+    }
+
+    fn compare_sql(
+        &self,
+        [score_param, components_param, updated_at_param, id_param]: [usize; 4],
+        operator: fn(&GameScoreSortMode) -> &'static str,
+        tie_operator: &str,
+    ) -> String {
+        let references = std::iter::once(format!("${score_param}"))
+            .chain((1..self.sort_modes.len()).map(|i| format!("(${components_param}::FLOAT[])[{i}]")))
+            .collect::<Vec<_>>();
+        let tie = format!(
+            "(updated_at {tie_operator} ${updated_at_param} OR (updated_at = ${updated_at_param} AND id {tie_operator} ${id_param}))");
+
+        self.key_columns()
+            .zip(references)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .fold(tie, |tie, ((column, sort_mode), reference)| {
+                format!("({column} {op} {reference} OR ({column} = {reference} AND {tie}))", op = operator(&sort_mode))
+            })
+    }
+
+    fn key_columns(&self) -> impl Iterator<Item = (String, GameScoreSortMode)> + '_ {
+        self.sort_modes.iter().enumerate().map(|(i, sort_mode)| {
+            let column = match i {
+                0 => "score".to_string(),
+                i => format!("score_components[{i}]"),
+            };
+            (column, *sort_mode)
+        })
+    }
+}
+
+impl LeaderboardEntry {
+    /// The score followed by the score components, as ranked by [`Ranking`]
+    pub fn ranking_keys(&self) -> Vec<f64> {
+        std::iter::once(self.score).chain(self.score_components.iter().copied()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranking(sort_modes: &[GameScoreSortMode]) -> Ranking {
+        Ranking { sort_modes: sort_modes.to_vec() }
+    }
+
+    const HIGHER: GameScoreSortMode = GameScoreSortMode::HigherIsBetter;
+    const LESSER: GameScoreSortMode = GameScoreSortMode::LesserIsBetter;
+
+    #[test]
+    fn compares_by_score_first() {
+        let ranking = ranking(&[HIGHER, LESSER]);
+        assert_eq!(Ordering::Less, ranking.compare(&[10.0, 99.0], &[5.0, 1.0]));
+        assert_eq!(Ordering::Greater, ranking.compare(&[5.0, 1.0], &[10.0, 99.0]));
+    }
+
+    #[test]
+    fn compares_ties_by_components() {
+        let ranking = ranking(&[HIGHER, LESSER, HIGHER]);
+        assert_eq!(Ordering::Less, ranking.compare(&[10.0, 3.0, 0.0], &[10.0, 4.0, 9.0]));
+        assert_eq!(Ordering::Less, ranking.compare(&[10.0, 3.0, 9.0], &[10.0, 3.0, 0.0]));
+        assert_eq!(Ordering::Equal, ranking.compare(&[10.0, 3.0, 9.0], &[10.0, 3.0, 9.0]));
+    }
+
+    #[test]
+    fn orders_by_every_key_then_updated_at_then_id() {
+        let ranking = ranking(&[HIGHER, LESSER]);
+        assert_eq!("score DESC, score_components[1] ASC, updated_at ASC, id ASC", ranking.order_by_sql());
+        assert_eq!("score ASC, score_components[1] DESC, updated_at DESC, id DESC", ranking.reverse_order_by_sql());
+    }
+
+    #[test]
+    fn score_only_condition() {
+        let ranking = ranking(&[HIGHER]);
+        assert_eq!(
+            "(score > $2 OR (score = $2 AND (updated_at < $4 OR (updated_at = $4 AND id < $5))))",
+            ranking.ranks_before_sql(2, 3, 4, 5),
+        );
+    }
+
+    #[test]
+    fn nests_component_conditions() {
+        let ranking = ranking(&[HIGHER, LESSER]);
+        assert_eq!(
+            "(score < $5 OR (score = $5 AND \
+            (score_components[1] > ($6::FLOAT[])[1] OR (score_components[1] = ($6::FLOAT[])[1] AND \
+            (updated_at > $7 OR (updated_at = $7 AND id > $8))))))",
+            ranking.ranks_after_sql(5, 6, 7, 8),
+        );
+    }
+}
@@ -0,0 +1,354 @@
+//! Throttles game creations and score submissions with token buckets, kept in the process.
+//!
+//! Each route has budgets per client IP, per API key and, for submissions, per `user_id`. A request takes a token
+//! from the bucket of each of its keys, and is refused with a 429 when any of them is empty. Buckets refill
+//! continuously, so a budget of `10/60` allows bursts of 10 requests and one more every second after that.
+//! Instances do not share their buckets, so behind a load balancer each budget applies per instance.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::async_trait;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+use sqlx::types::Uuid;
+
+use super::models::ApiKey;
+use crate::app_state::AppState;
+use crate::errors::ApiError;
+
+/// Buckets are purged of those full again once there are this many, so idle clients do not pile up
+const PURGE_THRESHOLD: usize = 10_000;
+
+/// Up to `burst` requests at once, refilled at `per_minute` requests a minute
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl Budget {
+    /// Parses `<burst>/<per_minute>`, or `off` for no budget
+    fn parse(value: &str) -> Option<Option<Self>> {
+        if value.trim() == "off" {
+            return Some(None);
+        }
+        let (burst, per_minute) = value.split_once('/')?;
+        let budget = Budget { burst: burst.trim().parse().ok()?, per_minute: per_minute.trim().parse().ok()? };
+        (budget.burst > 0 && budget.per_minute > 0).then_some(Some(budget))
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.per_minute as f64 / 60.0
+    }
+}
+
+/// Budgets of one route, None for no limit by that key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RouteBudgets {
+    pub per_ip: Option<Budget>,
+    pub per_api_key: Option<Budget>,
+    pub per_user: Option<Budget>,
+}
+
+impl RouteBudgets {
+    pub const UNLIMITED: Self = Self { per_ip: None, per_api_key: None, per_user: None };
+
+    /// Seconds for an empty bucket of any of the budgets to be full again
+    fn longest_refill_secs(&self) -> f64 {
+        [self.per_ip, self.per_api_key, self.per_user].into_iter()
+            .flatten()
+            .map(|budget| budget.burst as f64 / budget.refill_per_sec())
+            .fold(0.0, f64::max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimits {
+    /// `POST /leaderboard/games`
+    pub create_game: RouteBudgets,
+    /// `POST /leaderboard/games/{game_id}/entries`, and submissions over WebSocket sessions
+    pub create_game_entry: RouteBudgets,
+    /// Takes the client's IP from the last `X-Forwarded-For` address, the one a reverse proxy saw, rather than
+    /// from the connection. Only set it behind a proxy, clients can send the header themselves
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for RateLimits {
+    /// Generous enough for a game server submitting for all of its players through one key
+    fn default() -> Self {
+        Self {
+            create_game: RouteBudgets {
+                per_ip: Some(Budget { burst: 10, per_minute: 10 }),
+                per_api_key: Some(Budget { burst: 20, per_minute: 20 }),
+                per_user: None,
+            },
+            create_game_entry: RouteBudgets {
+                per_ip: Some(Budget { burst: 120, per_minute: 600 }),
+                per_api_key: Some(Budget { burst: 600, per_minute: 6000 }),
+                per_user: Some(Budget { burst: 10, per_minute: 60 }),
+            },
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+impl RateLimits {
+    pub const UNLIMITED: Self = Self {
+        create_game: RouteBudgets::UNLIMITED,
+        create_game_entry: RouteBudgets::UNLIMITED,
+        trust_forwarded_for: false,
+    };
+
+    fn budgets(&self, route: Route) -> RouteBudgets {
+        match route {
+            Route::CreateGame => self.create_game,
+            Route::CreateGameEntry => self.create_game_entry,
+        }
+    }
+
+    /// The defaults, overridden by the variables which are set:
+    /// - `RATE_LIMIT_GAMES_PER_IP` and `RATE_LIMIT_GAMES_PER_API_KEY`
+    /// - `RATE_LIMIT_ENTRIES_PER_IP`, `RATE_LIMIT_ENTRIES_PER_API_KEY` and `RATE_LIMIT_ENTRIES_PER_USER`
+    ///
+    /// as `<burst>/<per_minute>` or `off`, and `TRUST_FORWARDED_FOR` as `true` or `false`
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let budget = |name: &str, default: Option<Budget>| match var(name) {
+            None => default,
+            Some(value) => Budget::parse(&value)
+                .unwrap_or_else(|| panic!("{name} should be <burst>/<per_minute> or off")),
+        };
+        let defaults = Self::default();
+
+        Self {
+            create_game: RouteBudgets {
+                per_ip: budget("RATE_LIMIT_GAMES_PER_IP", defaults.create_game.per_ip),
+                per_api_key: budget("RATE_LIMIT_GAMES_PER_API_KEY", defaults.create_game.per_api_key),
+                per_user: None,
+            },
+            create_game_entry: RouteBudgets {
+                per_ip: budget("RATE_LIMIT_ENTRIES_PER_IP", defaults.create_game_entry.per_ip),
+                per_api_key: budget("RATE_LIMIT_ENTRIES_PER_API_KEY", defaults.create_game_entry.per_api_key),
+                per_user: budget("RATE_LIMIT_ENTRIES_PER_USER", defaults.create_game_entry.per_user),
+            },
+            trust_forwarded_for: var("TRUST_FORWARDED_FOR")
+                .map(|value| value.parse().expect("TRUST_FORWARDED_FOR should be true or false"))
+                .unwrap_or(defaults.trust_forwarded_for),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Route {
+    CreateGame,
+    CreateGameEntry,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BucketKey {
+    Ip(IpAddr),
+    ApiKey(i32),
+    User(Uuid),
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, budget: &Budget, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * budget.refill_per_sec()).min(budget.burst as f64);
+        self.refilled_at = now;
+    }
+
+    fn retry_after(&self, budget: &Budget) -> Duration {
+        Duration::from_secs_f64((1.0 - self.tokens).max(0.0) / budget.refill_per_sec())
+    }
+}
+
+/// Token buckets of every route and key, shared by the clones
+#[derive(Clone)]
+pub struct RateLimiter {
+    limits: RateLimits,
+    buckets: Arc<Mutex<HashMap<(Route, BucketKey), Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        Self { limits, buckets: Default::default() }
+    }
+
+    pub fn limits(&self) -> &RateLimits {
+        &self.limits
+    }
+
+    /// Takes a token from the buckets of the client's IP, API key and user, or none when any of them is empty
+    pub fn check(&self, route: Route, ip: Option<IpAddr>, api_key: &ApiKey, user_id: Option<Uuid>) -> Result<(), ApiError> {
+        self.check_at(route, ip, api_key, user_id, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        route: Route,
+        ip: Option<IpAddr>,
+        api_key: &ApiKey,
+        user_id: Option<Uuid>,
+        now: Instant,
+    ) -> Result<(), ApiError> {
+        let budgets = self.limits.budgets(route);
+        let keys = [
+            ip.map(BucketKey::Ip).zip(budgets.per_ip),
+            Some(BucketKey::ApiKey(api_key.id)).zip(budgets.per_api_key),
+            user_id.map(BucketKey::User).zip(budgets.per_user),
+        ];
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= PURGE_THRESHOLD {
+            buckets.retain(|(route, _), bucket| {
+                let idle = now.saturating_duration_since(bucket.refilled_at).as_secs_f64();
+                idle < self.limits.budgets(*route).longest_refill_secs()
+            });
+        }
+
+        // Every bucket is checked before any is taken from, so a refused request costs nothing
+        let mut refused: Option<(Duration, u32)> = None;
+        for (key, budget) in keys.iter().flatten() {
+            let bucket = buckets.entry((route, *key))
+                .or_insert(Bucket { tokens: budget.burst as f64, refilled_at: now });
+            bucket.refill(budget, now);
+            if bucket.tokens < 1.0 {
+                let retry_after = bucket.retry_after(budget);
+                if refused.is_none_or(|(longest, _)| retry_after > longest) {
+                    refused = Some((retry_after, budget.burst));
+                }
+            }
+        }
+        if let Some((retry_after, limit)) = refused {
+            return Err(ApiError::RateLimited { retry_after_secs: retry_after.as_secs_f64().ceil() as u64, limit });
+        }
+        for (key, _) in keys.iter().flatten() {
+            if let Some(bucket) = buckets.get_mut(&(route, *key)) {
+                bucket.tokens -= 1.0;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// IP of the client, from the connection or from `X-Forwarded-For` when trusted. None when neither tells it,
+/// like when the server was not started with connect info
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        if state.rate_limiter.limits().trust_forwarded_for {
+            let forwarded = parts.headers.get_all("x-forwarded-for").iter()
+                .filter_map(|header| header.to_str().ok())
+                .flat_map(|header| header.split(','))
+                .last()
+                .and_then(|address| address.trim().parse().ok());
+            return Ok(Self(forwarded));
+        }
+
+        let connected = parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(address)| address.ip());
+        Ok(Self(connected))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use super::super::models::ApiKeyRole;
+
+    fn api_key(id: i32) -> ApiKey {
+        ApiKey { id, name: "test".into(), role: ApiKeyRole::Submitter, game_id: None, created_at: Utc::now(), revoked_at: None }
+    }
+
+    fn limiter(per_user: Budget) -> RateLimiter {
+        RateLimiter::new(RateLimits {
+            create_game_entry: RouteBudgets { per_user: Some(per_user), ..RouteBudgets::UNLIMITED },
+            ..RateLimits::UNLIMITED
+        })
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let limiter = limiter(Budget { burst: 2, per_minute: 6 });
+        let (key, user) = (api_key(1), Some(Uuid::new_v4()));
+        let start = Instant::now();
+
+        assert!(limiter.check_at(Route::CreateGameEntry, None, &key, user, start).is_ok());
+        assert!(limiter.check_at(Route::CreateGameEntry, None, &key, user, start).is_ok());
+        let Err(ApiError::RateLimited { retry_after_secs, limit }) =
+            limiter.check_at(Route::CreateGameEntry, None, &key, user, start) else {
+            panic!("The third request should be limited");
+        };
+        assert_eq!((10, 2), (retry_after_secs, limit));
+
+        assert!(limiter.check_at(Route::CreateGameEntry, None, &key, user, start + Duration::from_secs(9)).is_err());
+        assert!(limiter.check_at(Route::CreateGameEntry, None, &key, user, start + Duration::from_secs(10)).is_ok());
+    }
+
+    #[test]
+    fn keeps_buckets_per_key_and_route() {
+        let limiter = limiter(Budget { burst: 1, per_minute: 1 });
+        let key = api_key(1);
+        let (user, other_user) = (Some(Uuid::new_v4()), Some(Uuid::new_v4()));
+        let now = Instant::now();
+
+        assert!(limiter.check_at(Route::CreateGameEntry, None, &key, user, now).is_ok());
+        assert!(limiter.check_at(Route::CreateGameEntry, None, &key, user, now).is_err());
+        assert!(limiter.check_at(Route::CreateGameEntry, None, &key, other_user, now).is_ok());
+        assert!(limiter.check_at(Route::CreateGame, None, &key, user, now).is_ok());
+    }
+
+    #[test]
+    fn refused_requests_take_no_tokens() {
+        let limiter = RateLimiter::new(RateLimits {
+            create_game_entry: RouteBudgets {
+                per_api_key: Some(Budget { burst: 2, per_minute: 1 }),
+                per_user: Some(Budget { burst: 1, per_minute: 1 }),
+                per_ip: None,
+            },
+            ..RateLimits::UNLIMITED
+        });
+        let key = api_key(1);
+        let user = Some(Uuid::new_v4());
+        let now = Instant::now();
+
+        assert!(limiter.check_at(Route::CreateGameEntry, None, &key, user, now).is_ok());
+        assert!(limiter.check_at(Route::CreateGameEntry, None, &key, user, now).is_err());
+        // The key still has the token the refused request did not take
+        assert!(limiter.check_at(Route::CreateGameEntry, None, &key, Some(Uuid::new_v4()), now).is_ok());
+    }
+
+    #[test]
+    fn parses_budgets() {
+        assert_eq!(Some(Some(Budget { burst: 10, per_minute: 60 })), Budget::parse("10/60"));
+        assert_eq!(Some(None), Budget::parse("off"));
+        assert_eq!(None, Budget::parse("10"));
+        assert_eq!(None, Budget::parse("0/60"));
+
+        let limits = RateLimits::from_vars(|name| match name {
+            "RATE_LIMIT_ENTRIES_PER_USER" => Some("off".into()),
+            "RATE_LIMIT_GAMES_PER_IP" => Some("1/2".into()),
+            "TRUST_FORWARDED_FOR" => Some("true".into()),
+            _ => None,
+        });
+        assert_eq!(None, limits.create_game_entry.per_user);
+        assert_eq!(Some(Budget { burst: 1, per_minute: 2 }), limits.create_game.per_ip);
+        assert_eq!(RateLimits::default().create_game_entry.per_ip, limits.create_game_entry.per_ip);
+        assert!(limits.trust_forwarded_for);
+    }
+}
@@ -2,29 +2,66 @@ use std::convert::Infallible;
 use std::time::Duration;
 
 use axum::{
-    extract::{Path, State},
-    response::{sse::Event, IntoResponse, Sse},
+    extract::{Path, Query, State},
+    response::{sse::Event, IntoResponse, Response, Sse},
     Extension, Json,
 };
 use axum::http::StatusCode;
-use log::error;
+use tracing::warn;
 use serde_json::json;
-use sqlx::PgPool;
-use sqlx::query::QueryAs;
+use sqlx::types::chrono::{DateTime, Utc};
 use sqlx::types::Uuid;
-use tokio::sync::broadcast::Sender;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::{Stream, StreamExt as _};
 
+use super::auth;
+use super::cursor::EntryCursor;
+pub use super::events::LeaderboardStream;
+use super::events::{LastEventId, StreamMessage, StreamedUpdate};
+use super::idempotency::{self, IdempotencyKey, Replay, IDEMPOTENT_REPLAYED_HEADER};
 use super::models::*;
+use super::rate_limit::{ClientIp, Route};
+use super::ranking::Ranking;
+use super::signing::{self, SubmissionSignature};
+use super::store::{Board, EntryValues, LeaderboardStore, ScoreUpdate};
 use super::templates;
+use super::windows::parse_timezone;
 use crate::hetero_req_resp::{AcceptType, JsonOrForm};
+use crate::metrics::SubmissionOutcome;
 use crate::models::MutationKind;
-use crate::{errors::ApiError, app_state::AppState};
+use crate::{errors::{ApiError, ErrorCode}, app_state::AppState};
 
-pub type LeaderboardStream = Sender<LeaderboardUpdate>;
+const INVALID_CURSOR: ApiError = ApiError::validation(ErrorCode::InvalidCursor, "Invalid cursor");
+const INVALID_TIMEZONE: ApiError = ApiError::validation(ErrorCode::InvalidTimezone, "Invalid window_timezone");
+const INVALID_RESET_HOUR: ApiError = ApiError::validation(ErrorCode::InvalidResetHour, "Invalid window_reset_hour");
+const INVALID_AGGREGATION_LAST_N: ApiError =
+    ApiError::validation(ErrorCode::InvalidAggregationLastN, "Invalid aggregation_last_n");
+const INVALID_SCORE_COMPONENTS: ApiError =
+    ApiError::validation(ErrorCode::InvalidScoreComponents, "Expected one value per score component of the game");
+const INVALID_SCORE_RANGE: ApiError =
+    ApiError::validation(ErrorCode::InvalidScoreRange, "min_score can not be above max_score");
+const INVALID_MAX_IMPROVEMENT: ApiError = ApiError::validation(ErrorCode::InvalidMaxImprovement, "Invalid max_improvement");
+const INVALID_SUBMISSION_INTERVAL: ApiError =
+    ApiError::validation(ErrorCode::InvalidSubmissionInterval, "Invalid min_submission_interval_secs");
 
-const NOT_FOUND_RESP: (StatusCode, &str) = (StatusCode::NOT_FOUND, "Not Found");
+const FORBIDDEN: ApiError = ApiError::forbidden(ErrorCode::Forbidden, "API key is not allowed to do this");
+const INVALID_API_KEY_GAME: ApiError =
+    ApiError::validation(ErrorCode::InvalidApiKeyGame, "Game owner keys need a game_id, admin keys can not have one");
+const MISSING_SIGNATURE: ApiError =
+    ApiError::unauthorized(ErrorCode::MissingSignature, "This game requires signed submissions");
+const STALE_SIGNATURE: ApiError = ApiError::unauthorized(ErrorCode::StaleSignature, "Submission signature expired");
+const INVALID_SIGNATURE: ApiError = ApiError::unauthorized(ErrorCode::InvalidSignature, "Invalid submission signature");
+const REPLAYED_NONCE: ApiError = ApiError::unauthorized(ErrorCode::ReplayedNonce, "Submission nonce was already used");
+const BANNED: ApiError = ApiError::forbidden(ErrorCode::UserBanned, "User is banned from this game");
+const ENTRY_INVALIDATED: ApiError =
+    ApiError::forbidden(ErrorCode::EntryInvalidated, "User's entry on this game was invalidated by a moderator");
+
+const DEFAULT_PAGE_SIZE: i64 = 10;
+const MAX_PAGE_SIZE: i64 = 100;
+
+const DEFAULT_NEIGHBORHOOD_RADIUS: i64 = 5;
+const MAX_NEIGHBORHOOD_RADIUS: i64 = 50;
 
 /// Get the home page
 ///
@@ -51,9 +88,7 @@ pub async fn get_games(
     accept_type: AcceptType,
     State(state): State<AppState>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let games = sqlx::query_as::<_, Game>("SELECT * FROM games")
-        .fetch_all(&state.db)
-        .await?;
+    let games = state.store.get_games().await?;
 
     Ok(match accept_type {
         AcceptType::HTMX => templates::Games { games }.into_response(),
@@ -63,27 +98,73 @@ pub async fn get_games(
 
 /// Create Game
 ///
+/// Rate limited per client IP and API key.
 /// Responds with the created game
 #[utoipa::path(
     post,
     path = "/leaderboard/games",
     request_body = GameNew,
+    security(("api_key" = [])),
     responses(
-        (status = 200, description = "New Game", body = Game)
+        (status = 200, description = "New Game", body = Game),
+        (status = 400, description = "Invalid window, aggregation or score validation settings", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Too many games created by the client's IP or API key, retry after the Retry-After header's seconds", body = Problem, content_type = "application/problem+json"),
     )
 )]
 pub async fn create_game(
     accept_type: AcceptType,
     State(state): State<AppState>,
+    api_key: ApiKey,
+    ClientIp(ip): ClientIp,
     JsonOrForm(request): JsonOrForm<GameNew>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let game = sqlx::query_as::<_, Game>(
-        "INSERT INTO games (description, score_sort_mode) VALUES ($1, $2) RETURNING id, description, score_sort_mode",
-    )
-        .bind(request.description)
-        .bind(request.score_sort_mode.unwrap_or(GameScoreSortMode::HigherIsBetter))
-    .fetch_one(&state.db)
-    .await?;
+    state.rate_limiter.check(Route::CreateGame, ip, &api_key, None)?;
+    if !api_key.is_admin() {
+        return Err(FORBIDDEN);
+    }
+    let window_timezone = request.window_timezone.unwrap_or("UTC".into());
+    if parse_timezone(&window_timezone).is_none() {
+        return Err(INVALID_TIMEZONE);
+    }
+    let window_reset_hour = request.window_reset_hour.unwrap_or(0);
+    if !(0..24).contains(&window_reset_hour) {
+        return Err(INVALID_RESET_HOUR);
+    }
+    let aggregation_last_n = request.aggregation_last_n.unwrap_or(5);
+    if aggregation_last_n < 1 {
+        return Err(INVALID_AGGREGATION_LAST_N);
+    }
+    if let (Some(min_score), Some(max_score)) = (request.min_score, request.max_score) {
+        if min_score > max_score {
+            return Err(INVALID_SCORE_RANGE);
+        }
+    }
+    if request.max_improvement.is_some_and(|max_improvement| max_improvement < 0.0) {
+        return Err(INVALID_MAX_IMPROVEMENT);
+    }
+    if request.min_submission_interval_secs.is_some_and(|interval| interval < 0) {
+        return Err(INVALID_SUBMISSION_INTERVAL);
+    }
+
+    let game = state.store.create_game(Game {
+        id: 0,
+        description: request.description,
+        score_sort_mode: request.score_sort_mode.unwrap_or(GameScoreSortMode::HigherIsBetter),
+        score_components: sqlx::types::Json(request.score_components.unwrap_or_default()),
+        score_aggregation: request.score_aggregation.unwrap_or_default(),
+        aggregation_last_n,
+        window_timezone,
+        window_reset_hour,
+        require_signed_submissions: request.require_signed_submissions.unwrap_or(false),
+        signing_secret: signing::generate_secret(),
+        min_score: request.min_score,
+        max_score: request.max_score,
+        integer_scores: request.integer_scores.unwrap_or(false),
+        max_improvement: request.max_improvement,
+        min_submission_interval_secs: request.min_submission_interval_secs,
+    }).await?;
 
     Ok(match accept_type {
         AcceptType::HTMX => templates::GameNewTemplate { game }.into_response(),
@@ -91,16 +172,6 @@ pub async fn create_game(
     })
 }
 
-async fn get_game_internal(db: &PgPool, game_id: i32) -> Result<Option<Game>, ApiError> {
-    let entry = sqlx::query_as::<_, Game>(
-        "SELECT * FROM games WHERE id = $1;")
-        .bind(game_id)
-        .fetch_optional(db)
-        .await?;
-
-    Ok(entry)
-}
-
 /// Get Game
 ///
 /// Responds with full details about the game
@@ -109,7 +180,7 @@ async fn get_game_internal(db: &PgPool, game_id: i32) -> Result<Option<Game>, Ap
     path = "/leaderboard/games/{game_id}",
     responses(
         (status = 200, description = "Game", body = Game),
-        (status = 404, description = "Game not found", body = String, example = json!("Not Found")),
+        (status = 404, description = "Game not found", body = Problem, content_type = "application/problem+json"),
     )
 )]
 pub async fn get_game(
@@ -117,8 +188,8 @@ pub async fn get_game(
     State(state): State<AppState>,
     Path(game_id): Path<i32>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let Some(game) = get_game_internal(&state.db, game_id).await? else {
-        return Ok(NOT_FOUND_RESP.into_response());
+    let Some(game) = state.store.get_game(game_id).await? else {
+        return Err(ApiError::NotFound);
     };
 
     Ok(match accept_type {
@@ -127,72 +198,276 @@ pub async fn get_game(
     })
 }
 
+/// Update Game
+///
+/// Responds with the updated game. Absent fields are left as they are.
+/// Flipping the score sort mode keeps every stored score: existing entries are re-ranked in the new direction,
+/// and a kept best is only replaced once a new submission beats it in the new direction.
+#[utoipa::path(
+    patch,
+    path = "/leaderboard/games/{game_id}",
+    request_body = GameUpdate,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Updated Game", body = Game),
+        (status = 404, description = "Game not found", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn update_game(
+    accept_type: AcceptType,
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(game_id): Path<i32>,
+    Extension(tx): Extension<LeaderboardStream>,
+    JsonOrForm(request): JsonOrForm<GameUpdate>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !api_key.can_manage_game(game_id) {
+        return Err(FORBIDDEN);
+    }
+    let game = state.store.update_game(game_id, request).await?;
+    let Some(game) = game else {
+        return Err(ApiError::NotFound);
+    };
+
+    tx.publish(LeaderboardUpdate::game(MutationKind::Update, game.id)).await;
+
+    Ok(match accept_type {
+        AcceptType::HTMX => templates::GameNewTemplate { game }.into_response(),
+        AcceptType::JSON => Json(game).into_response(),
+    })
+}
+
+/// Delete Game
+///
+/// Deletes the game together with all of its entries, seasons, submission history and bans.
+/// Its API keys are revoked, and kept on record.
+/// Responds with the deleted game
+#[utoipa::path(
+    delete,
+    path = "/leaderboard/games/{game_id}",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Deleted Game", body = Game),
+        (status = 404, description = "Game not found", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn delete_game(
+    accept_type: AcceptType,
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(game_id): Path<i32>,
+    Extension(tx): Extension<LeaderboardStream>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !api_key.is_admin() {
+        return Err(FORBIDDEN);
+    }
+    let Some(game) = state.store.delete_game(game_id).await? else {
+        return Err(ApiError::NotFound);
+    };
+
+    tx.publish(LeaderboardUpdate::game(MutationKind::Delete, game.id)).await;
+
+    Ok(match accept_type {
+        // htmx swaps the game's row out for nothing
+        AcceptType::HTMX => "".into_response(),
+        AcceptType::JSON => Json(game).into_response(),
+    })
+}
+
+/// Get Seasons list
+///
+/// Responds with every season of the game, newest first
+#[utoipa::path(
+    get,
+    path = "/leaderboard/games/{game_id}/seasons",
+    responses(
+        (status = 200, description = "Seasons list", body = Vec<Season>),
+        (status = 404, description = "Game not found", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_seasons(
+    accept_type: AcceptType,
+    State(state): State<AppState>,
+    Path(game_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    if state.store.get_game(game_id).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    let seasons = state.store.get_seasons(game_id).await?;
+
+    Ok(match accept_type {
+        AcceptType::HTMX => templates::SeasonsTemplate { seasons }.into_response(),
+        AcceptType::JSON => Json(seasons).into_response(),
+    })
+}
+
+/// Open Season
+///
+/// Opens a new season of the game. New entries are submitted to the open season
+/// until it is closed. Entries already on the live leaderboard carry over into the season.
+#[utoipa::path(
+    post,
+    path = "/leaderboard/games/{game_id}/seasons",
+    request_body = SeasonNew,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "New Season", body = Season),
+        (status = 404, description = "Game not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Season which is already open", body = Season),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn create_season(
+    accept_type: AcceptType,
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(game_id): Path<i32>,
+    JsonOrForm(request): JsonOrForm<SeasonNew>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !api_key.can_manage_game(game_id) {
+        return Err(FORBIDDEN);
+    }
+    if state.store.get_game(game_id).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+    if let Some(open_season) = state.store.get_open_season(game_id).await? {
+        return Ok((StatusCode::CONFLICT, Json(open_season)).into_response());
+    }
+
+    let season = state.store.create_season(game_id, request.name).await?;
+
+    Ok(match accept_type {
+        AcceptType::HTMX => templates::SeasonTemplate { season }.into_response(),
+        AcceptType::JSON => Json(season).into_response(),
+    })
+}
+
+/// Close Season
+///
+/// Closes the season, freezing the live leaderboard into the season's read only standings,
+/// then empties the live leaderboard. Daily, weekly and monthly leaderboards are left as they are.
+#[utoipa::path(
+    post,
+    path = "/leaderboard/games/{game_id}/seasons/{season_id}/close",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Closed Season", body = Season),
+        (status = 404, description = "Game or Season not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Season which was already closed", body = Season),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn close_season(
+    accept_type: AcceptType,
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path((game_id, season_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !api_key.can_manage_game(game_id) {
+        return Err(FORBIDDEN);
+    }
+    let Some(season) = state.store.close_season(game_id, season_id).await? else {
+        return Ok(match state.store.get_season(game_id, season_id).await? {
+            Some(season) => (StatusCode::CONFLICT, Json(season)).into_response(),
+            None => return Err(ApiError::NotFound),
+        });
+    };
+
+    Ok(match accept_type {
+        AcceptType::HTMX => templates::SeasonTemplate { season }.into_response(),
+        AcceptType::JSON => Json(season).into_response(),
+    })
+}
+
 /// Get Game Entries
 ///
-/// Responds with a list of game entries, sorted by score based on the score_sort_mode of the game
+/// Responds with a page of game entries, sorted by score based on the score_sort_mode of the game.
+/// Equal scores are ranked by the game's score components, then by when each entry reached its score, then by entry id,
+/// so pages stay stable while new scores arrive.
+/// Daily, weekly and monthly leaderboards only hold the best entries submitted since the window last reset.
+/// Closed seasons keep their final standings, read only.
 #[utoipa::path(
     get,
     path = "/leaderboard/games/{game_id}/entries",
+    params(LeaderboardEntriesQuery),
     responses(
-        (status = 200, description = "Game Entries list", body = Vec<LeaderboardEntry>),
-        (status = 404, description = "Game not found", body = String, example = json!("Not Found"))
+        (status = 200, description = "Game Entries page", body = LeaderboardEntriesPage),
+        (status = 400, description = "Invalid cursor", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Game or Season not found", body = Problem, content_type = "application/problem+json")
     )
 )]
 pub async fn get_game_entries(
     accept_type: AcceptType,
     State(state): State<AppState>,
     Path(game_id): Path<i32>,
+    Query(query): Query<LeaderboardEntriesQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let Some(game) =  get_game_internal(&state.db, game_id).await? else {
-        return Ok(NOT_FOUND_RESP.into_response());
+    let Some(game) =  state.store.get_game(game_id).await? else {
+        return Err(ApiError::NotFound);
     };
 
-    let ordering = match game.score_sort_mode {
-        GameScoreSortMode::HigherIsBetter => "DESC",
-        GameScoreSortMode::LesserIsBetter  => "ASC",
+    let cursor = match query.cursor.as_deref().map(EntryCursor::decode) {
+        None => None,
+        Some(Some(cursor)) => Some(cursor),
+        Some(None) => return Err(INVALID_CURSOR),
     };
-    let sql = format!(
-        "SELECT * \
-            FROM leaderboard_entries \
-            WHERE game_id = $1 \
-            ORDER BY score {} \
-            LIMIT 10;",
-        ordering
-    );
-    let entries = sqlx::query_as::<_, LeaderboardEntry>(sql.as_str())
-        .bind(game_id)
-        .fetch_all(&state.db)
-        .await?;
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let window = query.window.unwrap_or_default();
 
-    Ok(match accept_type {
-        AcceptType::HTMX => templates::LeaderboardEntriesTemplate { entries }.into_response(),
-        AcceptType::JSON => Json(entries).into_response(),
-    })
-}
+    let board = match query.season {
+        Some(season_id) => {
+            let Some(season) = state.store.get_season(game_id, season_id).await? else {
+                return Err(ApiError::NotFound);
+            };
+            match season.ended_at {
+                Some(_) => Board::Season(season_id),
+                None => Board::AllTime,
+            }
+        }
+        None => match window {
+            LeaderboardWindow::AllTime => Board::AllTime,
+            window => Board::Window(window, window.start_for_game(Utc::now(), &game)),
+        },
+    };
+
+    let ranking = Ranking::for_game(&game);
+    if cursor.as_ref().is_some_and(|cursor| cursor.components().len() != ranking.component_count()) {
+        return Err(INVALID_CURSOR);
+    }
+
+    let mut entries = state.store.get_entries(&game, board, cursor.as_ref(), limit + 1, offset).await?;
 
-async fn get_user_game_entry_internal(game_id: i32, user_id: Uuid, db: &PgPool) -> Result<Option<LeaderboardEntry>, ApiError> {
-    let entry = sqlx::query_as::<_, LeaderboardEntry>(
-        "SELECT * \
-            FROM leaderboard_entries \
-            WHERE game_id = $1 \
-              AND user_id = $2\
-            LIMIT 1;")
-        .bind(game_id).bind(user_id)
-        .fetch_optional(db)
-        .await?;
+    let next_cursor = match entries.len() as i64 > limit {
+        true => {
+            entries.truncate(limit as usize);
+            entries.last().map(|last| EntryCursor::of(last).encode())
+        }
+        false => None,
+    };
 
-    Ok(entry)
+    Ok(match accept_type {
+        AcceptType::HTMX => templates::LeaderboardEntriesTemplate { game_id, entries, next_cursor, limit, window }.into_response(),
+        AcceptType::JSON => Json(LeaderboardEntriesPage { entries, next_cursor }).into_response(),
+    })
 }
 
 /// Get User Game Entry
 ///
-/// Responds with a single game entry
+/// Responds with a single game entry, along with its rank on the game's leaderboard
 #[utoipa::path(
     get,
     path = "/leaderboard/users/{user_id}/games/{game_id}/entries",
     responses(
-        (status = 200, description = "Game Entry", body = LeaderboardEntry),
-        (status = 404, description = "Game or Entry not found", body = String, example = json!("Not Found")),
+        (status = 200, description = "Game Entry", body = LeaderboardEntryStanding),
+        (status = 404, description = "Game or Entry not found", body = Problem, content_type = "application/problem+json"),
     )
 )]
 pub async fn get_user_game_entry(
@@ -200,127 +475,920 @@ pub async fn get_user_game_entry(
     State(state): State<AppState>,
     Path((user_id, game_id)): Path<(Uuid, i32)>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let entry = get_user_game_entry_internal(game_id, user_id, &state.db).await?;
+    let entry = state.store.get_board_entry(game_id, Board::AllTime, user_id).await?;
 
     let Some(entry) = entry else {
-        return Ok(NOT_FOUND_RESP.into_response());
+        return Err(ApiError::NotFound);
+    };
+    let Some(game) = state.store.get_game(game_id).await? else {
+        return Err(ApiError::NotFound);
     };
+    let (rank, total) = state.store.get_standing(&game, &entry).await?;
 
     Ok(match accept_type {
-        AcceptType::HTMX => templates::LeaderboardEntriesTemplate { entries: vec![entry] }.into_response(),
-        AcceptType::JSON => Json(entry).into_response(),
+        AcceptType::HTMX => templates::LeaderboardEntriesTemplate {
+            game_id,
+            entries: vec![entry],
+            next_cursor: None,
+            limit: DEFAULT_PAGE_SIZE,
+            window: LeaderboardWindow::AllTime,
+        }.into_response(),
+        AcceptType::JSON => Json(LeaderboardEntryStanding { entry, rank, total }).into_response(),
     })
 }
 
-macro_rules! bind_all {
-    // Base case:
-    ($i:expr, $x:expr) => (QueryAs::bind($i, $x));
-    // `$x` followed by at least one `$y,`
-    ($i:expr, $x:expr, $($y:expr),+) => (
-        bind_all!(QueryAs::bind($i, $x), $($y),+)
+/// Get User Game Neighborhood
+///
+/// Responds with the user's entry surrounded by the entries ranked directly above and below it
+#[utoipa::path(
+    get,
+    path = "/leaderboard/users/{user_id}/games/{game_id}/neighborhood",
+    params(NeighborhoodQuery),
+    responses(
+        (status = 200, description = "Entries around the user's entry", body = LeaderboardNeighborhood),
+        (status = 404, description = "Game or Entry not found", body = Problem, content_type = "application/problem+json"),
     )
+)]
+pub async fn get_user_game_neighborhood(
+    accept_type: AcceptType,
+    State(state): State<AppState>,
+    Path((user_id, game_id)): Path<(Uuid, i32)>,
+    Query(query): Query<NeighborhoodQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Some(entry) = state.store.get_board_entry(game_id, Board::AllTime, user_id).await? else {
+        return Err(ApiError::NotFound);
+    };
+    let Some(game) = state.store.get_game(game_id).await? else {
+        return Err(ApiError::NotFound);
+    };
+    let (rank, total) = state.store.get_standing(&game, &entry).await?;
+    let radius = query.radius.unwrap_or(DEFAULT_NEIGHBORHOOD_RADIUS).clamp(0, MAX_NEIGHBORHOOD_RADIUS);
+
+    let (above, below) = state.store.get_neighbors(&game, &entry, radius).await?;
+
+    let first_rank = rank - above.len() as i64;
+    let entries = above.into_iter()
+        .chain(std::iter::once(entry))
+        .chain(below);
+
+    Ok(match accept_type {
+        AcceptType::HTMX => templates::LeaderboardEntriesTemplate {
+            game_id,
+            entries: entries.collect(),
+            next_cursor: None,
+            limit: DEFAULT_PAGE_SIZE,
+            window: LeaderboardWindow::AllTime,
+        }.into_response(),
+        AcceptType::JSON => {
+            let entries = entries
+                .zip(first_rank..)
+                .map(|(entry, rank)| RankedLeaderboardEntry { entry, rank })
+                .collect();
+            Json(LeaderboardNeighborhood { entries, total }).into_response()
+        }
+    })
 }
 
-async fn try_get_better_or_equal_entry(db: &PgPool, game_id: i32, user_id: Uuid, better_than: f64) -> Result<Option<LeaderboardEntry>, ApiError> {
-    let existing_entry = get_user_game_entry_internal(game_id, user_id, db).await?;
-    let Some(existing_entry) = existing_entry else { return Ok(None); };
-    let Some(game) = get_game_internal(db, game_id).await? else { panic!("Game not found") };
+/// Get User Game Submission History
+///
+/// Responds with the user's submissions to the game, newest first, along with stats over all of them
+#[utoipa::path(
+    get,
+    path = "/leaderboard/users/{user_id}/games/{game_id}/history",
+    params(SubmissionHistoryQuery),
+    responses(
+        (status = 200, description = "Submission history", body = SubmissionHistory),
+        (status = 404, description = "Game not found", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_user_game_history(
+    accept_type: AcceptType,
+    State(state): State<AppState>,
+    Path((user_id, game_id)): Path<(Uuid, i32)>,
+    Query(query): Query<SubmissionHistoryQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    if state.store.get_game(game_id).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let submissions = state.store.get_submissions(game_id, user_id, limit, offset).await?;
+    let stats = state.store.get_submission_stats(game_id, user_id).await?;
 
-    let existing_score = existing_entry.score;
-    let is_existing_better_or_equal = match game.score_sort_mode{
-        GameScoreSortMode::HigherIsBetter => existing_score >= better_than,
-        GameScoreSortMode::LesserIsBetter => existing_score <= better_than,
+    let history = SubmissionHistory { submissions, stats };
+    Ok(match accept_type {
+        AcceptType::HTMX => templates::SubmissionHistoryTemplate { history }.into_response(),
+        AcceptType::JSON => Json(history).into_response(),
+    })
+}
+
+/// Merges the submitted score into the user's entry on one of the game's leaderboards, according to the
+/// game's score aggregation. `since` is when the leaderboard started collecting submissions, if ever.
+///
+/// `submitted` holds the submitted score followed by its score components.
+/// Returns the score to save along with how it updates the stored one, or the existing entry when it is kept as it is.
+/// Totals and counts are saved as increments instead, added by the store in the same write as it reads the stored score.
+async fn merge_board_entry_internal(
+    store: &dyn LeaderboardStore,
+    game: &Game,
+    board: Board,
+    since: Option<DateTime<Utc>>,
+    user_id: Uuid,
+    submitted: &[f64],
+) -> Result<Result<(f64, ScoreUpdate), LeaderboardEntry>, ApiError> {
+    if let Some(increment) = game.score_aggregation.increment(submitted[0]) {
+        return Ok(Ok((increment, ScoreUpdate::Add)));
+    }
+    let existing_entry = store.get_board_entry(game.id, board, user_id).await?;
+    let previous_scores = match game.score_aggregation {
+        ScoreAggregation::AverageOfLastN => {
+            let limit = game.aggregation_last_n as i64 - 1;
+            store.get_previous_scores(game.id, user_id, since, limit).await?
+        }
+        _ => vec![],
     };
-    Ok(match is_existing_better_or_equal {
-        true => Some(existing_entry),
-        false => None,
+
+    let existing_keys = existing_entry.as_ref().map(|entry| entry.ranking_keys());
+    let merged = game.score_aggregation.merge(
+        &Ranking::for_game(game),
+        existing_keys.as_deref(),
+        submitted,
+        &previous_scores,
+    );
+    Ok(match (merged, existing_entry) {
+        (Some(merged), _) => Ok((merged, ScoreUpdate::Replace)),
+        (None, Some(existing_entry)) => Err(existing_entry),
+        (None, None) => Ok((submitted[0], ScoreUpdate::Replace)),
     })
 }
 
+/// Checks a submission against the game's score validation rules
+async fn validate_submission_internal(
+    store: &dyn LeaderboardStore,
+    game: &Game,
+    user_id: Uuid,
+    score: f64,
+    score_components: &[f64],
+    now: DateTime<Utc>,
+) -> Result<Result<(), ScoreValidationFailure>, ApiError> {
+    if let Err(failure) = game.check_score(score, score_components) {
+        return Ok(Err(failure));
+    }
+    if game.min_submission_interval_secs.is_some() {
+        if let Some(previous) = store.get_last_submitted_at(game.id, user_id).await? {
+            if let Err(failure) = game.check_submission_interval(previous, now) {
+                return Ok(Err(failure));
+            }
+        }
+    }
+    if game.max_improvement.is_some() {
+        let previous = match game.score_aggregation {
+            ScoreAggregation::Best | ScoreAggregation::Latest => store
+                .get_board_entry(game.id, Board::AllTime, user_id).await?
+                .map(|entry| entry.score),
+            // The entry holds a total, count or average rather than one of the user's scores
+            ScoreAggregation::Sum | ScoreAggregation::AverageOfLastN | ScoreAggregation::Count => store
+                .get_previous_scores(game.id, user_id, None, 1).await?
+                .first()
+                .copied(),
+        };
+        if let Some(previous) = previous {
+            if let Err(failure) = game.check_improvement(previous, score) {
+                return Ok(Err(failure));
+            }
+        }
+    }
+    Ok(Ok(()))
+}
+
 /// Create User Game Entry
 ///
-/// Responds with the created entry, or an existing entry, if new entry is not an improvement.
+/// Merges the entry into the user's entries on every leaderboard of the game: all time, monthly, weekly and daily.
+/// How entries are merged depends on the game's score_aggregation. With the default, Best, an entry is only
+/// kept on the leaderboards it improves.
+/// While a season is open, the all time entry counts towards that season.
+/// AverageOfLastN only averages the all time entry over submissions made since the open season started, or since
+/// the last season closed.
+/// Every submission is kept in the user's history, including those which conflict.
+/// Games requiring signed submissions reject entries without a valid, fresh and unused signature, see the
+/// X-Signature headers.
+/// Banned users, and users whose entry was invalidated, can not submit until a moderator lifts the ban or
+/// restores or deletes the entry.
+/// Scores breaking the game's score validation rules are rejected, and kept in the history along with the rule they
+/// broke. Rejected submissions count towards min_submission_interval_secs too.
+/// Submissions are rate limited per client IP, API key and user_id.
+/// Submissions sent with an Idempotency-Key are handled once per API key and key: for 24 hours, retries get the
+/// first response replayed, with an Idempotent-Replayed header, and reusing the key for another request is refused.
+/// Rejected submissions free their key, server errors are replayed like other responses.
+/// Responds with the user's all time entry, or conflicts with it when the new entry changed no leaderboard.
 #[utoipa::path(
     post,
     path = "/leaderboard/games/{game_id}/entries",
     request_body = LeaderboardEntryNew,
+    params(
+        ("X-Signature-Timestamp" = Option<i64>, Header, description = "Unix seconds at signing. Required by games requiring signed submissions"),
+        ("X-Signature-Nonce" = Option<String>, Header, description = "Single use, 1 to 128 of A-Z a-z 0-9 - _. Required by games requiring signed submissions"),
+        ("X-Signature" = Option<String>, Header, description = "Hex HMAC-SHA256, keyed with the game's signing secret, of the lines: game_id, user_id, score, comma separated score_components, free_data, timestamp, nonce. Required by games requiring signed submissions"),
+        ("Idempotency-Key" = Option<String>, Header, description = "1 to 255 visible ASCII characters, unique per submission. Retries with the same key get the first response replayed"),
+    ),
+    security(("api_key" = [])),
     responses(
-        (status = 200, description = "New game entry", body = LeaderboardEntry),
-        (status = 409, description = "Old, better, game entry", body = LeaderboardEntry)
+        (status = 200, description = "User's all time game entry", body = LeaderboardEntry),
+        (status = 400, description = "Wrong number of score components, or a malformed Idempotency-Key", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key, or a missing, invalid, stale or replayed signature", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed, user is banned, or their entry was invalidated", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Game not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Old, better, game entry. A problem instead when the Idempotency-Key's first request is still being handled, for up to a minute", body = LeaderboardEntry),
+        (status = 422, description = "Score breaks one of the game's score validation rules, named by the problem's rule and limit members, or the Idempotency-Key was used with another request", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Too many submissions by the client's IP, API key or user, retry after the Retry-After header's seconds", body = Problem, content_type = "application/problem+json"),
     )
 )]
+// Axum handlers take one argument per extractor
+#[allow(clippy::too_many_arguments)]
 pub async fn create_game_entry(
     accept_type: AcceptType,
     State(state): State<AppState>,
+    api_key: ApiKey,
+    ClientIp(ip): ClientIp,
     Path(game_id): Path<i32>,
     Extension(tx): Extension<LeaderboardStream>,
+    IdempotencyKey(idempotency_key): IdempotencyKey,
+    signature: Option<SubmissionSignature>,
     JsonOrForm(request): JsonOrForm<LeaderboardEntryNew>,
 ) -> Result<impl IntoResponse, ApiError> {
-    if let Some(ex_user_id) = request.user_id {
-        if let Some(better_entry) = try_get_better_or_equal_entry(&state.db, game_id, ex_user_id, request.score).await? {
-            return Ok((StatusCode::CONFLICT, Json(better_entry)).into_response())
+    if let Some(key) = &idempotency_key {
+        let fingerprint = idempotency::fingerprint(game_id, &request);
+        if let Some(replay) = idempotency::claim(&*state.store, api_key.id, key, game_id, &fingerprint).await? {
+            let replayed = [(IDEMPOTENT_REPLAYED_HEADER, "true")];
+            return Ok(match replay {
+                Replay::Entry(status, entry) => (replayed, entry_response(accept_type, status, entry)).into_response(),
+                Replay::Problem(problem) => (replayed, problem).into_response(),
+            });
         }
     }
 
-    let leaderboard_entry = sqlx::query_as::<_, LeaderboardEntry>(
-        "INSERT INTO leaderboard_entries (game_id, score, user_name, free_data, user_id) \
-        VALUES ($1, $2, $3, $4, $5) \
-        ON CONFLICT (game_id, user_id) DO UPDATE SET \
-        score = EXCLUDED.score, user_name = EXCLUDED.user_name, free_data = EXCLUDED.free_data \
-        RETURNING id, score, game_id, user_name, free_data, user_id \
-        ",
-    );
-    let leaderboard_entry = bind_all!(
-        leaderboard_entry,
-        game_id,
-        request.score,
-        request.user_name,
-        request.free_data.unwrap_or("".into()),
-        request.user_id.unwrap_or(Uuid::new_v4())
-    );
-    let leaderboard_entry = leaderboard_entry.fetch_one(&state.db).await?;
-
-    if tx
-        .send(LeaderboardUpdate {
-            mutation_kind: MutationKind::Create,
-            id: leaderboard_entry.id,
-        })
-        .is_err()
-    {
-        error!(
-            "Record with ID {} was created but nobody's listening to the stream!",
-            leaderboard_entry.id
+    let submitted = match state.rate_limiter.check(Route::CreateGameEntry, ip, &api_key, request.user_id) {
+        Ok(()) => submit_entry_internal(&state, &tx, &api_key, game_id, signature, request).await,
+        Err(e) => Err(e),
+    };
+    let response = submitted.map(|submitted| match submitted {
+        Ok((entry, _rank)) => (StatusCode::OK, entry),
+        Err(better_entry) => (StatusCode::CONFLICT, better_entry),
+    });
+    if let Some(key) = &idempotency_key {
+        let finished = response.as_ref().map(|(status, entry)| (*status, entry));
+        idempotency::finish(&*state.store, api_key.id, key, finished).await;
+    }
+    let (status, entry) = response?;
+
+    Ok(entry_response(accept_type, status, entry))
+}
+
+/// The user's entry, or the better entry conflicting with their submission
+fn entry_response(accept_type: AcceptType, status: StatusCode, entry: LeaderboardEntry) -> Response {
+    if status == StatusCode::CONFLICT {
+        return (StatusCode::CONFLICT, Json(entry)).into_response();
+    }
+    match accept_type {
+        AcceptType::HTMX => templates::LeaderboardEntryNewTemplate { entry }.into_response(),
+        AcceptType::JSON => Json(entry).into_response(),
+    }
+}
+
+/// Validates the submission and merges it into the user's entries, streaming the change and counting its outcome.
+/// Returns the user's all time entry along with its rank, or the better entry when the submission changed no leaderboard
+#[tracing::instrument(name = "submission", skip_all, fields(game_id, user_id = request.user_id.map(tracing::field::display)))]
+pub(super) async fn submit_entry_internal(
+    state: &AppState,
+    tx: &LeaderboardStream,
+    api_key: &ApiKey,
+    game_id: i32,
+    signature: Option<SubmissionSignature>,
+    request: LeaderboardEntryNew,
+) -> Result<Result<(LeaderboardEntry, Option<i64>), LeaderboardEntry>, ApiError> {
+    let submitted = merge_submission(state, tx, api_key, game_id, signature, request).await;
+    state.metrics.record_submission(match &submitted {
+        Ok(Ok(_)) => SubmissionOutcome::Accepted,
+        Ok(Err(_)) => SubmissionOutcome::NotBetter,
+        Err(_) => SubmissionOutcome::Rejected,
+    });
+
+    submitted
+}
+
+async fn merge_submission(
+    state: &AppState,
+    tx: &LeaderboardStream,
+    api_key: &ApiKey,
+    game_id: i32,
+    signature: Option<SubmissionSignature>,
+    request: LeaderboardEntryNew,
+) -> Result<Result<(LeaderboardEntry, Option<i64>), LeaderboardEntry>, ApiError> {
+    if !api_key.can_submit_to_game(game_id) {
+        return Err(FORBIDDEN);
+    }
+    let store = state.store.as_ref();
+    let Some(game) = store.get_game(game_id).await? else {
+        return Err(ApiError::NotFound);
+    };
+    let nonce = if game.require_signed_submissions {
+        let Some(signature) = signature else {
+            return Err(MISSING_SIGNATURE);
+        };
+        if !signature.is_fresh(Utc::now()) {
+            return Err(STALE_SIGNATURE);
+        }
+        let payload = signing::canonical_payload(
+            game_id,
+            request.user_id,
+            request.score,
+            request.score_components.as_deref().unwrap_or_default(),
+            request.free_data.as_deref().unwrap_or_default(),
+            signature.timestamp,
+            &signature.nonce,
         );
+        if !signature.verify(&game.signing_secret, &payload) {
+            return Err(INVALID_SIGNATURE);
+        }
+        Some(signature.nonce)
+    } else {
+        None
+    };
+    let user_id = request.user_id.unwrap_or(Uuid::new_v4());
+    let free_data = request.free_data.unwrap_or("".into());
+    let score_components = request.score_components.unwrap_or_default();
+    if score_components.len() != Ranking::for_game(&game).component_count() {
+        return Err(INVALID_SCORE_COMPONENTS);
+    }
+    let submitted_keys = std::iter::once(request.score).chain(score_components.iter().copied()).collect::<Vec<_>>();
+    if store.is_user_banned(game_id, user_id).await? {
+        return Err(BANNED);
     }
+    if store.is_entry_invalidated(game_id, user_id).await? {
+        return Err(ENTRY_INVALIDATED);
+    }
+    let season = store.get_open_season(game_id).await?;
+    let submitted = EntryValues {
+        game_id,
+        user_id,
+        user_name: &request.user_name,
+        score: request.score,
+        score_components: &score_components,
+        free_data: &free_data,
+    };
+    let now = Utc::now();
+    if let Err(failure) = validate_submission_internal(store, &game, user_id, request.score, &score_components, now).await? {
+        // Kept for investigating suspicious scores, unless they are not numbers the history can hold
+        if failure.rule != ScoreValidationRule::Finite {
+            store.record_submission(submitted, false, Some(failure.rule), season.as_ref().map(|season| season.id)).await?;
+        }
+        return Err(failure.into());
+    }
+    // Claimed after every other check, so rejected submissions can be retried with the same signature
+    if let Some(nonce) = nonce {
+        if !store.claim_nonce(game_id, &nonce).await? {
+            return Err(REPLAYED_NONCE);
+        }
+    }
+    let previous_rank = match store.get_board_entry(game_id, Board::AllTime, user_id).await? {
+        Some(previous_entry) => store.get_rank(&previous_entry).await?,
+        None => None,
+    };
 
-    Ok(match accept_type {
-        AcceptType::HTMX => templates::LeaderboardEntryNewTemplate {
-            entry: leaderboard_entry,
+    let all_time_since = match &season {
+        Some(season) => Some(season.started_at),
+        // Closing a season empties the live leaderboard, which starts over from then on
+        None => store.get_seasons(game_id).await?.iter().filter_map(|season| season.ended_at).max(),
+    };
+
+    let all_time_merge = merge_board_entry_internal(
+        store, &game, Board::AllTime, all_time_since, user_id, &submitted_keys).await?;
+    let all_time_entry = match all_time_merge {
+        Err(existing_entry) => Err(existing_entry),
+        Ok((merged_score, update)) => {
+            let merged = EntryValues { score: merged_score, ..submitted };
+            Ok(store.save_entry(merged, update, season.as_ref().map(|season| season.id)).await?)
         }
-        .into_response(),
-        AcceptType::JSON => Json(leaderboard_entry).into_response(),
+    };
+
+    let mut improved_window = false;
+    for window in LeaderboardWindow::TIMED {
+        let window_start = window.start_for_game(now, &game);
+        let board = Board::Window(window, window_start);
+        let Ok((merged_score, update)) = merge_board_entry_internal(
+            store, &game, board, window_start, user_id, &submitted_keys).await? else {
+            continue;
+        };
+        store.save_window_entry(window, window_start, EntryValues { score: merged_score, ..submitted }, update).await?;
+        improved_window = true;
+    }
+
+    let accepted = all_time_entry.is_ok() || improved_window;
+    store.record_submission(submitted, accepted, None, season.map(|season| season.id)).await?;
+
+    let leaderboard_entry = match all_time_entry {
+        Ok(entry) => entry,
+        Err(better_entry) if !improved_window => return Ok(Err(better_entry)),
+        Err(better_entry) => better_entry,
+    };
+
+    let rank = store.get_rank(&leaderboard_entry).await?;
+    tx.publish(LeaderboardUpdate::entry(MutationKind::Create, leaderboard_entry.clone(), previous_rank, rank)).await;
+
+    Ok(Ok((leaderboard_entry, rank)))
+}
+
+/// Delete Entry
+///
+/// Moderation. Deletes a user's all time entry along with their daily, weekly and monthly entries on the same game.
+/// The user's submission history is kept. Responds with the deleted entry
+#[utoipa::path(
+    delete,
+    path = "/leaderboard/admin/entries/{entry_id}",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Deleted Entry", body = LeaderboardEntry),
+        (status = 404, description = "Entry not found", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn delete_entry(
+    accept_type: AcceptType,
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(entry_id): Path<i32>,
+    Extension(tx): Extension<LeaderboardStream>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Some(entry) = state.store.get_entry(entry_id).await? else {
+        return Err(ApiError::NotFound);
+    };
+    if !api_key.can_manage_game(entry.game_id) {
+        return Err(FORBIDDEN);
+    }
+    let previous_rank = state.store.get_rank(&entry).await?;
+
+    state.store.delete_entry(&entry).await?;
+
+    tx.publish(LeaderboardUpdate::entry(MutationKind::Delete, entry.clone(), previous_rank, None)).await;
+
+    Ok(match accept_type {
+        AcceptType::HTMX => "".into_response(),
+        AcceptType::JSON => Json(entry).into_response(),
+    })
+}
+
+/// Invalidate Entry
+///
+/// Moderation. Hides a user's all time entry, along with their daily, weekly and monthly entries on the same game,
+/// while keeping them for audit. The user can not submit to the game until the entry is restored or deleted.
+/// Streams the entry as deleted. Responds with the invalidated entry
+#[utoipa::path(
+    post,
+    path = "/leaderboard/admin/entries/{entry_id}/invalidate",
+    request_body = EntryInvalidation,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Invalidated Entry", body = LeaderboardEntry),
+        (status = 404, description = "Entry not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Entry is already invalidated", body = LeaderboardEntry),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn invalidate_entry(
+    accept_type: AcceptType,
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(entry_id): Path<i32>,
+    Extension(tx): Extension<LeaderboardStream>,
+    JsonOrForm(request): JsonOrForm<EntryInvalidation>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Some(entry) = state.store.get_entry(entry_id).await? else {
+        return Err(ApiError::NotFound);
+    };
+    if !api_key.can_manage_game(entry.game_id) {
+        return Err(FORBIDDEN);
+    }
+    if entry.invalidated_at.is_some() {
+        return Ok((StatusCode::CONFLICT, Json(entry)).into_response());
+    }
+    let previous_rank = state.store.get_rank(&entry).await?;
+
+    let entry = state.store.invalidate_entry(&entry, request.reason).await?;
+
+    tx.publish(LeaderboardUpdate::entry(MutationKind::Delete, entry.clone(), previous_rank, None)).await;
+
+    Ok(match accept_type {
+        AcceptType::HTMX => "".into_response(),
+        AcceptType::JSON => Json(entry).into_response(),
     })
 }
 
-// TODO: a unique stream per game?
+/// Restore Entry
+///
+/// Moderation. Shows an invalidated entry again, along with the user's daily, weekly and monthly entries
+/// on the same game. Streams the entry as created. Responds with the restored entry
+#[utoipa::path(
+    post,
+    path = "/leaderboard/admin/entries/{entry_id}/restore",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Restored Entry", body = LeaderboardEntry),
+        (status = 404, description = "Entry not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Entry is not invalidated", body = LeaderboardEntry),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn restore_entry(
+    accept_type: AcceptType,
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(entry_id): Path<i32>,
+    Extension(tx): Extension<LeaderboardStream>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Some(entry) = state.store.get_entry(entry_id).await? else {
+        return Err(ApiError::NotFound);
+    };
+    if !api_key.can_manage_game(entry.game_id) {
+        return Err(FORBIDDEN);
+    }
+    if entry.invalidated_at.is_none() {
+        return Ok((StatusCode::CONFLICT, Json(entry)).into_response());
+    }
+
+    let entry = state.store.restore_entry(&entry).await?;
+
+    let rank = state.store.get_rank(&entry).await?;
+    tx.publish(LeaderboardUpdate::entry(MutationKind::Create, entry.clone(), None, rank)).await;
+
+    Ok(match accept_type {
+        AcceptType::HTMX => templates::LeaderboardEntryNewTemplate { entry }.into_response(),
+        AcceptType::JSON => Json(entry).into_response(),
+    })
+}
+
+/// Get User Bans list
+///
+/// Moderation. Responds with every ban, oldest first
+#[utoipa::path(
+    get,
+    path = "/leaderboard/admin/bans",
+    params(UserBansQuery),
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "User Bans list", body = Vec<UserBan>),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_bans(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Query(query): Query<UserBansQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !api_key.is_admin() {
+        return Err(FORBIDDEN);
+    }
+    let bans = state.store.get_bans(query.user_id).await?;
+
+    Ok(Json(bans).into_response())
+}
+
+/// Ban User
+///
+/// Moderation. Bars the user from submitting scores to the game, or to every game, and hides their entries.
+/// Streams the hidden entries as deleted. Responds with the ban
+#[utoipa::path(
+    post,
+    path = "/leaderboard/admin/bans",
+    request_body = UserBanNew,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "New User Ban", body = UserBan),
+        (status = 404, description = "Game not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "User is already banned", body = UserBan),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn create_ban(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Extension(tx): Extension<LeaderboardStream>,
+    JsonOrForm(request): JsonOrForm<UserBanNew>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !request.game_id.map_or(api_key.is_admin(), |game_id| api_key.can_manage_game(game_id)) {
+        return Err(FORBIDDEN);
+    }
+    if let Some(game_id) = request.game_id {
+        if state.store.get_game(game_id).await?.is_none() {
+            return Err(ApiError::NotFound);
+        }
+    }
+    if let Some(existing_ban) = state.store.find_ban(request.user_id, request.game_id).await? {
+        return Ok((StatusCode::CONFLICT, Json(existing_ban)).into_response());
+    }
+
+    let mut hidden_entries = Vec::new();
+    for entry in state.store.get_visible_entries(request.user_id, request.game_id).await? {
+        let previous_rank = state.store.get_rank(&entry).await?;
+        hidden_entries.push((entry, previous_rank));
+    }
+    let ban = state.store.create_ban(request.user_id, request.game_id, request.reason.unwrap_or_default()).await?;
+
+    for (entry, previous_rank) in hidden_entries {
+        tx.publish(LeaderboardUpdate::entry(MutationKind::Delete, entry, previous_rank, None)).await;
+    }
+
+    Ok(Json(ban).into_response())
+}
+
+/// Lift User Ban
+///
+/// Moderation. Lets the user submit scores again and shows their entries, unless another ban still applies.
+/// Streams the shown entries as created. Responds with the lifted ban
+#[utoipa::path(
+    delete,
+    path = "/leaderboard/admin/bans/{ban_id}",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Lifted User Ban", body = UserBan),
+        (status = 404, description = "Ban not found", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn delete_ban(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(ban_id): Path<i32>,
+    Extension(tx): Extension<LeaderboardStream>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Some(ban) = state.store.get_ban(ban_id).await? else {
+        return Err(ApiError::NotFound);
+    };
+    if !ban.game_id.map_or(api_key.is_admin(), |game_id| api_key.can_manage_game(game_id)) {
+        return Err(FORBIDDEN);
+    }
+    state.store.delete_ban(ban_id).await?;
+
+    for entry in state.store.get_visible_entries(ban.user_id, ban.game_id).await? {
+        let rank = state.store.get_rank(&entry).await?;
+        tx.publish(LeaderboardUpdate::entry(MutationKind::Create, entry, None, rank)).await;
+    }
+
+    Ok(Json(ban).into_response())
+}
+
+/// Get Game Signing Secret
+///
+/// Responds with the secret the game's clients sign their score submissions with
+#[utoipa::path(
+    get,
+    path = "/leaderboard/games/{game_id}/signing_secret",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Game Signing Secret", body = GameSigningSecret),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Game not found", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_signing_secret(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(game_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !api_key.can_manage_game(game_id) {
+        return Err(FORBIDDEN);
+    }
+    let Some(game) = state.store.get_game(game_id).await? else {
+        return Err(ApiError::NotFound);
+    };
+
+    Ok(Json(GameSigningSecret { game_id, signing_secret: game.signing_secret }).into_response())
+}
+
+/// Rotate Game Signing Secret
+///
+/// Replaces the game's signing secret. Submissions signed with the old secret are rejected from then on.
+/// Responds with the new secret
+#[utoipa::path(
+    post,
+    path = "/leaderboard/games/{game_id}/signing_secret",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "New Game Signing Secret", body = GameSigningSecret),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Game not found", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn rotate_signing_secret(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(game_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !api_key.can_manage_game(game_id) {
+        return Err(FORBIDDEN);
+    }
+    let Some(game) = state.store.set_signing_secret(game_id, signing::generate_secret()).await? else {
+        return Err(ApiError::NotFound);
+    };
+
+    Ok(Json(GameSigningSecret { game_id, signing_secret: game.signing_secret }).into_response())
+}
+
+/// Get API Keys list
+///
+/// Admin only. Responds with every API key, without the keys themselves
+#[utoipa::path(
+    get,
+    path = "/leaderboard/admin/keys",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "API Keys list", body = Vec<ApiKey>),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_api_keys(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+) -> Result<impl IntoResponse, ApiError> {
+    if !api_key.is_admin() {
+        return Err(FORBIDDEN);
+    }
+    let api_keys = state.store.get_api_keys().await?;
+
+    Ok(Json(api_keys).into_response())
+}
+
+/// Create API Key
+///
+/// Admin only. Mints a new key. Responds with the key, which is not stored and can not be retrieved again
+#[utoipa::path(
+    post,
+    path = "/leaderboard/admin/keys",
+    request_body = ApiKeyNew,
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "New API Key", body = ApiKeyCreated),
+        (status = 400, description = "Invalid game_id for the role", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Game not found", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    JsonOrForm(request): JsonOrForm<ApiKeyNew>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !api_key.is_admin() {
+        return Err(FORBIDDEN);
+    }
+    let game_id_allowed = match request.role {
+        ApiKeyRole::Admin => request.game_id.is_none(),
+        ApiKeyRole::GameOwner => request.game_id.is_some(),
+        ApiKeyRole::Submitter => true,
+    };
+    if !game_id_allowed {
+        return Err(INVALID_API_KEY_GAME);
+    }
+    if let Some(game_id) = request.game_id {
+        if state.store.get_game(game_id).await?.is_none() {
+            return Err(ApiError::NotFound);
+        }
+    }
+
+    let api_key = auth::insert_api_key(state.store.as_ref(), &request).await?;
+
+    Ok(Json(api_key).into_response())
+}
+
+/// Revoke API Key
+///
+/// Admin only. Revoked keys are rejected from then on. Responds with the revoked key
+#[utoipa::path(
+    delete,
+    path = "/leaderboard/admin/keys/{key_id}",
+    security(("api_key" = [])),
+    responses(
+        (status = 200, description = "Revoked API Key", body = ApiKey),
+        (status = 401, description = "Missing or invalid API key", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "API key not allowed", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "API Key not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "API Key is already revoked", body = ApiKey),
+    )
+)]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    api_key: ApiKey,
+    Path(key_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !api_key.is_admin() {
+        return Err(FORBIDDEN);
+    }
+    let Some(existing_key) = state.store.get_api_key(key_id).await? else {
+        return Err(ApiError::NotFound);
+    };
+    if existing_key.revoked_at.is_some() {
+        return Ok((StatusCode::CONFLICT, Json(existing_key)).into_response());
+    }
+
+    let Some(revoked_key) = state.store.revoke_api_key(key_id).await? else {
+        return Err(ApiError::NotFound);
+    };
+
+    Ok(Json(revoked_key).into_response())
+}
+
+/// Streams the updates of every game
 pub async fn handle_stream(
     accept_type: AcceptType,
+    State(state): State<AppState>,
+    LastEventId(last_event_id): LastEventId,
     Extension(tx): Extension<LeaderboardStream>,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    let rx = tx.subscribe();
+    update_events(accept_type, &state, &tx, last_event_id, None)
+}
 
-    let stream = BroadcastStream::new(rx);
+/// Stream Game Updates
+///
+/// Server sent events of the game's updates. Entry updates carry the user's all time entry along with its
+/// rank before and after the update, for animating rank changes without fetching the leaderboard again.
+/// Reconnecting clients get the updates they missed since their `Last-Event-ID`. When those are no longer kept,
+/// or a client falls too far behind, it gets a `resync` event instead and should fetch the leaderboard again.
+/// So does every client when this instance lost updates while reconnecting to the database.
+/// Event ids are only known to the instance which sent them, reconnecting to another instance also resyncs
+#[utoipa::path(
+    get,
+    path = "/leaderboard/games/{game_id}/stream",
+    params(
+        ("Last-Event-ID" = Option<u64>, Header, description = "Id of the last event received, to get the missed ones from the same instance"),
+    ),
+    responses(
+        (status = 200, description = "Stream of Game Updates", body = LeaderboardUpdate, content_type = "text/event-stream"),
+        (status = 404, description = "Game not found", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn handle_game_stream(
+    accept_type: AcceptType,
+    State(state): State<AppState>,
+    Path(game_id): Path<i32>,
+    LastEventId(last_event_id): LastEventId,
+    Extension(tx): Extension<LeaderboardStream>,
+) -> Result<impl IntoResponse, ApiError> {
+    if state.store.get_game(game_id).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(update_events(accept_type, &state, &tx, last_event_id, Some(game_id)))
+}
+
+/// Subscribes to the updates, of one game or of every game, replaying those missed since the last event id.
+/// Ends on shutdown, reconnecting clients then resume from another instance
+fn update_events(
+    accept_type: AcceptType,
+    state: &AppState,
+    tx: &LeaderboardStream,
+    last_event_id: Option<u64>,
+    game_id: Option<i32>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let subscription = tx.subscribe(last_event_id);
+    let missed = match subscription.missed {
+        Some(missed) => missed.into_iter().map(|streamed| Ok(StreamMessage::Update(streamed))).collect(),
+        None => vec![Ok(StreamMessage::Resync)],
+    };
+    let stream = tokio_stream::iter(missed).chain(BroadcastStream::new(subscription.rx));
+    let stream = futures_util::StreamExt::take_until(stream, state.shutdown.triggered());
+    let metrics = state.metrics.clone();
 
     Sse::new(
         stream
-            .map(move |msg| {
-                let msg = msg.unwrap();
-                let json = json!(msg);
-                let message = match accept_type {
-                    AcceptType::HTMX => format!("<div>{}</div>", json),
-                    AcceptType::JSON => json.to_string(),
-                };
-                Event::default().data(message)
+            .filter(move |msg| match msg {
+                Ok(StreamMessage::Update(streamed)) => game_id.is_none_or(|game_id| game_id == streamed.update.game_id),
+                Ok(StreamMessage::Resync) | Err(_) => true,
+            })
+            .map(move |msg| match msg {
+                Ok(StreamMessage::Update(StreamedUpdate { id, update })) => {
+                    let json = json!(update);
+                    let message = match accept_type {
+                        AcceptType::HTMX => format!("<div>{}</div>", json),
+                        AcceptType::JSON => json.to_string(),
+                    };
+                    Event::default().id(id.to_string()).data(message)
+                }
+                Ok(StreamMessage::Resync) => resync_event(),
+                Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                    metrics.record_lagged("leaderboard", skipped);
+                    warn!("Stream subscriber fell {skipped} updates behind, asking it to resync");
+                    resync_event()
+                }
             })
             .map(Ok),
     )
@@ -331,6 +1399,10 @@ pub async fn handle_stream(
     )
 }
 
+fn resync_event() -> Event {
+    Event::default().event("resync").data("Updates were missed, fetch the leaderboard again")
+}
+
 #[cfg(test)]
 mod tests{
     use axum::http::StatusCode;
@@ -338,7 +1410,7 @@ mod tests{
 
     #[test]
     fn not_found_is_not_found(){
-        assert_eq!(StatusCode::NOT_FOUND, NOT_FOUND_RESP.0);
-        assert_eq!("Not Found", NOT_FOUND_RESP.1);
+        assert_eq!(StatusCode::NOT_FOUND, ApiError::NotFound.status());
+        assert_eq!("Not Found", ApiError::NotFound.problem().detail);
     }
 }
\ No newline at end of file
@@ -0,0 +1,158 @@
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use sqlx::types::Uuid;
+
+use crate::errors::{ApiError, ErrorCode};
+
+pub const TIMESTAMP_HEADER: &str = "x-signature-timestamp";
+pub const NONCE_HEADER: &str = "x-signature-nonce";
+pub const SIGNATURE_HEADER: &str = "x-signature";
+
+/// How far a signature's timestamp may be from the server's clock, in seconds
+pub const TIMESTAMP_TOLERANCE_SECS: i64 = 300;
+
+const MAX_NONCE_LEN: usize = 128;
+
+/// HMAC-SHA256 signature of a score submission, sent in the `X-Signature-Timestamp` (unix seconds),
+/// `X-Signature-Nonce` (1 to 128 of `A-Z a-z 0-9 - _`) and `X-Signature` (hex) headers.
+///
+/// The signature covers the lines of [`canonical_payload`], keyed with the game's signing secret.
+#[derive(Debug, PartialEq)]
+pub struct SubmissionSignature {
+    pub timestamp: i64,
+    pub nonce: String,
+    pub signature: Vec<u8>,
+}
+
+/// Rejects requests with malformed signature headers. Take it as an `Option` to allow unsigned requests
+#[async_trait]
+impl<S> FromRequestParts<S> for SubmissionSignature
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        const MALFORMED: ApiError =
+            ApiError::unauthorized(ErrorCode::InvalidSignature, "Missing or malformed signature headers");
+        let header = |name: &str| parts.headers.get(name).and_then(|value| value.to_str().ok());
+
+        let timestamp = header(TIMESTAMP_HEADER).and_then(|value| value.parse().ok()).ok_or(MALFORMED)?;
+        let nonce = header(NONCE_HEADER).ok_or(MALFORMED)?;
+        let signature = header(SIGNATURE_HEADER).ok_or(MALFORMED)?;
+
+        Self::parse(timestamp, nonce, signature).ok_or(MALFORMED)
+    }
+}
+
+impl SubmissionSignature {
+    /// Checks the nonce and decodes the hex signature
+    pub fn parse(timestamp: i64, nonce: &str, signature: &str) -> Option<Self> {
+        if !is_valid_nonce(nonce) {
+            return None;
+        }
+        let signature = hex::decode(signature).ok()?;
+
+        Some(Self { timestamp, nonce: nonce.to_string(), signature })
+    }
+
+    /// Whether the signature was made within [`TIMESTAMP_TOLERANCE_SECS`] of `now`
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        (now.timestamp() - self.timestamp).abs() <= TIMESTAMP_TOLERANCE_SECS
+    }
+
+    /// Checks the signature in constant time
+    pub fn verify(&self, secret: &str, payload: &str) -> bool {
+        mac(secret, payload).verify_slice(&self.signature).is_ok()
+    }
+}
+
+fn is_valid_nonce(nonce: &str) -> bool {
+    (1..=MAX_NONCE_LEN).contains(&nonce.len())
+        && nonce.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn mac(secret: &str, payload: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+/// Text a submission's signature covers, one field per line:
+/// game id, user id (empty when absent), score, comma separated score components, free data (empty when absent),
+/// timestamp and nonce. Numbers are written as their shortest round-tripping decimal, without exponent.
+pub fn canonical_payload(
+    game_id: i32,
+    user_id: Option<Uuid>,
+    score: f64,
+    score_components: &[f64],
+    free_data: &str,
+    timestamp: i64,
+    nonce: &str,
+) -> String {
+    let user_id = user_id.map(|user_id| user_id.to_string()).unwrap_or_default();
+    let score_components = score_components.iter()
+        .map(|component| component.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{game_id}\n{user_id}\n{score}\n{score_components}\n{free_data}\n{timestamp}\n{nonce}")
+}
+
+/// Hex HMAC-SHA256 of the payload, as clients send it in the `X-Signature` header
+pub fn sign(secret: &str, payload: &str) -> String {
+    hex::encode(mac(secret, payload).finalize().into_bytes())
+}
+
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(secret: &str, payload: &str, timestamp: i64) -> SubmissionSignature {
+        SubmissionSignature { timestamp, nonce: "n".into(), signature: hex::decode(sign(secret, payload)).unwrap() }
+    }
+
+    #[test]
+    fn payload_has_one_field_per_line() {
+        let user_id = Uuid::nil();
+        assert_eq!(
+            "7\n00000000-0000-0000-0000-000000000000\n12.5\n3,0.25\nhi\n1700000000\nabc",
+            canonical_payload(7, Some(user_id), 12.5, &[3.0, 0.25], "hi", 1700000000, "abc"),
+        );
+        assert_eq!("7\n\n12\n\n\n1700000000\nabc", canonical_payload(7, None, 12.0, &[], "", 1700000000, "abc"));
+    }
+
+    #[test]
+    fn verifies_only_matching_signatures() {
+        let signature = signature("secret", "payload", 0);
+        assert!(signature.verify("secret", "payload"));
+        assert!(!signature.verify("secret", "tampered"));
+        assert!(!signature.verify("other secret", "payload"));
+    }
+
+    #[test]
+    fn rejects_stale_timestamps() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        assert!(signature("s", "p", 1_700_000_000 - TIMESTAMP_TOLERANCE_SECS).is_fresh(now));
+        assert!(signature("s", "p", 1_700_000_000 + TIMESTAMP_TOLERANCE_SECS).is_fresh(now));
+        assert!(!signature("s", "p", 1_700_000_000 - TIMESTAMP_TOLERANCE_SECS - 1).is_fresh(now));
+    }
+
+    #[test]
+    fn validates_nonces() {
+        assert!(is_valid_nonce("a1-_Z"));
+        assert!(!is_valid_nonce(""));
+        assert!(!is_valid_nonce("has space"));
+        assert!(!is_valid_nonce(&"a".repeat(MAX_NONCE_LEN + 1)));
+    }
+}
@@ -0,0 +1,805 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::types::Uuid;
+
+use super::{compare_entries, ranks_after_cursor, Board, EntryValues, IdempotentRequest, LeaderboardStore, Migrations, ScoreUpdate};
+use crate::errors::ApiError;
+use crate::leaderboard::cursor::EntryCursor;
+use crate::leaderboard::idempotency;
+use crate::leaderboard::models::*;
+use crate::leaderboard::ranking::Ranking;
+use crate::leaderboard::signing;
+
+/// Keeps everything in the process, lost on restart
+#[derive(Default)]
+pub struct MemoryStore {
+    data: Mutex<Data>,
+}
+
+#[derive(Default)]
+struct Data {
+    last_id: i32,
+    games: BTreeMap<i32, Game>,
+    seasons: Vec<Season>,
+    entries: Vec<LeaderboardEntry>,
+    window_entries: Vec<WindowEntry>,
+    /// Final standings, by the closed season
+    season_entries: Vec<LeaderboardEntry>,
+    submissions: Vec<ScoreSubmission>,
+    /// Keys along with their hashes
+    api_keys: Vec<(String, ApiKey)>,
+    bans: Vec<UserBan>,
+    /// Game, nonce and when it was claimed
+    nonces: Vec<(i32, String, DateTime<Utc>)>,
+    idempotency_keys: Vec<IdempotencyKey>,
+}
+
+struct IdempotencyKey {
+    api_key_id: i32,
+    key: String,
+    game_id: i32,
+    request: IdempotentRequest,
+    /// When the request claimed the key, taking over a stale claim claims it anew
+    created_at: DateTime<Utc>,
+}
+
+struct WindowEntry {
+    window: LeaderboardWindow,
+    window_start: Option<DateTime<Utc>>,
+    entry: LeaderboardEntry,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Data {
+    fn next_id(&mut self) -> i32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// Visible entries on the board, best first
+    fn board(&self, game: &Game, board: Board) -> Vec<&LeaderboardEntry> {
+        let mut entries = match board {
+            Board::AllTime => self.entries.iter()
+                .filter(|entry| entry.game_id == game.id)
+                .collect::<Vec<_>>(),
+            Board::Window(window, window_start) => self.window_entries.iter()
+                .filter(|window_entry| window_entry.window == window && window_entry.window_start == window_start)
+                .map(|window_entry| &window_entry.entry)
+                .filter(|entry| entry.game_id == game.id)
+                .collect(),
+            Board::Season(season_id) => self.season_entries.iter()
+                .filter(|entry| entry.game_id == game.id && entry.season_id == Some(season_id))
+                .collect(),
+        };
+        entries.retain(|entry| self.is_visible(entry));
+        let ranking = Ranking::for_game(game);
+        entries.sort_by(|a, b| compare_entries(&ranking, a, b));
+        entries
+    }
+
+    /// Whether the entry is neither invalidated nor of a banned user
+    fn is_visible(&self, entry: &LeaderboardEntry) -> bool {
+        entry.invalidated_at.is_none() && !self.is_banned(entry.game_id, entry.user_id)
+    }
+
+    fn is_banned(&self, game_id: i32, user_id: Uuid) -> bool {
+        self.bans.iter()
+            .any(|ban| ban.user_id == user_id && ban.game_id.is_none_or(|ban_game_id| ban_game_id == game_id))
+    }
+
+    fn window_entries_of<'a>(&'a mut self, entry: &'a LeaderboardEntry) -> impl Iterator<Item = &'a mut LeaderboardEntry> {
+        self.window_entries.iter_mut()
+            .map(|window_entry| &mut window_entry.entry)
+            .filter(|window_entry| window_entry.game_id == entry.game_id && window_entry.user_id == entry.user_id)
+    }
+}
+
+fn new_entry(id: i32, values: EntryValues<'_>, season_id: Option<i32>) -> LeaderboardEntry {
+    let now = Utc::now();
+    LeaderboardEntry {
+        id,
+        score: values.score,
+        score_components: values.score_components.to_vec(),
+        game_id: values.game_id,
+        user_name: values.user_name.to_string(),
+        user_id: values.user_id,
+        free_data: values.free_data.to_string(),
+        created_at: now,
+        updated_at: now,
+        season_id,
+        invalidated_at: None,
+        invalidation_reason: None,
+    }
+}
+
+fn update_entry(entry: &mut LeaderboardEntry, values: EntryValues<'_>, update: ScoreUpdate) {
+    entry.score = match update {
+        ScoreUpdate::Replace => values.score,
+        ScoreUpdate::Add => entry.score + values.score,
+    };
+    entry.score_components = values.score_components.to_vec();
+    entry.user_name = values.user_name.to_string();
+    entry.free_data = values.free_data.to_string();
+    entry.updated_at = Utc::now();
+}
+
+#[async_trait]
+impl LeaderboardStore for MemoryStore {
+    async fn get_games(&self) -> Result<Vec<Game>, ApiError> {
+        Ok(self.data.lock().unwrap().games.values().cloned().collect())
+    }
+
+    async fn get_game(&self, game_id: i32) -> Result<Option<Game>, ApiError> {
+        Ok(self.data.lock().unwrap().games.get(&game_id).cloned())
+    }
+
+    async fn create_game(&self, mut game: Game) -> Result<Game, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        game.id = data.next_id();
+        data.games.insert(game.id, game.clone());
+
+        Ok(game)
+    }
+
+    async fn update_game(&self, game_id: i32, update: GameUpdate) -> Result<Option<Game>, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let Some(game) = data.games.get_mut(&game_id) else {
+            return Ok(None);
+        };
+        if let Some(description) = update.description {
+            game.description = description;
+        }
+        if let Some(score_sort_mode) = update.score_sort_mode {
+            game.score_sort_mode = score_sort_mode;
+        }
+        if let Some(require_signed_submissions) = update.require_signed_submissions {
+            game.require_signed_submissions = require_signed_submissions;
+        }
+
+        Ok(Some(game.clone()))
+    }
+
+    async fn set_signing_secret(&self, game_id: i32, signing_secret: String) -> Result<Option<Game>, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let game = data.games.get_mut(&game_id).map(|game| {
+            game.signing_secret = signing_secret;
+            game.clone()
+        });
+
+        Ok(game)
+    }
+
+    async fn delete_game(&self, game_id: i32) -> Result<Option<Game>, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let game = data.games.remove(&game_id);
+        data.seasons.retain(|season| season.game_id != game_id);
+        data.entries.retain(|entry| entry.game_id != game_id);
+        data.window_entries.retain(|window_entry| window_entry.entry.game_id != game_id);
+        data.season_entries.retain(|entry| entry.game_id != game_id);
+        data.submissions.retain(|submission| submission.game_id != game_id);
+        let now = Utc::now();
+        for (_, api_key) in data.api_keys.iter_mut().filter(|(_, api_key)| api_key.game_id == Some(game_id)) {
+            api_key.revoked_at.get_or_insert(now);
+        }
+        data.bans.retain(|ban| ban.game_id != Some(game_id));
+        data.nonces.retain(|(nonce_game_id, ..)| *nonce_game_id != game_id);
+        data.idempotency_keys.retain(|idempotency_key| idempotency_key.game_id != game_id);
+
+        Ok(game)
+    }
+
+    async fn get_seasons(&self, game_id: i32) -> Result<Vec<Season>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let seasons = data.seasons.iter().rev()
+            .filter(|season| season.game_id == game_id)
+            .cloned()
+            .collect();
+
+        Ok(seasons)
+    }
+
+    async fn get_season(&self, game_id: i32, season_id: i32) -> Result<Option<Season>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let season = data.seasons.iter()
+            .find(|season| season.game_id == game_id && season.id == season_id)
+            .cloned();
+
+        Ok(season)
+    }
+
+    async fn get_open_season(&self, game_id: i32) -> Result<Option<Season>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let season = data.seasons.iter()
+            .find(|season| season.game_id == game_id && season.ended_at.is_none())
+            .cloned();
+
+        Ok(season)
+    }
+
+    async fn create_season(&self, game_id: i32, name: String) -> Result<Season, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let season = Season { id: data.next_id(), game_id, name, started_at: Utc::now(), ended_at: None };
+        data.seasons.push(season.clone());
+
+        Ok(season)
+    }
+
+    async fn close_season(&self, game_id: i32, season_id: i32) -> Result<Option<Season>, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let Some(season) = data.seasons.iter_mut()
+            .find(|season| season.game_id == game_id && season.id == season_id && season.ended_at.is_none()) else {
+            return Ok(None);
+        };
+        season.ended_at = Some(Utc::now());
+        let season = season.clone();
+
+        let (standings, entries) = std::mem::take(&mut data.entries).into_iter()
+            .partition::<Vec<_>, _>(|entry| entry.game_id == game_id);
+        data.entries = entries;
+        data.season_entries.extend(standings.into_iter().map(|entry| LeaderboardEntry { season_id: Some(season_id), ..entry }));
+
+        Ok(Some(season))
+    }
+
+    async fn get_entries(
+        &self,
+        game: &Game,
+        board: Board,
+        after: Option<&EntryCursor>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<LeaderboardEntry>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let ranking = Ranking::for_game(game);
+        let entries = data.board(game, board).into_iter()
+            .filter(|entry| after.is_none_or(|after| ranks_after_cursor(&ranking, entry, after)))
+            .skip(offset as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect();
+
+        Ok(entries)
+    }
+
+    async fn get_board_entry(&self, game_id: i32, board: Board, user_id: Uuid) -> Result<Option<LeaderboardEntry>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let Some(game) = data.games.get(&game_id) else {
+            return Ok(None);
+        };
+        let entry = data.board(game, board).into_iter()
+            .find(|entry| entry.user_id == user_id)
+            .cloned();
+
+        Ok(entry)
+    }
+
+    async fn get_entry(&self, entry_id: i32) -> Result<Option<LeaderboardEntry>, ApiError> {
+        let data = self.data.lock().unwrap();
+
+        Ok(data.entries.iter().find(|entry| entry.id == entry_id).cloned())
+    }
+
+    async fn is_entry_invalidated(&self, game_id: i32, user_id: Uuid) -> Result<bool, ApiError> {
+        let data = self.data.lock().unwrap();
+        let invalidated = data.entries.iter()
+            .any(|entry| entry.game_id == game_id && entry.user_id == user_id && entry.invalidated_at.is_some());
+
+        Ok(invalidated)
+    }
+
+    async fn save_entry(&self, values: EntryValues<'_>, update: ScoreUpdate, season_id: Option<i32>) -> Result<LeaderboardEntry, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let existing = data.entries.iter_mut()
+            .find(|entry| entry.game_id == values.game_id && entry.user_id == values.user_id);
+        if let Some(entry) = existing {
+            update_entry(entry, values, update);
+            entry.season_id = season_id;
+            return Ok(entry.clone());
+        }
+        let entry = new_entry(data.next_id(), values, season_id);
+        data.entries.push(entry.clone());
+
+        Ok(entry)
+    }
+
+    async fn save_window_entry(
+        &self,
+        window: LeaderboardWindow,
+        window_start: Option<DateTime<Utc>>,
+        values: EntryValues<'_>,
+        update: ScoreUpdate,
+    ) -> Result<(), ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let existing = data.window_entries.iter_mut().find(|window_entry| {
+            window_entry.window == window
+                && window_entry.window_start == window_start
+                && window_entry.entry.game_id == values.game_id
+                && window_entry.entry.user_id == values.user_id
+        });
+        match existing {
+            Some(window_entry) => update_entry(&mut window_entry.entry, values, update),
+            None => {
+                let entry = new_entry(data.next_id(), values, None);
+                data.window_entries.push(WindowEntry { window, window_start, entry });
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn delete_entry(&self, entry: &LeaderboardEntry) -> Result<(), ApiError> {
+        let mut data = self.data.lock().unwrap();
+        data.entries.retain(|kept| kept.id != entry.id);
+        data.window_entries.retain(|kept| kept.entry.game_id != entry.game_id || kept.entry.user_id != entry.user_id);
+
+        Ok(())
+    }
+
+    async fn invalidate_entry(&self, entry: &LeaderboardEntry, reason: Option<String>) -> Result<LeaderboardEntry, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let now = Utc::now();
+        for window_entry in data.window_entries_of(entry).filter(|window_entry| window_entry.invalidated_at.is_none()) {
+            window_entry.invalidated_at = Some(now);
+            window_entry.invalidation_reason = reason.clone();
+        }
+        let Some(entry) = data.entries.iter_mut().find(|kept| kept.id == entry.id) else {
+            return Err(sqlx::Error::RowNotFound.into());
+        };
+        entry.invalidated_at = Some(now);
+        entry.invalidation_reason = reason;
+
+        Ok(entry.clone())
+    }
+
+    async fn restore_entry(&self, entry: &LeaderboardEntry) -> Result<LeaderboardEntry, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        for window_entry in data.window_entries_of(entry) {
+            window_entry.invalidated_at = None;
+            window_entry.invalidation_reason = None;
+        }
+        let Some(entry) = data.entries.iter_mut().find(|kept| kept.id == entry.id) else {
+            return Err(sqlx::Error::RowNotFound.into());
+        };
+        entry.invalidated_at = None;
+        entry.invalidation_reason = None;
+
+        Ok(entry.clone())
+    }
+
+    async fn record_submission(
+        &self,
+        values: EntryValues<'_>,
+        accepted: bool,
+        rejected_by: Option<ScoreValidationRule>,
+        season_id: Option<i32>,
+    ) -> Result<(), ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let submission = ScoreSubmission {
+            id: data.next_id() as i64,
+            game_id: values.game_id,
+            user_id: values.user_id,
+            user_name: values.user_name.to_string(),
+            score: values.score,
+            score_components: values.score_components.to_vec(),
+            free_data: values.free_data.to_string(),
+            accepted,
+            rejected_by,
+            season_id,
+            submitted_at: Utc::now(),
+        };
+        data.submissions.push(submission);
+
+        Ok(())
+    }
+
+    async fn get_submissions(&self, game_id: i32, user_id: Uuid, limit: i64, offset: i64) -> Result<Vec<ScoreSubmission>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let submissions = data.submissions.iter().rev()
+            .filter(|submission| submission.game_id == game_id && submission.user_id == user_id)
+            .skip(offset as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect();
+
+        Ok(submissions)
+    }
+
+    async fn get_submission_stats(&self, game_id: i32, user_id: Uuid) -> Result<SubmissionStats, ApiError> {
+        let data = self.data.lock().unwrap();
+        let submissions = data.submissions.iter()
+            .filter(|submission| submission.game_id == game_id && submission.user_id == user_id)
+            .collect::<Vec<_>>();
+        let scores = submissions.iter().map(|submission| submission.score);
+
+        Ok(SubmissionStats {
+            count: submissions.len() as i64,
+            accepted_count: submissions.iter().filter(|submission| submission.accepted).count() as i64,
+            min_score: scores.clone().reduce(f64::min),
+            max_score: scores.clone().reduce(f64::max),
+            avg_score: scores.clone().reduce(|a, b| a + b).map(|total| total / submissions.len() as f64),
+        })
+    }
+
+    async fn get_previous_scores(
+        &self,
+        game_id: i32,
+        user_id: Uuid,
+        since: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<f64>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let scores = data.submissions.iter().rev()
+            .filter(|submission| submission.game_id == game_id && submission.user_id == user_id)
+            .filter(|submission| submission.rejected_by.is_none())
+            .filter(|submission| since.is_none_or(|since| submission.submitted_at >= since))
+            .take(limit.max(0) as usize)
+            .map(|submission| submission.score)
+            .collect();
+
+        Ok(scores)
+    }
+
+    async fn get_last_submitted_at(&self, game_id: i32, user_id: Uuid) -> Result<Option<DateTime<Utc>>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let submitted_at = data.submissions.iter()
+            .filter(|submission| submission.game_id == game_id && submission.user_id == user_id)
+            .map(|submission| submission.submitted_at)
+            .max();
+
+        Ok(submitted_at)
+    }
+
+    async fn get_standing(&self, game: &Game, entry: &LeaderboardEntry) -> Result<(i64, i64), ApiError> {
+        let data = self.data.lock().unwrap();
+        let ranking = Ranking::for_game(game);
+        let board = data.board(game, Board::AllTime);
+        let before = board.iter().filter(|other| compare_entries(&ranking, other, entry).is_lt()).count();
+
+        Ok((before as i64 + 1, board.len() as i64))
+    }
+
+    async fn get_neighbors(
+        &self,
+        game: &Game,
+        entry: &LeaderboardEntry,
+        radius: i64,
+    ) -> Result<(Vec<LeaderboardEntry>, Vec<LeaderboardEntry>), ApiError> {
+        let data = self.data.lock().unwrap();
+        let ranking = Ranking::for_game(game);
+        let board = data.board(game, Board::AllTime);
+        let radius = radius.max(0) as usize;
+
+        let above = board.iter().filter(|other| compare_entries(&ranking, other, entry).is_lt()).collect::<Vec<_>>();
+        let above = above[above.len().saturating_sub(radius)..].iter().map(|other| (**other).clone()).collect();
+        let below = board.iter()
+            .filter(|other| compare_entries(&ranking, other, entry).is_gt())
+            .take(radius)
+            .map(|other| (*other).clone())
+            .collect();
+
+        Ok((above, below))
+    }
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let api_key = data.api_keys.iter()
+            .find(|(hash, api_key)| hash == key_hash && api_key.revoked_at.is_none())
+            .map(|(_, api_key)| api_key.clone());
+
+        Ok(api_key)
+    }
+
+    async fn get_api_keys(&self) -> Result<Vec<ApiKey>, ApiError> {
+        let data = self.data.lock().unwrap();
+
+        Ok(data.api_keys.iter().map(|(_, api_key)| api_key.clone()).collect())
+    }
+
+    async fn get_api_key(&self, key_id: i32) -> Result<Option<ApiKey>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let api_key = data.api_keys.iter()
+            .find(|(_, api_key)| api_key.id == key_id)
+            .map(|(_, api_key)| api_key.clone());
+
+        Ok(api_key)
+    }
+
+    async fn create_api_key(&self, request: &ApiKeyNew, key_hash: &str) -> Result<ApiKey, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let api_key = ApiKey {
+            id: data.next_id(),
+            name: request.name.clone(),
+            role: request.role,
+            game_id: request.game_id,
+            created_at: Utc::now(),
+            revoked_at: None,
+        };
+        data.api_keys.push((key_hash.to_string(), api_key.clone()));
+
+        Ok(api_key)
+    }
+
+    async fn ensure_admin_key(&self, key_hash: &str) -> Result<(), ApiError> {
+        let mut data = self.data.lock().unwrap();
+        if data.api_keys.iter().any(|(hash, _)| hash == key_hash) {
+            return Ok(());
+        }
+        let api_key = ApiKey {
+            id: data.next_id(),
+            name: "configured admin".into(),
+            role: ApiKeyRole::Admin,
+            game_id: None,
+            created_at: Utc::now(),
+            revoked_at: None,
+        };
+        data.api_keys.push((key_hash.to_string(), api_key));
+
+        Ok(())
+    }
+
+    async fn revoke_api_key(&self, key_id: i32) -> Result<Option<ApiKey>, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let api_key = data.api_keys.iter_mut()
+            .find(|(_, api_key)| api_key.id == key_id)
+            .map(|(_, api_key)| {
+                api_key.revoked_at = Some(Utc::now());
+                api_key.clone()
+            });
+
+        Ok(api_key)
+    }
+
+    async fn get_bans(&self, user_id: Option<Uuid>) -> Result<Vec<UserBan>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let bans = data.bans.iter()
+            .filter(|ban| user_id.is_none_or(|user_id| ban.user_id == user_id))
+            .cloned()
+            .collect();
+
+        Ok(bans)
+    }
+
+    async fn get_ban(&self, ban_id: i32) -> Result<Option<UserBan>, ApiError> {
+        let data = self.data.lock().unwrap();
+
+        Ok(data.bans.iter().find(|ban| ban.id == ban_id).cloned())
+    }
+
+    async fn find_ban(&self, user_id: Uuid, game_id: Option<i32>) -> Result<Option<UserBan>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let ban = data.bans.iter()
+            .find(|ban| ban.user_id == user_id && ban.game_id == game_id)
+            .cloned();
+
+        Ok(ban)
+    }
+
+    async fn create_ban(&self, user_id: Uuid, game_id: Option<i32>, reason: String) -> Result<UserBan, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let ban = UserBan { id: data.next_id(), user_id, game_id, reason, created_at: Utc::now() };
+        data.bans.push(ban.clone());
+
+        Ok(ban)
+    }
+
+    async fn delete_ban(&self, ban_id: i32) -> Result<(), ApiError> {
+        self.data.lock().unwrap().bans.retain(|ban| ban.id != ban_id);
+
+        Ok(())
+    }
+
+    async fn is_user_banned(&self, game_id: i32, user_id: Uuid) -> Result<bool, ApiError> {
+        Ok(self.data.lock().unwrap().is_banned(game_id, user_id))
+    }
+
+    async fn get_visible_entries(&self, user_id: Uuid, game_id: Option<i32>) -> Result<Vec<LeaderboardEntry>, ApiError> {
+        let data = self.data.lock().unwrap();
+        let entries = data.entries.iter()
+            .filter(|entry| entry.user_id == user_id && game_id.is_none_or(|game_id| entry.game_id == game_id))
+            .filter(|entry| data.is_visible(entry))
+            .cloned()
+            .collect();
+
+        Ok(entries)
+    }
+
+    async fn claim_nonce(&self, game_id: i32, nonce: &str) -> Result<bool, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let now = Utc::now();
+        let tolerance = chrono::Duration::seconds(2 * signing::TIMESTAMP_TOLERANCE_SECS);
+        data.nonces.retain(|(nonce_game_id, _, claimed_at)| *nonce_game_id != game_id || now - *claimed_at < tolerance);
+        if data.nonces.iter().any(|(nonce_game_id, claimed, _)| *nonce_game_id == game_id && claimed == nonce) {
+            return Ok(false);
+        }
+        data.nonces.push((game_id, nonce.to_string(), now));
+
+        Ok(true)
+    }
+
+    async fn claim_idempotency_key(
+        &self,
+        api_key_id: i32,
+        key: &str,
+        game_id: i32,
+        fingerprint: &str,
+    ) -> Result<Option<IdempotentRequest>, ApiError> {
+        let mut data = self.data.lock().unwrap();
+        let now = Utc::now();
+        let retention = chrono::Duration::seconds(idempotency::RETENTION_SECS);
+        data.idempotency_keys.retain(|idempotency_key| now - idempotency_key.created_at < retention);
+        let lease = chrono::Duration::seconds(idempotency::IN_FLIGHT_LEASE_SECS);
+        if let Some(earlier) = data.idempotency_keys.iter_mut()
+            .find(|idempotency_key| idempotency_key.api_key_id == api_key_id && idempotency_key.key == key) {
+            if earlier.request.response.is_some() || now - earlier.created_at < lease {
+                return Ok(Some(earlier.request.clone()));
+            }
+            earlier.game_id = game_id;
+            earlier.request = IdempotentRequest { fingerprint: fingerprint.to_string(), response: None };
+            earlier.created_at = now;
+            return Ok(None);
+        }
+        data.idempotency_keys.push(IdempotencyKey {
+            api_key_id,
+            key: key.to_string(),
+            game_id,
+            request: IdempotentRequest { fingerprint: fingerprint.to_string(), response: None },
+            created_at: now,
+        });
+
+        Ok(None)
+    }
+
+    async fn complete_idempotency_key(&self, api_key_id: i32, key: &str, status: u16, body: &str) -> Result<(), ApiError> {
+        let mut data = self.data.lock().unwrap();
+        if let Some(idempotency_key) = data.idempotency_keys.iter_mut()
+            .find(|idempotency_key| idempotency_key.api_key_id == api_key_id && idempotency_key.key == key) {
+            idempotency_key.request.response = Some((status, body.to_string()));
+        }
+
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&self, api_key_id: i32, key: &str) -> Result<(), ApiError> {
+        let mut data = self.data.lock().unwrap();
+        data.idempotency_keys.retain(|idempotency_key| idempotency_key.api_key_id != api_key_id || idempotency_key.key != key);
+
+        Ok(())
+    }
+
+    /// Nothing to migrate
+    async fn get_migrations(&self) -> Result<Migrations, ApiError> {
+        Ok(Migrations::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::types::Json;
+
+    fn game() -> Game {
+        Game {
+            id: 0,
+            description: "".into(),
+            score_sort_mode: GameScoreSortMode::HigherIsBetter,
+            score_components: Json(vec![]),
+            score_aggregation: ScoreAggregation::Best,
+            aggregation_last_n: 5,
+            window_timezone: "UTC".into(),
+            window_reset_hour: 0,
+            require_signed_submissions: false,
+            min_score: None,
+            max_score: None,
+            integer_scores: false,
+            max_improvement: None,
+            min_submission_interval_secs: None,
+            signing_secret: "".into(),
+        }
+    }
+
+    async fn submit(store: &MemoryStore, game: &Game, score: f64) -> LeaderboardEntry {
+        let values = EntryValues {
+            game_id: game.id,
+            user_id: Uuid::new_v4(),
+            user_name: "memory",
+            score,
+            score_components: &[],
+            free_data: "",
+        };
+        store.save_entry(values, ScoreUpdate::Replace, None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn pages_through_entries_best_first() {
+        let store = MemoryStore::new();
+        let game = store.create_game(game()).await.unwrap();
+        for score in [20.0, 40.0, 30.0, 10.0] {
+            submit(&store, &game, score).await;
+        }
+
+        let first_page = store.get_entries(&game, Board::AllTime, None, 2, 0).await.unwrap();
+        let last = first_page.last().unwrap();
+        let cursor = EntryCursor::of(last);
+        let second_page = store.get_entries(&game, Board::AllTime, Some(&cursor), 2, 0).await.unwrap();
+
+        let scores = |entries: Vec<LeaderboardEntry>| entries.iter().map(|entry| entry.score).collect::<Vec<_>>();
+        assert_eq!(vec![40.0, 30.0], scores(first_page));
+        assert_eq!(vec![20.0, 10.0], scores(second_page));
+    }
+
+    #[tokio::test]
+    async fn ranks_ties_by_id() {
+        let store = MemoryStore::new();
+        let game = store.create_game(game()).await.unwrap();
+        let first = submit(&store, &game, 10.0).await;
+        let second = submit(&store, &game, 10.0).await;
+
+        assert_eq!((1, 2), store.get_standing(&game, &first).await.unwrap());
+        assert_eq!(Some(2), store.get_rank(&second).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn invalidated_entries_are_hidden() {
+        let store = MemoryStore::new();
+        let game = store.create_game(game()).await.unwrap();
+        let entry = submit(&store, &game, 10.0).await;
+
+        store.invalidate_entry(&entry, Some("cheating".into())).await.unwrap();
+
+        assert_eq!(None, store.get_rank(&entry).await.unwrap());
+        assert!(store.is_entry_invalidated(game.id, entry.user_id).await.unwrap());
+        store.restore_entry(&entry).await.unwrap();
+        assert_eq!(Some(1), store.get_rank(&entry).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn entries_of_banned_users_are_hidden() {
+        let store = MemoryStore::new();
+        let game = store.create_game(game()).await.unwrap();
+        let entry = submit(&store, &game, 10.0).await;
+
+        let ban = store.create_ban(entry.user_id, Some(game.id), "".into()).await.unwrap();
+
+        assert!(store.is_user_banned(game.id, entry.user_id).await.unwrap());
+        assert_eq!(None, store.get_rank(&entry).await.unwrap());
+        store.delete_ban(ban.id).await.unwrap();
+        assert_eq!(1, store.get_visible_entries(entry.user_id, None).await.unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn closing_seasons_archives_the_all_time_entries() {
+        let store = MemoryStore::new();
+        let game = store.create_game(game()).await.unwrap();
+        let season = store.create_season(game.id, "First".into()).await.unwrap();
+        submit(&store, &game, 10.0).await;
+
+        assert!(store.close_season(game.id, season.id).await.unwrap().is_some());
+
+        assert!(store.get_entries(&game, Board::AllTime, None, 10, 0).await.unwrap().is_empty());
+        assert_eq!(1, store.get_entries(&game, Board::Season(season.id), None, 10, 0).await.unwrap().len());
+        assert_eq!(None, store.close_season(game.id, season.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn stale_in_flight_claims_are_taken_over() {
+        let store = MemoryStore::new();
+        assert_eq!(None, store.claim_idempotency_key(1, "retry", 1, "first").await.unwrap());
+        let in_flight = IdempotentRequest { fingerprint: "first".into(), response: None };
+        assert_eq!(Some(in_flight), store.claim_idempotency_key(1, "retry", 1, "first").await.unwrap());
+
+        store.data.lock().unwrap().idempotency_keys[0].created_at -=
+            chrono::Duration::seconds(idempotency::IN_FLIGHT_LEASE_SECS);
+
+        assert_eq!(None, store.claim_idempotency_key(1, "retry", 1, "second").await.unwrap());
+        let taken_over = IdempotentRequest { fingerprint: "second".into(), response: None };
+        assert_eq!(Some(taken_over), store.claim_idempotency_key(1, "retry", 1, "second").await.unwrap());
+    }
+}
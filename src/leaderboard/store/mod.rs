@@ -0,0 +1,291 @@
+//! Storage of games, their seasons, entries and submissions, and the ranks of those entries,
+//! along with the API keys, bans and submission nonces guarding them, and the responses to idempotent submissions.
+//!
+//! [`PgStore`] is what the app runs on. [`MemoryStore`] keeps everything in the process, for tests and demos.
+//! [`TimedStore`] wraps any of them, timing each operation for the metrics.
+
+/// Condition matching entries, aliased as `entry`, which are neither invalidated nor of a banned user
+macro_rules! visible_entry_sql {
+    () => {
+        "entry.invalidated_at IS NULL \
+        AND NOT EXISTS ( \
+            SELECT 1 FROM user_bans ban \
+            WHERE ban.user_id = entry.user_id \
+              AND (ban.game_id IS NULL OR ban.game_id = entry.game_id) \
+        )"
+    };
+}
+
+pub mod memory;
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod timed;
+
+use std::cmp::Ordering;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::migrate::Migrator;
+use sqlx::types::Uuid;
+
+use super::cursor::EntryCursor;
+use super::models::*;
+use super::ranking::Ranking;
+use crate::errors::ApiError;
+
+pub use memory::MemoryStore;
+pub use postgres::PgStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+pub use timed::TimedStore;
+
+/// One of a game's leaderboards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Board {
+    /// Best entries of the open season, or of all time for entries submitted outside a season
+    AllTime,
+    /// Best entries submitted since the start of a time window
+    Window(LeaderboardWindow, Option<DateTime<Utc>>),
+    /// Final standings of a closed season
+    Season(i32),
+}
+
+/// Order of entries on a leaderboard, ties broken by updated_at and then id like on Postgres
+fn compare_entries(ranking: &Ranking, a: &LeaderboardEntry, b: &LeaderboardEntry) -> Ordering {
+    ranking.compare(&a.ranking_keys(), &b.ranking_keys())
+        .then(a.updated_at.cmp(&b.updated_at))
+        .then(a.id.cmp(&b.id))
+}
+
+/// Whether the entry ranks after the cursor's entry, like [`compare_entries`]
+fn ranks_after_cursor(ranking: &Ranking, entry: &LeaderboardEntry, cursor: &EntryCursor) -> bool {
+    ranking.compare(&entry.ranking_keys(), &cursor.keys)
+        .then(entry.updated_at.cmp(&cursor.updated_at))
+        .then(entry.id.cmp(&cursor.id))
+        .is_gt()
+}
+
+/// Versions of the schema migrations, oldest first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Migrations {
+    pub applied: Vec<i64>,
+    /// Known to the app but not applied to the database yet
+    pub pending: Vec<i64>,
+}
+
+impl Migrations {
+    fn of(migrator: &Migrator, applied: Vec<i64>) -> Self {
+        let pending = migrator.iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .map(|migration| migration.version)
+            .filter(|version| !applied.contains(version))
+            .collect();
+
+        Self { applied, pending }
+    }
+}
+
+/// A request made under an idempotency key
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdempotentRequest {
+    pub fingerprint: String,
+    /// Status and body of its response, None while it is still being handled
+    pub response: Option<(u16, String)>,
+}
+
+/// A user's score, as kept on a leaderboard or in their submission history
+#[derive(Debug, Clone, Copy)]
+pub struct EntryValues<'a> {
+    pub game_id: i32,
+    pub user_id: Uuid,
+    pub user_name: &'a str,
+    pub score: f64,
+    pub score_components: &'a [f64],
+    pub free_data: &'a str,
+}
+
+/// How a saved score updates the one already stored for the user, if any
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoreUpdate {
+    Replace,
+    /// Adds the saved score to the stored one, in the same write so concurrent submissions all count
+    Add,
+}
+
+#[async_trait]
+pub trait LeaderboardStore: Send + Sync {
+    async fn get_games(&self) -> Result<Vec<Game>, ApiError>;
+
+    async fn get_game(&self, game_id: i32) -> Result<Option<Game>, ApiError>;
+
+    /// Stores the game under a new id, whatever its id is
+    async fn create_game(&self, game: Game) -> Result<Game, ApiError>;
+
+    async fn update_game(&self, game_id: i32, update: GameUpdate) -> Result<Option<Game>, ApiError>;
+
+    async fn set_signing_secret(&self, game_id: i32, signing_secret: String) -> Result<Option<Game>, ApiError>;
+
+    /// Deletes the game together with all of its seasons, entries and submissions
+    async fn delete_game(&self, game_id: i32) -> Result<Option<Game>, ApiError>;
+
+    /// Every season of the game, newest first
+    async fn get_seasons(&self, game_id: i32) -> Result<Vec<Season>, ApiError>;
+
+    async fn get_season(&self, game_id: i32, season_id: i32) -> Result<Option<Season>, ApiError>;
+
+    async fn get_open_season(&self, game_id: i32) -> Result<Option<Season>, ApiError>;
+
+    async fn create_season(&self, game_id: i32, name: String) -> Result<Season, ApiError>;
+
+    /// Closes the season, archiving the all time entries as its standings and emptying the all time leaderboard.
+    /// Returns None when there is no such open season
+    async fn close_season(&self, game_id: i32, season_id: i32) -> Result<Option<Season>, ApiError>;
+
+    /// A page of the board's visible entries, best first, starting after the cursor
+    async fn get_entries(
+        &self,
+        game: &Game,
+        board: Board,
+        after: Option<&EntryCursor>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<LeaderboardEntry>, ApiError>;
+
+    /// The user's visible entry on the board
+    async fn get_board_entry(&self, game_id: i32, board: Board, user_id: Uuid) -> Result<Option<LeaderboardEntry>, ApiError>;
+
+    /// All time entry, whether it is visible or not
+    async fn get_entry(&self, entry_id: i32) -> Result<Option<LeaderboardEntry>, ApiError>;
+
+    async fn is_entry_invalidated(&self, game_id: i32, user_id: Uuid) -> Result<bool, ApiError>;
+
+    /// Creates or updates the user's all time entry
+    async fn save_entry(&self, values: EntryValues<'_>, update: ScoreUpdate, season_id: Option<i32>) -> Result<LeaderboardEntry, ApiError>;
+
+    /// Creates or updates the user's entry on a time window's leaderboard
+    async fn save_window_entry(
+        &self,
+        window: LeaderboardWindow,
+        window_start: Option<DateTime<Utc>>,
+        values: EntryValues<'_>,
+        update: ScoreUpdate,
+    ) -> Result<(), ApiError>;
+
+    /// Deletes the all time entry along with the user's window entries on the same game
+    async fn delete_entry(&self, entry: &LeaderboardEntry) -> Result<(), ApiError>;
+
+    /// Hides the all time entry along with the user's window entries on the same game
+    async fn invalidate_entry(&self, entry: &LeaderboardEntry, reason: Option<String>) -> Result<LeaderboardEntry, ApiError>;
+
+    /// Shows the all time entry again, along with the user's window entries on the same game
+    async fn restore_entry(&self, entry: &LeaderboardEntry) -> Result<LeaderboardEntry, ApiError>;
+
+    /// Keeps the submission in the user's history. Rejected submissions are kept with the rule they broke
+    async fn record_submission(
+        &self,
+        values: EntryValues<'_>,
+        accepted: bool,
+        rejected_by: Option<ScoreValidationRule>,
+        season_id: Option<i32>,
+    ) -> Result<(), ApiError>;
+
+    /// The user's submissions to the game, newest first
+    async fn get_submissions(&self, game_id: i32, user_id: Uuid, limit: i64, offset: i64) -> Result<Vec<ScoreSubmission>, ApiError>;
+
+    async fn get_submission_stats(&self, game_id: i32, user_id: Uuid) -> Result<SubmissionStats, ApiError>;
+
+    /// Scores of the user's most recent submissions to the game since `since`, newest first. Rejected scores are left out
+    async fn get_previous_scores(
+        &self,
+        game_id: i32,
+        user_id: Uuid,
+        since: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<f64>, ApiError>;
+
+    /// When the user last submitted to the game, rejected submissions included
+    async fn get_last_submitted_at(&self, game_id: i32, user_id: Uuid) -> Result<Option<DateTime<Utc>>, ApiError>;
+
+    /// Rank of the entry on its game's all time leaderboard, and the total number of entries on that leaderboard
+    async fn get_standing(&self, game: &Game, entry: &LeaderboardEntry) -> Result<(i64, i64), ApiError>;
+
+    /// Up to `radius` visible all time entries ranked directly before the entry, and up to `radius` ranked
+    /// directly after it, both best first
+    async fn get_neighbors(
+        &self,
+        game: &Game,
+        entry: &LeaderboardEntry,
+        radius: i64,
+    ) -> Result<(Vec<LeaderboardEntry>, Vec<LeaderboardEntry>), ApiError>;
+
+    /// Usable key with the hash, None when there is no such key or it is revoked
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, ApiError>;
+
+    /// Every API key, oldest first
+    async fn get_api_keys(&self) -> Result<Vec<ApiKey>, ApiError>;
+
+    async fn get_api_key(&self, key_id: i32) -> Result<Option<ApiKey>, ApiError>;
+
+    async fn create_api_key(&self, request: &ApiKeyNew, key_hash: &str) -> Result<ApiKey, ApiError>;
+
+    /// Stores an admin key with the hash, unless one is already stored
+    async fn ensure_admin_key(&self, key_hash: &str) -> Result<(), ApiError>;
+
+    /// Returns None when there is no such key
+    async fn revoke_api_key(&self, key_id: i32) -> Result<Option<ApiKey>, ApiError>;
+
+    /// Every ban, or those of a user, oldest first
+    async fn get_bans(&self, user_id: Option<Uuid>) -> Result<Vec<UserBan>, ApiError>;
+
+    async fn get_ban(&self, ban_id: i32) -> Result<Option<UserBan>, ApiError>;
+
+    /// The user's ban from the game, or from every game when there is no game
+    async fn find_ban(&self, user_id: Uuid, game_id: Option<i32>) -> Result<Option<UserBan>, ApiError>;
+
+    async fn create_ban(&self, user_id: Uuid, game_id: Option<i32>, reason: String) -> Result<UserBan, ApiError>;
+
+    async fn delete_ban(&self, ban_id: i32) -> Result<(), ApiError>;
+
+    /// Whether a ban from the game, or from every game, applies to the user
+    async fn is_user_banned(&self, game_id: i32, user_id: Uuid) -> Result<bool, ApiError>;
+
+    /// The user's visible all time entries, on one game or on every game
+    async fn get_visible_entries(&self, user_id: Uuid, game_id: Option<i32>) -> Result<Vec<LeaderboardEntry>, ApiError>;
+
+    /// Records the nonce as used for the game, purging nonces too old to pass the timestamp check anyway.
+    /// Returns false when the nonce was already used
+    async fn claim_nonce(&self, game_id: i32, nonce: &str) -> Result<bool, ApiError>;
+
+    /// Claims the API key's idempotency key for a request to the game, purging keys older than the retention window.
+    /// Returns the earlier request which already claimed it instead, when there is one. An earlier claim without
+    /// a response older than the in-flight lease is taken over
+    async fn claim_idempotency_key(
+        &self,
+        api_key_id: i32,
+        key: &str,
+        game_id: i32,
+        fingerprint: &str,
+    ) -> Result<Option<IdempotentRequest>, ApiError>;
+
+    /// Keeps the response to the claimed key's request, for replaying it to retries
+    async fn complete_idempotency_key(&self, api_key_id: i32, key: &str, status: u16, body: &str) -> Result<(), ApiError>;
+
+    /// Lets the next request with the claimed key be handled afresh
+    async fn release_idempotency_key(&self, api_key_id: i32, key: &str) -> Result<(), ApiError>;
+
+    /// Schema migrations of the database. Fails when the database can not be reached
+    async fn get_migrations(&self) -> Result<Migrations, ApiError>;
+
+    /// All time rank of the entry, or None when it is not shown on the leaderboard
+    async fn get_rank(&self, entry: &LeaderboardEntry) -> Result<Option<i64>, ApiError> {
+        let visible = self.get_board_entry(entry.game_id, Board::AllTime, entry.user_id).await?
+            .is_some_and(|visible| visible.id == entry.id);
+        let Some(game) = self.get_game(entry.game_id).await?.filter(|_| visible) else {
+            return Ok(None);
+        };
+        let (rank, _) = self.get_standing(&game, entry).await?;
+
+        Ok(Some(rank))
+    }
+}
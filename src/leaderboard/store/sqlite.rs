@@ -0,0 +1,945 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::migrate::Migrator;
+use sqlx::types::{Json, Uuid};
+use sqlx::SqlitePool;
+
+use super::{compare_entries, ranks_after_cursor, Board, EntryValues, IdempotentRequest, LeaderboardStore, Migrations, ScoreUpdate};
+use crate::errors::ApiError;
+use crate::leaderboard::cursor::EntryCursor;
+use crate::leaderboard::idempotency;
+use crate::leaderboard::models::*;
+use crate::leaderboard::ranking::Ranking;
+use crate::leaderboard::signing;
+
+/// Counterparts of the Postgres migrations, under the same versions
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations_sqlite");
+
+/// Keeps everything in a SQLite database, for deployments without Postgres.
+/// Boards are filtered in SQL but ranked in the app, which is fine for the few thousand entries of a game jam.
+///
+/// Statements with a `RETURNING` clause are fetched in full: SQLite only commits them once they are stepped
+/// to completion, which `fetch_one` leaves to the next use of the connection
+#[derive(Clone)]
+pub struct SqliteStore {
+    db: SqlitePool,
+}
+
+impl SqliteStore {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Visible entries on the board, best first
+    async fn board(&self, game: &Game, board: Board) -> Result<Vec<LeaderboardEntry>, ApiError> {
+        let sql = match board {
+            Board::AllTime => concat!(
+                "SELECT * \
+                FROM leaderboard_entries entry \
+                WHERE game_id = $1 \
+                  AND ", visible_entry_sql!(), ";"),
+            Board::Window(..) => concat!(
+                "SELECT id, score, score_components, game_id, user_name, user_id, free_data, \
+                    created_at, updated_at, NULL AS season_id, invalidated_at, invalidation_reason \
+                FROM leaderboard_window_entries entry \
+                WHERE game_id = $1 \
+                  AND time_window = $2 \
+                  AND window_start = $3 \
+                  AND ", visible_entry_sql!(), ";"),
+            Board::Season(_) => concat!(
+                "SELECT entry_id AS id, score, score_components, game_id, user_name, user_id, free_data, \
+                    created_at, updated_at, season_id, invalidated_at, invalidation_reason \
+                FROM season_entries entry \
+                WHERE game_id = $1 \
+                  AND season_id = $4 \
+                  AND ", visible_entry_sql!(), ";"),
+        };
+        let (window, window_start, season_id) = match board {
+            Board::AllTime => (LeaderboardWindow::AllTime, None, None),
+            Board::Window(window, window_start) => (window, window_start, None),
+            Board::Season(season_id) => (LeaderboardWindow::AllTime, None, Some(season_id)),
+        };
+        let mut entries = sqlx::query_as::<_, EntryRow>(sql)
+            .bind(game.id)
+            .bind(window)
+            .bind(window_start)
+            .bind(season_id)
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(LeaderboardEntry::from)
+            .collect::<Vec<_>>();
+        let ranking = Ranking::for_game(game);
+        entries.sort_by(|a, b| compare_entries(&ranking, a, b));
+
+        Ok(entries)
+    }
+}
+
+/// Entry as stored, with its score components as a JSON array
+#[derive(sqlx::FromRow)]
+struct EntryRow {
+    id: i32,
+    score: f64,
+    score_components: Json<Vec<f64>>,
+    game_id: i32,
+    user_name: String,
+    user_id: Uuid,
+    free_data: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    season_id: Option<i32>,
+    invalidated_at: Option<DateTime<Utc>>,
+    invalidation_reason: Option<String>,
+}
+
+impl From<EntryRow> for LeaderboardEntry {
+    fn from(row: EntryRow) -> Self {
+        Self {
+            id: row.id,
+            score: row.score,
+            score_components: row.score_components.0,
+            game_id: row.game_id,
+            user_name: row.user_name,
+            user_id: row.user_id,
+            free_data: row.free_data,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            season_id: row.season_id,
+            invalidated_at: row.invalidated_at,
+            invalidation_reason: row.invalidation_reason,
+        }
+    }
+}
+
+/// Submission as stored, with its score components as a JSON array
+#[derive(sqlx::FromRow)]
+struct SubmissionRow {
+    id: i64,
+    game_id: i32,
+    user_id: Uuid,
+    user_name: String,
+    score: f64,
+    score_components: Json<Vec<f64>>,
+    free_data: String,
+    accepted: bool,
+    rejected_by: Option<ScoreValidationRule>,
+    season_id: Option<i32>,
+    submitted_at: DateTime<Utc>,
+}
+
+impl From<SubmissionRow> for ScoreSubmission {
+    fn from(row: SubmissionRow) -> Self {
+        Self {
+            id: row.id,
+            game_id: row.game_id,
+            user_id: row.user_id,
+            user_name: row.user_name,
+            score: row.score,
+            score_components: row.score_components.0,
+            free_data: row.free_data,
+            accepted: row.accepted,
+            rejected_by: row.rejected_by,
+            season_id: row.season_id,
+            submitted_at: row.submitted_at,
+        }
+    }
+}
+
+#[async_trait]
+impl LeaderboardStore for SqliteStore {
+    async fn get_games(&self) -> Result<Vec<Game>, ApiError> {
+        let games = sqlx::query_as::<_, Game>("SELECT * FROM games")
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(games)
+    }
+
+    async fn get_game(&self, game_id: i32) -> Result<Option<Game>, ApiError> {
+        let game = sqlx::query_as::<_, Game>(
+            "SELECT * FROM games WHERE id = $1;")
+            .bind(game_id)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(game)
+    }
+
+    async fn create_game(&self, game: Game) -> Result<Game, ApiError> {
+        let game = sqlx::query_as::<_, Game>(
+            "INSERT INTO games \
+                (description, score_sort_mode, score_components, score_aggregation, aggregation_last_n, \
+                    window_timezone, window_reset_hour, require_signed_submissions, signing_secret, \
+                    min_score, max_score, integer_scores, max_improvement, min_submission_interval_secs) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14) \
+            RETURNING *",
+        )
+            .bind(game.description)
+            .bind(game.score_sort_mode)
+            .bind(game.score_components)
+            .bind(game.score_aggregation)
+            .bind(game.aggregation_last_n)
+            .bind(game.window_timezone)
+            .bind(game.window_reset_hour)
+            .bind(game.require_signed_submissions)
+            .bind(game.signing_secret)
+            .bind(game.min_score)
+            .bind(game.max_score)
+            .bind(game.integer_scores)
+            .bind(game.max_improvement)
+            .bind(game.min_submission_interval_secs)
+            .fetch_all(&self.db)
+            .await?
+            .pop()
+            .ok_or(sqlx::Error::RowNotFound)?;
+
+        Ok(game)
+    }
+
+    async fn update_game(&self, game_id: i32, update: GameUpdate) -> Result<Option<Game>, ApiError> {
+        let game = sqlx::query_as::<_, Game>(
+            "UPDATE games SET \
+                description = COALESCE($2, description), \
+                score_sort_mode = COALESCE($3, score_sort_mode), \
+                require_signed_submissions = COALESCE($4, require_signed_submissions) \
+            WHERE id = $1 \
+            RETURNING *",
+        )
+            .bind(game_id)
+            .bind(update.description)
+            .bind(update.score_sort_mode)
+            .bind(update.require_signed_submissions)
+            .fetch_all(&self.db)
+            .await?
+            .pop();
+
+        Ok(game)
+    }
+
+    async fn set_signing_secret(&self, game_id: i32, signing_secret: String) -> Result<Option<Game>, ApiError> {
+        let game = sqlx::query_as::<_, Game>(
+            "UPDATE games SET signing_secret = $2 WHERE id = $1 RETURNING *;")
+            .bind(game_id)
+            .bind(signing_secret)
+            .fetch_all(&self.db)
+            .await?
+            .pop();
+
+        Ok(game)
+    }
+
+    async fn delete_game(&self, game_id: i32) -> Result<Option<Game>, ApiError> {
+        let mut transaction = self.db.begin().await?;
+        // children first, the foreign keys do not cascade
+        for table in [
+            "idempotency_keys",
+            "submission_nonces",
+            "score_submissions",
+            "season_entries",
+            "leaderboard_window_entries",
+            "leaderboard_entries",
+            "seasons",
+            "user_bans",
+        ] {
+            sqlx::query(&format!("DELETE FROM {table} WHERE game_id = $1;"))
+                .bind(game_id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        // API keys are kept on record, revoked
+        sqlx::query("UPDATE api_keys SET revoked_at = COALESCE(revoked_at, $2) WHERE game_id = $1;")
+            .bind(game_id)
+            .bind(Utc::now())
+            .execute(&mut *transaction)
+            .await?;
+        let game = sqlx::query_as::<_, Game>(
+            "DELETE FROM games WHERE id = $1 RETURNING *;")
+            .bind(game_id)
+            .fetch_all(&mut *transaction)
+            .await?
+            .pop();
+        if game.is_some() {
+            transaction.commit().await?;
+        }
+
+        Ok(game)
+    }
+
+    async fn get_seasons(&self, game_id: i32) -> Result<Vec<Season>, ApiError> {
+        let seasons = sqlx::query_as::<_, Season>(
+            "SELECT * FROM seasons WHERE game_id = $1 ORDER BY id DESC;")
+            .bind(game_id)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(seasons)
+    }
+
+    async fn get_season(&self, game_id: i32, season_id: i32) -> Result<Option<Season>, ApiError> {
+        let season = sqlx::query_as::<_, Season>(
+            "SELECT * FROM seasons WHERE game_id = $1 AND id = $2;")
+            .bind(game_id)
+            .bind(season_id)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(season)
+    }
+
+    async fn get_open_season(&self, game_id: i32) -> Result<Option<Season>, ApiError> {
+        let season = sqlx::query_as::<_, Season>(
+            "SELECT * FROM seasons WHERE game_id = $1 AND ended_at IS NULL;")
+            .bind(game_id)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(season)
+    }
+
+    async fn create_season(&self, game_id: i32, name: String) -> Result<Season, ApiError> {
+        let season = sqlx::query_as::<_, Season>(
+            "INSERT INTO seasons (game_id, name, started_at) VALUES ($1, $2, $3) RETURNING *;")
+            .bind(game_id)
+            .bind(name)
+            .bind(Utc::now())
+            .fetch_all(&self.db)
+            .await?
+            .pop()
+            .ok_or(sqlx::Error::RowNotFound)?;
+
+        Ok(season)
+    }
+
+    async fn close_season(&self, game_id: i32, season_id: i32) -> Result<Option<Season>, ApiError> {
+        let mut transaction = self.db.begin().await?;
+
+        let closed_season = sqlx::query_as::<_, Season>(
+            "UPDATE seasons SET ended_at = $3 \
+            WHERE game_id = $1 AND id = $2 AND ended_at IS NULL \
+            RETURNING *;")
+            .bind(game_id)
+            .bind(season_id)
+            .bind(Utc::now())
+            .fetch_all(&mut *transaction)
+            .await?
+            .pop();
+        let Some(season) = closed_season else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            "INSERT INTO season_entries \
+                (season_id, entry_id, game_id, score, score_components, user_name, user_id, free_data, \
+                    created_at, updated_at, invalidated_at, invalidation_reason) \
+            SELECT $2, id, game_id, score, score_components, user_name, user_id, free_data, created_at, updated_at, \
+                invalidated_at, invalidation_reason \
+            FROM leaderboard_entries \
+            WHERE game_id = $1;")
+            .bind(game_id)
+            .bind(season_id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query("DELETE FROM leaderboard_entries WHERE game_id = $1;")
+            .bind(game_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(Some(season))
+    }
+
+    async fn get_entries(
+        &self,
+        game: &Game,
+        board: Board,
+        after: Option<&EntryCursor>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<LeaderboardEntry>, ApiError> {
+        let ranking = Ranking::for_game(game);
+        let entries = self.board(game, board).await?.into_iter()
+            .filter(|entry| after.is_none_or(|after| ranks_after_cursor(&ranking, entry, after)))
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect();
+
+        Ok(entries)
+    }
+
+    async fn get_board_entry(&self, game_id: i32, board: Board, user_id: Uuid) -> Result<Option<LeaderboardEntry>, ApiError> {
+        let Some(game) = self.get_game(game_id).await? else {
+            return Ok(None);
+        };
+        let entry = self.board(&game, board).await?.into_iter()
+            .find(|entry| entry.user_id == user_id);
+
+        Ok(entry)
+    }
+
+    async fn get_entry(&self, entry_id: i32) -> Result<Option<LeaderboardEntry>, ApiError> {
+        let entry = sqlx::query_as::<_, EntryRow>(
+            "SELECT * FROM leaderboard_entries WHERE id = $1;")
+            .bind(entry_id)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(entry.map(LeaderboardEntry::from))
+    }
+
+    async fn is_entry_invalidated(&self, game_id: i32, user_id: Uuid) -> Result<bool, ApiError> {
+        let invalidated = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS ( \
+                SELECT 1 FROM leaderboard_entries \
+                WHERE game_id = $1 \
+                  AND user_id = $2 \
+                  AND invalidated_at IS NOT NULL \
+            );")
+            .bind(game_id)
+            .bind(user_id)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok(invalidated)
+    }
+
+    async fn save_entry(&self, values: EntryValues<'_>, update: ScoreUpdate, season_id: Option<i32>) -> Result<LeaderboardEntry, ApiError> {
+        let entry = sqlx::query_as::<_, EntryRow>(
+            "INSERT INTO leaderboard_entries \
+                (game_id, score, score_components, user_name, free_data, user_id, season_id, created_at, updated_at) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8) \
+            ON CONFLICT (game_id, user_id) DO UPDATE SET \
+            score = CASE WHEN $9 THEN leaderboard_entries.score + excluded.score ELSE excluded.score END, \
+            score_components = excluded.score_components, \
+            user_name = excluded.user_name, free_data = excluded.free_data, \
+            season_id = excluded.season_id, updated_at = excluded.updated_at \
+            RETURNING *;",
+        )
+            .bind(values.game_id)
+            .bind(values.score)
+            .bind(Json(values.score_components))
+            .bind(values.user_name)
+            .bind(values.free_data)
+            .bind(values.user_id)
+            .bind(season_id)
+            .bind(Utc::now())
+            .bind(update == ScoreUpdate::Add)
+            .fetch_all(&self.db)
+            .await?
+            .pop()
+            .ok_or(sqlx::Error::RowNotFound)?;
+
+        Ok(entry.into())
+    }
+
+    async fn save_window_entry(
+        &self,
+        window: LeaderboardWindow,
+        window_start: Option<DateTime<Utc>>,
+        values: EntryValues<'_>,
+        update: ScoreUpdate,
+    ) -> Result<(), ApiError> {
+        sqlx::query(
+            "INSERT INTO leaderboard_window_entries \
+                (game_id, time_window, window_start, score, score_components, user_name, free_data, user_id, \
+                    created_at, updated_at) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $9) \
+            ON CONFLICT (game_id, time_window, window_start, user_id) DO UPDATE SET \
+            score = CASE WHEN $10 THEN leaderboard_window_entries.score + excluded.score ELSE excluded.score END, \
+            score_components = excluded.score_components, \
+            user_name = excluded.user_name, free_data = excluded.free_data, \
+            updated_at = excluded.updated_at;",
+        )
+            .bind(values.game_id)
+            .bind(window)
+            .bind(window_start)
+            .bind(values.score)
+            .bind(Json(values.score_components))
+            .bind(values.user_name)
+            .bind(values.free_data)
+            .bind(values.user_id)
+            .bind(Utc::now())
+            .bind(update == ScoreUpdate::Add)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_entry(&self, entry: &LeaderboardEntry) -> Result<(), ApiError> {
+        let mut transaction = self.db.begin().await?;
+        sqlx::query("DELETE FROM leaderboard_entries WHERE id = $1;")
+            .bind(entry.id)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query("DELETE FROM leaderboard_window_entries WHERE game_id = $1 AND user_id = $2;")
+            .bind(entry.game_id)
+            .bind(entry.user_id)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn invalidate_entry(&self, entry: &LeaderboardEntry, reason: Option<String>) -> Result<LeaderboardEntry, ApiError> {
+        let mut transaction = self.db.begin().await?;
+        let now = Utc::now();
+        let entry = sqlx::query_as::<_, EntryRow>(
+            "UPDATE leaderboard_entries \
+            SET invalidated_at = $3, invalidation_reason = $2 \
+            WHERE id = $1 \
+            RETURNING *;")
+            .bind(entry.id)
+            .bind(&reason)
+            .bind(now)
+            .fetch_all(&mut *transaction)
+            .await?
+            .pop()
+            .ok_or(sqlx::Error::RowNotFound)?;
+        sqlx::query(
+            "UPDATE leaderboard_window_entries \
+            SET invalidated_at = $4, invalidation_reason = $3 \
+            WHERE game_id = $1 \
+              AND user_id = $2 \
+              AND invalidated_at IS NULL;")
+            .bind(entry.game_id)
+            .bind(entry.user_id)
+            .bind(&reason)
+            .bind(now)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+
+        Ok(entry.into())
+    }
+
+    async fn restore_entry(&self, entry: &LeaderboardEntry) -> Result<LeaderboardEntry, ApiError> {
+        let mut transaction = self.db.begin().await?;
+        let entry = sqlx::query_as::<_, EntryRow>(
+            "UPDATE leaderboard_entries \
+            SET invalidated_at = NULL, invalidation_reason = NULL \
+            WHERE id = $1 \
+            RETURNING *;")
+            .bind(entry.id)
+            .fetch_all(&mut *transaction)
+            .await?
+            .pop()
+            .ok_or(sqlx::Error::RowNotFound)?;
+        sqlx::query(
+            "UPDATE leaderboard_window_entries \
+            SET invalidated_at = NULL, invalidation_reason = NULL \
+            WHERE game_id = $1 \
+              AND user_id = $2;")
+            .bind(entry.game_id)
+            .bind(entry.user_id)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+
+        Ok(entry.into())
+    }
+
+    async fn record_submission(
+        &self,
+        values: EntryValues<'_>,
+        accepted: bool,
+        rejected_by: Option<ScoreValidationRule>,
+        season_id: Option<i32>,
+    ) -> Result<(), ApiError> {
+        sqlx::query(
+            "INSERT INTO score_submissions \
+                (game_id, user_id, user_name, score, score_components, free_data, accepted, rejected_by, season_id, submitted_at) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10);",
+        )
+            .bind(values.game_id)
+            .bind(values.user_id)
+            .bind(values.user_name)
+            .bind(values.score)
+            .bind(Json(values.score_components))
+            .bind(values.free_data)
+            .bind(accepted)
+            .bind(rejected_by)
+            .bind(season_id)
+            .bind(Utc::now())
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_submissions(&self, game_id: i32, user_id: Uuid, limit: i64, offset: i64) -> Result<Vec<ScoreSubmission>, ApiError> {
+        let submissions = sqlx::query_as::<_, SubmissionRow>(
+            "SELECT * \
+                FROM score_submissions \
+                WHERE game_id = $1 \
+                  AND user_id = $2 \
+                ORDER BY id DESC \
+                LIMIT $3 OFFSET $4;")
+            .bind(game_id)
+            .bind(user_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(submissions.into_iter().map(ScoreSubmission::from).collect())
+    }
+
+    async fn get_submission_stats(&self, game_id: i32, user_id: Uuid) -> Result<SubmissionStats, ApiError> {
+        let stats = sqlx::query_as::<_, SubmissionStats>(
+            "SELECT \
+                    COUNT(*) AS count, \
+                    COUNT(*) FILTER (WHERE accepted) AS accepted_count, \
+                    MIN(score) AS min_score, \
+                    MAX(score) AS max_score, \
+                    AVG(score) AS avg_score \
+                FROM score_submissions \
+                WHERE game_id = $1 \
+                  AND user_id = $2;")
+            .bind(game_id)
+            .bind(user_id)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok(stats)
+    }
+
+    async fn get_previous_scores(
+        &self,
+        game_id: i32,
+        user_id: Uuid,
+        since: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<f64>, ApiError> {
+        let scores = sqlx::query_scalar::<_, f64>(
+            "SELECT score \
+                FROM score_submissions \
+                WHERE game_id = $1 \
+                  AND user_id = $2 \
+                  AND rejected_by IS NULL \
+                  AND ($3 IS NULL OR submitted_at >= $3) \
+                ORDER BY id DESC \
+                LIMIT $4;")
+            .bind(game_id)
+            .bind(user_id)
+            .bind(since)
+            .bind(limit)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(scores)
+    }
+
+    async fn get_last_submitted_at(&self, game_id: i32, user_id: Uuid) -> Result<Option<DateTime<Utc>>, ApiError> {
+        let submitted_at = sqlx::query_scalar::<_, Option<DateTime<Utc>>>(
+            "SELECT submitted_at FROM score_submissions WHERE game_id = $1 AND user_id = $2 ORDER BY id DESC LIMIT 1;")
+            .bind(game_id)
+            .bind(user_id)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(submitted_at.flatten())
+    }
+
+    async fn get_standing(&self, game: &Game, entry: &LeaderboardEntry) -> Result<(i64, i64), ApiError> {
+        let ranking = Ranking::for_game(game);
+        let board = self.board(game, Board::AllTime).await?;
+        let before = board.iter().filter(|other| compare_entries(&ranking, other, entry).is_lt()).count();
+
+        Ok((before as i64 + 1, board.len() as i64))
+    }
+
+    async fn get_neighbors(
+        &self,
+        game: &Game,
+        entry: &LeaderboardEntry,
+        radius: i64,
+    ) -> Result<(Vec<LeaderboardEntry>, Vec<LeaderboardEntry>), ApiError> {
+        let ranking = Ranking::for_game(game);
+        let radius = radius.max(0) as usize;
+        let (mut above, mut below) = self.board(game, Board::AllTime).await?.into_iter()
+            .filter(|other| other.id != entry.id)
+            .partition::<Vec<_>, _>(|other| compare_entries(&ranking, other, entry).is_lt());
+        above.drain(..above.len().saturating_sub(radius));
+        below.truncate(radius);
+
+        Ok((above, below))
+    }
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, ApiError> {
+        let api_key = sqlx::query_as::<_, ApiKey>(
+            "SELECT id, name, role, game_id, created_at, revoked_at \
+            FROM api_keys \
+            WHERE key_hash = $1 \
+              AND revoked_at IS NULL;")
+            .bind(key_hash)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(api_key)
+    }
+
+    async fn get_api_keys(&self) -> Result<Vec<ApiKey>, ApiError> {
+        let api_keys = sqlx::query_as::<_, ApiKey>(
+            "SELECT id, name, role, game_id, created_at, revoked_at FROM api_keys ORDER BY id;")
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(api_keys)
+    }
+
+    async fn get_api_key(&self, key_id: i32) -> Result<Option<ApiKey>, ApiError> {
+        let api_key = sqlx::query_as::<_, ApiKey>(
+            "SELECT id, name, role, game_id, created_at, revoked_at FROM api_keys WHERE id = $1;")
+            .bind(key_id)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(api_key)
+    }
+
+    async fn create_api_key(&self, request: &ApiKeyNew, key_hash: &str) -> Result<ApiKey, ApiError> {
+        let api_key = sqlx::query_as::<_, ApiKey>(
+            "INSERT INTO api_keys (name, key_hash, role, game_id, created_at) \
+            VALUES ($1, $2, $3, $4, $5) \
+            RETURNING id, name, role, game_id, created_at, revoked_at;")
+            .bind(&request.name)
+            .bind(key_hash)
+            .bind(request.role)
+            .bind(request.game_id)
+            .bind(Utc::now())
+            .fetch_all(&self.db)
+            .await?
+            .pop()
+            .ok_or(sqlx::Error::RowNotFound)?;
+
+        Ok(api_key)
+    }
+
+    async fn ensure_admin_key(&self, key_hash: &str) -> Result<(), ApiError> {
+        sqlx::query(
+            "INSERT INTO api_keys (name, key_hash, role, created_at) \
+            VALUES ('configured admin', $1, 'Admin', $2) \
+            ON CONFLICT (key_hash) DO NOTHING;")
+            .bind(key_hash)
+            .bind(Utc::now())
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_api_key(&self, key_id: i32) -> Result<Option<ApiKey>, ApiError> {
+        let api_key = sqlx::query_as::<_, ApiKey>(
+            "UPDATE api_keys SET revoked_at = $2 \
+            WHERE id = $1 \
+            RETURNING id, name, role, game_id, created_at, revoked_at;")
+            .bind(key_id)
+            .bind(Utc::now())
+            .fetch_all(&self.db)
+            .await?
+            .pop();
+
+        Ok(api_key)
+    }
+
+    async fn get_bans(&self, user_id: Option<Uuid>) -> Result<Vec<UserBan>, ApiError> {
+        let bans = sqlx::query_as::<_, UserBan>(
+            "SELECT * FROM user_bans \
+            WHERE ($1 IS NULL OR user_id = $1) \
+            ORDER BY id;")
+            .bind(user_id)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(bans)
+    }
+
+    async fn get_ban(&self, ban_id: i32) -> Result<Option<UserBan>, ApiError> {
+        let ban = sqlx::query_as::<_, UserBan>(
+            "SELECT * FROM user_bans WHERE id = $1;")
+            .bind(ban_id)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(ban)
+    }
+
+    async fn find_ban(&self, user_id: Uuid, game_id: Option<i32>) -> Result<Option<UserBan>, ApiError> {
+        let ban = sqlx::query_as::<_, UserBan>(
+            "SELECT * FROM user_bans WHERE user_id = $1 AND game_id IS $2;")
+            .bind(user_id)
+            .bind(game_id)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(ban)
+    }
+
+    async fn create_ban(&self, user_id: Uuid, game_id: Option<i32>, reason: String) -> Result<UserBan, ApiError> {
+        let ban = sqlx::query_as::<_, UserBan>(
+            "INSERT INTO user_bans (user_id, game_id, reason, created_at) \
+            VALUES ($1, $2, $3, $4) \
+            RETURNING *;")
+            .bind(user_id)
+            .bind(game_id)
+            .bind(reason)
+            .bind(Utc::now())
+            .fetch_all(&self.db)
+            .await?
+            .pop()
+            .ok_or(sqlx::Error::RowNotFound)?;
+
+        Ok(ban)
+    }
+
+    async fn delete_ban(&self, ban_id: i32) -> Result<(), ApiError> {
+        sqlx::query("DELETE FROM user_bans WHERE id = $1;")
+            .bind(ban_id)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn is_user_banned(&self, game_id: i32, user_id: Uuid) -> Result<bool, ApiError> {
+        let banned = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS ( \
+                SELECT 1 FROM user_bans \
+                WHERE user_id = $2 \
+                  AND (game_id IS NULL OR game_id = $1) \
+            );")
+            .bind(game_id)
+            .bind(user_id)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok(banned)
+    }
+
+    async fn get_visible_entries(&self, user_id: Uuid, game_id: Option<i32>) -> Result<Vec<LeaderboardEntry>, ApiError> {
+        let entries = sqlx::query_as::<_, EntryRow>(concat!(
+            "SELECT * \
+                FROM leaderboard_entries entry \
+                WHERE user_id = $1 \
+                  AND ($2 IS NULL OR game_id = $2) \
+                  AND ", visible_entry_sql!(), ";"))
+            .bind(user_id)
+            .bind(game_id)
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(entries.into_iter().map(LeaderboardEntry::from).collect())
+    }
+
+    async fn claim_nonce(&self, game_id: i32, nonce: &str) -> Result<bool, ApiError> {
+        let now = Utc::now();
+        sqlx::query("DELETE FROM submission_nonces WHERE game_id = $1 AND created_at < $2;")
+            .bind(game_id)
+            .bind(now - chrono::Duration::seconds(2 * signing::TIMESTAMP_TOLERANCE_SECS))
+            .execute(&self.db)
+            .await?;
+        let claimed = sqlx::query(
+            "INSERT INTO submission_nonces (game_id, nonce, created_at) VALUES ($1, $2, $3) \
+            ON CONFLICT DO NOTHING;")
+            .bind(game_id)
+            .bind(nonce)
+            .bind(now)
+            .execute(&self.db)
+            .await?
+            .rows_affected() == 1;
+
+        Ok(claimed)
+    }
+
+    async fn claim_idempotency_key(
+        &self,
+        api_key_id: i32,
+        key: &str,
+        game_id: i32,
+        fingerprint: &str,
+    ) -> Result<Option<IdempotentRequest>, ApiError> {
+        let now = Utc::now();
+        sqlx::query("DELETE FROM idempotency_keys WHERE created_at < $1;")
+            .bind(now - chrono::Duration::seconds(idempotency::RETENTION_SECS))
+            .execute(&self.db)
+            .await?;
+        // The earlier request may be released between the insert and the select, the claim is then tried again
+        loop {
+            let claimed = sqlx::query(
+                "INSERT INTO idempotency_keys (api_key_id, key, game_id, fingerprint, created_at) VALUES ($1, $2, $3, $4, $5) \
+                ON CONFLICT DO NOTHING;")
+                .bind(api_key_id)
+                .bind(key)
+                .bind(game_id)
+                .bind(fingerprint)
+                .bind(now)
+                .execute(&self.db)
+                .await?
+                .rows_affected() == 1;
+            if claimed {
+                return Ok(None);
+            }
+            let taken_over = sqlx::query(
+                "UPDATE idempotency_keys SET game_id = $3, fingerprint = $4, created_at = $5 \
+                WHERE api_key_id = $1 AND key = $2 AND response_status IS NULL AND created_at < $6;")
+                .bind(api_key_id)
+                .bind(key)
+                .bind(game_id)
+                .bind(fingerprint)
+                .bind(now)
+                .bind(now - chrono::Duration::seconds(idempotency::IN_FLIGHT_LEASE_SECS))
+                .execute(&self.db)
+                .await?
+                .rows_affected() == 1;
+            if taken_over {
+                return Ok(None);
+            }
+            let earlier = sqlx::query_as::<_, (String, Option<i64>, Option<String>)>(
+                "SELECT fingerprint, response_status, response_body FROM idempotency_keys \
+                WHERE api_key_id = $1 AND key = $2;")
+                .bind(api_key_id)
+                .bind(key)
+                .fetch_optional(&self.db)
+                .await?;
+            if let Some((fingerprint, status, body)) = earlier {
+                let response = status.zip(body).map(|(status, body)| (status as u16, body));
+                return Ok(Some(IdempotentRequest { fingerprint, response }));
+            }
+        }
+    }
+
+    async fn complete_idempotency_key(&self, api_key_id: i32, key: &str, status: u16, body: &str) -> Result<(), ApiError> {
+        sqlx::query(
+            "UPDATE idempotency_keys SET response_status = $3, response_body = $4 \
+            WHERE api_key_id = $1 AND key = $2;")
+            .bind(api_key_id)
+            .bind(key)
+            .bind(status as i64)
+            .bind(body)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn release_idempotency_key(&self, api_key_id: i32, key: &str) -> Result<(), ApiError> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE api_key_id = $1 AND key = $2;")
+            .bind(api_key_id)
+            .bind(key)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_migrations(&self) -> Result<Migrations, ApiError> {
+        let applied = sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success ORDER BY version;")
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(Migrations::of(&MIGRATOR, applied))
+    }
+}
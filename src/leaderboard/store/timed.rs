@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::types::Uuid;
+use tracing::{info_span, Instrument};
+
+use super::{Board, EntryValues, IdempotentRequest, LeaderboardStore, Migrations, ScoreUpdate};
+use crate::errors::ApiError;
+use crate::leaderboard::cursor::EntryCursor;
+use crate::leaderboard::models::*;
+use crate::metrics::Metrics;
+
+/// Wraps a store, observing how long each of its operations takes, and running each in a span of its own
+pub struct TimedStore {
+    inner: Arc<dyn LeaderboardStore>,
+    metrics: Metrics,
+}
+
+impl TimedStore {
+    pub fn new(inner: Arc<dyn LeaderboardStore>, metrics: Metrics) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+/// Runs the operation on the wrapped store, labelling its timing and span with the operation's name
+macro_rules! timed {
+    ($self:ident.$operation:ident($($arg:expr),*)) => {{
+        let _timer = $self.metrics.time_store_operation(stringify!($operation));
+        $self.inner.$operation($($arg),*)
+            .instrument(info_span!("store", operation = stringify!($operation)))
+            .await
+    }};
+}
+
+#[async_trait]
+impl LeaderboardStore for TimedStore {
+    async fn get_games(&self) -> Result<Vec<Game>, ApiError> {
+        timed!(self.get_games())
+    }
+
+    async fn get_game(&self, game_id: i32) -> Result<Option<Game>, ApiError> {
+        timed!(self.get_game(game_id))
+    }
+
+    async fn create_game(&self, game: Game) -> Result<Game, ApiError> {
+        timed!(self.create_game(game))
+    }
+
+    async fn update_game(&self, game_id: i32, update: GameUpdate) -> Result<Option<Game>, ApiError> {
+        timed!(self.update_game(game_id, update))
+    }
+
+    async fn set_signing_secret(&self, game_id: i32, signing_secret: String) -> Result<Option<Game>, ApiError> {
+        timed!(self.set_signing_secret(game_id, signing_secret))
+    }
+
+    async fn delete_game(&self, game_id: i32) -> Result<Option<Game>, ApiError> {
+        timed!(self.delete_game(game_id))
+    }
+
+    async fn get_seasons(&self, game_id: i32) -> Result<Vec<Season>, ApiError> {
+        timed!(self.get_seasons(game_id))
+    }
+
+    async fn get_season(&self, game_id: i32, season_id: i32) -> Result<Option<Season>, ApiError> {
+        timed!(self.get_season(game_id, season_id))
+    }
+
+    async fn get_open_season(&self, game_id: i32) -> Result<Option<Season>, ApiError> {
+        timed!(self.get_open_season(game_id))
+    }
+
+    async fn create_season(&self, game_id: i32, name: String) -> Result<Season, ApiError> {
+        timed!(self.create_season(game_id, name))
+    }
+
+    async fn close_season(&self, game_id: i32, season_id: i32) -> Result<Option<Season>, ApiError> {
+        timed!(self.close_season(game_id, season_id))
+    }
+
+    async fn get_entries(
+        &self,
+        game: &Game,
+        board: Board,
+        after: Option<&EntryCursor>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<LeaderboardEntry>, ApiError> {
+        timed!(self.get_entries(game, board, after, limit, offset))
+    }
+
+    async fn get_board_entry(&self, game_id: i32, board: Board, user_id: Uuid) -> Result<Option<LeaderboardEntry>, ApiError> {
+        timed!(self.get_board_entry(game_id, board, user_id))
+    }
+
+    async fn get_entry(&self, entry_id: i32) -> Result<Option<LeaderboardEntry>, ApiError> {
+        timed!(self.get_entry(entry_id))
+    }
+
+    async fn is_entry_invalidated(&self, game_id: i32, user_id: Uuid) -> Result<bool, ApiError> {
+        timed!(self.is_entry_invalidated(game_id, user_id))
+    }
+
+    async fn save_entry(&self, values: EntryValues<'_>, update: ScoreUpdate, season_id: Option<i32>) -> Result<LeaderboardEntry, ApiError> {
+        timed!(self.save_entry(values, update, season_id))
+    }
+
+    async fn save_window_entry(
+        &self,
+        window: LeaderboardWindow,
+        window_start: Option<DateTime<Utc>>,
+        values: EntryValues<'_>,
+        update: ScoreUpdate,
+    ) -> Result<(), ApiError> {
+        timed!(self.save_window_entry(window, window_start, values, update))
+    }
+
+    async fn delete_entry(&self, entry: &LeaderboardEntry) -> Result<(), ApiError> {
+        timed!(self.delete_entry(entry))
+    }
+
+    async fn invalidate_entry(&self, entry: &LeaderboardEntry, reason: Option<String>) -> Result<LeaderboardEntry, ApiError> {
+        timed!(self.invalidate_entry(entry, reason))
+    }
+
+    async fn restore_entry(&self, entry: &LeaderboardEntry) -> Result<LeaderboardEntry, ApiError> {
+        timed!(self.restore_entry(entry))
+    }
+
+    async fn record_submission(
+        &self,
+        values: EntryValues<'_>,
+        accepted: bool,
+        rejected_by: Option<ScoreValidationRule>,
+        season_id: Option<i32>,
+    ) -> Result<(), ApiError> {
+        timed!(self.record_submission(values, accepted, rejected_by, season_id))
+    }
+
+    async fn get_submissions(&self, game_id: i32, user_id: Uuid, limit: i64, offset: i64) -> Result<Vec<ScoreSubmission>, ApiError> {
+        timed!(self.get_submissions(game_id, user_id, limit, offset))
+    }
+
+    async fn get_submission_stats(&self, game_id: i32, user_id: Uuid) -> Result<SubmissionStats, ApiError> {
+        timed!(self.get_submission_stats(game_id, user_id))
+    }
+
+    async fn get_previous_scores(
+        &self,
+        game_id: i32,
+        user_id: Uuid,
+        since: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<f64>, ApiError> {
+        timed!(self.get_previous_scores(game_id, user_id, since, limit))
+    }
+
+    async fn get_last_submitted_at(&self, game_id: i32, user_id: Uuid) -> Result<Option<DateTime<Utc>>, ApiError> {
+        timed!(self.get_last_submitted_at(game_id, user_id))
+    }
+
+    async fn get_standing(&self, game: &Game, entry: &LeaderboardEntry) -> Result<(i64, i64), ApiError> {
+        timed!(self.get_standing(game, entry))
+    }
+
+    async fn get_neighbors(
+        &self,
+        game: &Game,
+        entry: &LeaderboardEntry,
+        radius: i64,
+    ) -> Result<(Vec<LeaderboardEntry>, Vec<LeaderboardEntry>), ApiError> {
+        timed!(self.get_neighbors(game, entry, radius))
+    }
+
+    async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, ApiError> {
+        timed!(self.get_api_key_by_hash(key_hash))
+    }
+
+    async fn get_api_keys(&self) -> Result<Vec<ApiKey>, ApiError> {
+        timed!(self.get_api_keys())
+    }
+
+    async fn get_api_key(&self, key_id: i32) -> Result<Option<ApiKey>, ApiError> {
+        timed!(self.get_api_key(key_id))
+    }
+
+    async fn create_api_key(&self, request: &ApiKeyNew, key_hash: &str) -> Result<ApiKey, ApiError> {
+        timed!(self.create_api_key(request, key_hash))
+    }
+
+    async fn ensure_admin_key(&self, key_hash: &str) -> Result<(), ApiError> {
+        timed!(self.ensure_admin_key(key_hash))
+    }
+
+    async fn revoke_api_key(&self, key_id: i32) -> Result<Option<ApiKey>, ApiError> {
+        timed!(self.revoke_api_key(key_id))
+    }
+
+    async fn get_bans(&self, user_id: Option<Uuid>) -> Result<Vec<UserBan>, ApiError> {
+        timed!(self.get_bans(user_id))
+    }
+
+    async fn get_ban(&self, ban_id: i32) -> Result<Option<UserBan>, ApiError> {
+        timed!(self.get_ban(ban_id))
+    }
+
+    async fn find_ban(&self, user_id: Uuid, game_id: Option<i32>) -> Result<Option<UserBan>, ApiError> {
+        timed!(self.find_ban(user_id, game_id))
+    }
+
+    async fn create_ban(&self, user_id: Uuid, game_id: Option<i32>, reason: String) -> Result<UserBan, ApiError> {
+        timed!(self.create_ban(user_id, game_id, reason))
+    }
+
+    async fn delete_ban(&self, ban_id: i32) -> Result<(), ApiError> {
+        timed!(self.delete_ban(ban_id))
+    }
+
+    async fn is_user_banned(&self, game_id: i32, user_id: Uuid) -> Result<bool, ApiError> {
+        timed!(self.is_user_banned(game_id, user_id))
+    }
+
+    async fn get_visible_entries(&self, user_id: Uuid, game_id: Option<i32>) -> Result<Vec<LeaderboardEntry>, ApiError> {
+        timed!(self.get_visible_entries(user_id, game_id))
+    }
+
+    async fn claim_nonce(&self, game_id: i32, nonce: &str) -> Result<bool, ApiError> {
+        timed!(self.claim_nonce(game_id, nonce))
+    }
+
+    async fn claim_idempotency_key(
+        &self,
+        api_key_id: i32,
+        key: &str,
+        game_id: i32,
+        fingerprint: &str,
+    ) -> Result<Option<IdempotentRequest>, ApiError> {
+        timed!(self.claim_idempotency_key(api_key_id, key, game_id, fingerprint))
+    }
+
+    async fn complete_idempotency_key(&self, api_key_id: i32, key: &str, status: u16, body: &str) -> Result<(), ApiError> {
+        timed!(self.complete_idempotency_key(api_key_id, key, status, body))
+    }
+
+    async fn release_idempotency_key(&self, api_key_id: i32, key: &str) -> Result<(), ApiError> {
+        timed!(self.release_idempotency_key(api_key_id, key))
+    }
+
+    async fn get_migrations(&self) -> Result<Migrations, ApiError> {
+        timed!(self.get_migrations())
+    }
+}
@@ -30,7 +30,11 @@ pub struct GameTemplate {
 #[derive(Template)]
 #[template(path = "leaderboard/game_entries.html")]
 pub struct LeaderboardEntriesTemplate {
+    pub game_id: i32,
     pub entries: Vec<models::LeaderboardEntry>,
+    pub next_cursor: Option<String>,
+    pub limit: i64,
+    pub window: models::LeaderboardWindow,
 }
 
 #[derive(Template)]
@@ -38,3 +42,21 @@ pub struct LeaderboardEntriesTemplate {
 pub struct LeaderboardEntryNewTemplate {
     pub entry: models::LeaderboardEntry,
 }
+
+#[derive(Template)]
+#[template(path = "leaderboard/seasons.html")]
+pub struct SeasonsTemplate {
+    pub seasons: Vec<models::Season>,
+}
+
+#[derive(Template)]
+#[template(path = "leaderboard/season_row.html")]
+pub struct SeasonTemplate {
+    pub season: models::Season,
+}
+
+#[derive(Template)]
+#[template(path = "leaderboard/submission_history.html")]
+pub struct SubmissionHistoryTemplate {
+    pub history: models::SubmissionHistory,
+}
@@ -0,0 +1,165 @@
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+
+use super::models::{Game, GameScoreSortMode, ScoreValidationFailure, ScoreValidationRule};
+use crate::errors::{ApiError, ErrorCode};
+
+impl ScoreValidationFailure {
+    fn new(rule: ScoreValidationRule, limit: Option<f64>, message: String) -> Self {
+        Self { rule, limit, message }
+    }
+}
+
+/// Rejects the submission with a `score_rejected` problem, with the failure's rule and limit as extension members
+impl From<ScoreValidationFailure> for ApiError {
+    fn from(failure: ScoreValidationFailure) -> Self {
+        let extensions = json!({ "rule": failure.rule, "limit": failure.limit });
+        ApiError::Unprocessable {
+            code: ErrorCode::ScoreRejected,
+            detail: failure.message.into(),
+            extensions: extensions.as_object().cloned().unwrap_or_default(),
+        }
+    }
+}
+
+impl Game {
+    /// Checks a submitted score and its score components against the game's limits
+    pub fn check_score(&self, score: f64, score_components: &[f64]) -> Result<(), ScoreValidationFailure> {
+        if !score.is_finite() || !score_components.iter().all(|component| component.is_finite()) {
+            return Err(ScoreValidationFailure::new(
+                ScoreValidationRule::Finite,
+                None,
+                "Scores and score components must be finite numbers".into(),
+            ));
+        }
+        if let Some(min_score) = self.min_score.filter(|min_score| score < *min_score) {
+            return Err(ScoreValidationFailure::new(
+                ScoreValidationRule::MinScore,
+                Some(min_score),
+                format!("Score is below the game's minimum of {min_score}"),
+            ));
+        }
+        if let Some(max_score) = self.max_score.filter(|max_score| score > *max_score) {
+            return Err(ScoreValidationFailure::new(
+                ScoreValidationRule::MaxScore,
+                Some(max_score),
+                format!("Score is above the game's maximum of {max_score}"),
+            ));
+        }
+        if self.integer_scores && score.fract() != 0.0 {
+            return Err(ScoreValidationFailure::new(
+                ScoreValidationRule::IntegerScores,
+                None,
+                "Score must be a whole number".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks by how much a submitted score beats a previous score of the user
+    pub fn check_improvement(&self, previous: f64, score: f64) -> Result<(), ScoreValidationFailure> {
+        let improvement = match self.score_sort_mode {
+            GameScoreSortMode::HigherIsBetter => score - previous,
+            GameScoreSortMode::LesserIsBetter => previous - score,
+        };
+        match self.max_improvement {
+            Some(max_improvement) if improvement > max_improvement => Err(ScoreValidationFailure::new(
+                ScoreValidationRule::MaxImprovement,
+                Some(max_improvement),
+                format!("Score improves on the previous score {previous} by more than {max_improvement}"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the time since the user's previous submission to the game
+    pub fn check_submission_interval(&self, previous: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), ScoreValidationFailure> {
+        match self.min_submission_interval_secs {
+            Some(interval) if now - previous < Duration::seconds(interval as i64) => Err(ScoreValidationFailure::new(
+                ScoreValidationRule::MinSubmissionInterval,
+                Some(interval as f64),
+                format!("Submissions must be at least {interval} seconds apart"),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::leaderboard::models::ScoreAggregation;
+    use sqlx::types::Json;
+
+    fn game() -> Game {
+        Game {
+            id: 0,
+            description: "".into(),
+            score_sort_mode: GameScoreSortMode::HigherIsBetter,
+            score_components: Json(vec![]),
+            score_aggregation: ScoreAggregation::Best,
+            aggregation_last_n: 5,
+            window_timezone: "UTC".into(),
+            window_reset_hour: 0,
+            require_signed_submissions: false,
+            min_score: None,
+            max_score: None,
+            integer_scores: false,
+            max_improvement: None,
+            min_submission_interval_secs: None,
+            signing_secret: "".into(),
+        }
+    }
+
+    fn rule<T>(result: Result<T, ScoreValidationFailure>) -> Option<ScoreValidationRule> {
+        result.err().map(|failure| failure.rule)
+    }
+
+    #[test]
+    fn rejects_non_finite_numbers() {
+        let game = game();
+        assert_eq!(Some(ScoreValidationRule::Finite), rule(game.check_score(f64::NAN, &[])));
+        assert_eq!(Some(ScoreValidationRule::Finite), rule(game.check_score(f64::INFINITY, &[])));
+        assert_eq!(Some(ScoreValidationRule::Finite), rule(game.check_score(1.0, &[f64::NEG_INFINITY])));
+        assert_eq!(None, rule(game.check_score(1e300, &[])));
+    }
+
+    #[test]
+    fn checks_score_range_inclusively() {
+        let game = Game { min_score: Some(0.0), max_score: Some(100.0), ..game() };
+        assert_eq!(Some(ScoreValidationRule::MinScore), rule(game.check_score(-0.5, &[])));
+        assert_eq!(Some(ScoreValidationRule::MaxScore), rule(game.check_score(100.5, &[])));
+        assert_eq!(None, rule(game.check_score(0.0, &[])));
+        assert_eq!(None, rule(game.check_score(100.0, &[])));
+    }
+
+    #[test]
+    fn checks_integer_scores() {
+        let game = Game { integer_scores: true, ..game() };
+        assert_eq!(Some(ScoreValidationRule::IntegerScores), rule(game.check_score(1.5, &[])));
+        assert_eq!(None, rule(game.check_score(-3.0, &[])));
+    }
+
+    #[test]
+    fn checks_improvement_in_sort_direction() {
+        let higher = Game { max_improvement: Some(10.0), ..game() };
+        assert_eq!(Some(ScoreValidationRule::MaxImprovement), rule(higher.check_improvement(5.0, 16.0)));
+        assert_eq!(None, rule(higher.check_improvement(5.0, 15.0)));
+        assert_eq!(None, rule(higher.check_improvement(50.0, 0.0)));
+
+        let lesser = Game { score_sort_mode: GameScoreSortMode::LesserIsBetter, ..higher };
+        assert_eq!(Some(ScoreValidationRule::MaxImprovement), rule(lesser.check_improvement(50.0, 39.0)));
+        assert_eq!(None, rule(lesser.check_improvement(50.0, 99.0)));
+    }
+
+    #[test]
+    fn checks_submission_interval() {
+        let game = Game { min_submission_interval_secs: Some(60), ..game() };
+        let previous = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        assert_eq!(
+            Some(ScoreValidationRule::MinSubmissionInterval),
+            rule(game.check_submission_interval(previous, previous + Duration::seconds(59))),
+        );
+        assert_eq!(None, rule(game.check_submission_interval(previous, previous + Duration::seconds(60))));
+    }
+}
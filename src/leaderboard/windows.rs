@@ -0,0 +1,103 @@
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use super::models::{Game, LeaderboardWindow};
+
+impl LeaderboardWindow {
+    /// Windows which keep their own bests, separate from the all time leaderboard
+    pub const TIMED: [LeaderboardWindow; 3] = [
+        LeaderboardWindow::Daily,
+        LeaderboardWindow::Weekly,
+        LeaderboardWindow::Monthly,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LeaderboardWindow::Daily => "daily",
+            LeaderboardWindow::Weekly => "weekly",
+            LeaderboardWindow::Monthly => "monthly",
+            LeaderboardWindow::AllTime => "all_time",
+        }
+    }
+
+    /// Start of the window containing `now`, for a game's reset timezone and hour.
+    /// Weeks start on Monday. The all time window has no start.
+    pub fn start_at(&self, now: DateTime<Utc>, timezone: Tz, reset_hour: u32) -> Option<DateTime<Utc>> {
+        let reset = Duration::hours(reset_hour as i64);
+        let today = (now.with_timezone(&timezone) - reset).date_naive();
+        let first_day = match self {
+            LeaderboardWindow::Daily => today,
+            LeaderboardWindow::Weekly => today - Duration::days(today.weekday().num_days_from_monday() as i64),
+            LeaderboardWindow::Monthly => today.with_day(1)?,
+            LeaderboardWindow::AllTime => return None,
+        };
+        let local_start = first_day.and_hms_opt(0, 0, 0)? + reset;
+
+        // a reset inside a daylight saving gap happens once the clocks have jumped forward
+        let start = timezone.from_local_datetime(&local_start).earliest()
+            .or_else(|| timezone.from_local_datetime(&(local_start + Duration::hours(1))).earliest())?;
+        Some(start.with_timezone(&Utc))
+    }
+
+    /// Start of the window containing `now` for the game
+    pub fn start_for_game(&self, now: DateTime<Utc>, game: &Game) -> Option<DateTime<Utc>> {
+        let timezone = parse_timezone(&game.window_timezone).unwrap_or(Tz::UTC);
+        self.start_at(now, timezone, game.window_reset_hour.clamp(0, 23) as u32)
+    }
+}
+
+pub fn parse_timezone(timezone: &str) -> Option<Tz> {
+    timezone.parse::<Tz>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn daily_resets_at_midnight_utc() {
+        let start = LeaderboardWindow::Daily.start_at(utc("2024-05-15T13:45:00Z"), Tz::UTC, 0);
+        assert_eq!(Some(utc("2024-05-15T00:00:00Z")), start);
+    }
+
+    #[test]
+    fn daily_before_reset_hour_belongs_to_previous_day() {
+        let start = LeaderboardWindow::Daily.start_at(utc("2024-05-15T03:00:00Z"), Tz::UTC, 4);
+        assert_eq!(Some(utc("2024-05-14T04:00:00Z")), start);
+    }
+
+    #[test]
+    fn daily_respects_timezone() {
+        let start = LeaderboardWindow::Daily.start_at(utc("2024-05-15T02:00:00Z"), Tz::America__New_York, 0);
+        assert_eq!(Some(utc("2024-05-14T04:00:00Z")), start);
+    }
+
+    #[test]
+    fn weekly_starts_on_monday() {
+        // 2024-05-15 is a Wednesday
+        let start = LeaderboardWindow::Weekly.start_at(utc("2024-05-15T13:45:00Z"), Tz::UTC, 0);
+        assert_eq!(Some(utc("2024-05-13T00:00:00Z")), start);
+    }
+
+    #[test]
+    fn monthly_starts_on_the_first() {
+        let start = LeaderboardWindow::Monthly.start_at(utc("2024-05-15T13:45:00Z"), Tz::Europe__Berlin, 0);
+        assert_eq!(Some(utc("2024-04-30T22:00:00Z")), start);
+    }
+
+    #[test]
+    fn reset_in_daylight_saving_gap_happens_after_the_jump() {
+        // clocks in New York jumped from 02:00 to 03:00 on 2024-03-10
+        let start = LeaderboardWindow::Daily.start_at(utc("2024-03-10T12:00:00Z"), Tz::America__New_York, 2);
+        assert_eq!(Some(utc("2024-03-10T07:00:00Z")), start);
+    }
+
+    #[test]
+    fn all_time_has_no_start() {
+        assert_eq!(None, LeaderboardWindow::AllTime.start_at(utc("2024-05-15T13:45:00Z"), Tz::UTC, 0));
+    }
+}
@@ -0,0 +1,228 @@
+//! WebSocket sessions at `/leaderboard/ws`, for game clients which want to submit scores and follow
+//! leaderboards over one connection.
+//!
+//! Messages are JSON objects tagged by their `type`, see [`ClientMessage`] and [`ServerMessage`].
+//! Following games is anonymous, submitting needs the same bearer API key as the HTTP API,
+//! sent in the `Authorization` header of the upgrade request.
+
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use axum::Extension;
+use tracing::{error, Instrument, Span};
+use serde::{Deserialize, Serialize};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt as _;
+
+use super::auth::ApiKeyRejection;
+use super::events::{LeaderboardStream, StreamMessage, StreamedUpdate};
+use super::models::{ApiKey, LeaderboardEntry, LeaderboardEntryNew, LeaderboardUpdate};
+use super::rate_limit::{ClientIp, Route};
+use super::routes::submit_entry_internal;
+use super::signing::SubmissionSignature;
+use crate::app_state::AppState;
+use crate::errors::{ApiError, ErrorCode, Problem};
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Start receiving the game's updates
+    Subscribe { game_id: i32 },
+    /// Stop receiving the game's updates
+    Unsubscribe { game_id: i32 },
+    /// Submit a score, like `POST /leaderboard/games/{game_id}/entries`
+    Submit {
+        /// Echoed in the reply, to tell which submission it is about
+        request_id: Option<String>,
+        game_id: i32,
+        #[serde(flatten)]
+        entry: LeaderboardEntryNew,
+        /// Required by games requiring signed submissions
+        signature: Option<SignatureMessage>,
+    },
+}
+
+/// The `X-Signature` headers of a submission, see [`SubmissionSignature`]
+#[derive(Deserialize, Debug)]
+pub struct SignatureMessage {
+    pub timestamp: i64,
+    pub nonce: String,
+    /// Hex HMAC-SHA256
+    pub signature: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Subscribed { game_id: i32 },
+    Unsubscribed { game_id: i32 },
+    /// The submission changed at least one leaderboard. Carries the user's all time entry and its rank
+    Accepted { request_id: Option<String>, entry: LeaderboardEntry, rank: Option<i64> },
+    /// The submission changed no leaderboard. Carries the user's better all time entry
+    Conflict { request_id: Option<String>, entry: LeaderboardEntry },
+    /// A message failed, with the problem the HTTP API would have responded with
+    Error { request_id: Option<String>, problem: Problem },
+    /// Update of a subscribed game, like the events of `/leaderboard/games/{game_id}/stream`
+    Update { id: u64, update: LeaderboardUpdate },
+    /// Updates were missed, leaderboards of subscribed games should be fetched again
+    Resync,
+}
+
+/// Upgrades to a WebSocket session. An invalid API key does not fail the upgrade, only the submissions
+pub async fn handle_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Extension(tx): Extension<LeaderboardStream>,
+    ClientIp(ip): ClientIp,
+    api_key: Result<ApiKey, ApiKeyRejection>,
+) -> Result<Response, ApiError> {
+    let api_key = match api_key {
+        Ok(api_key) => Ok(api_key),
+        Err(ApiKeyRejection::Store(e)) => return Err(e),
+        Err(rejection) => Err(ApiError::from(rejection).problem()),
+    };
+
+    // The session runs in the span of the upgrade request, logging under its request id
+    let span = Span::current();
+    Ok(ws.on_upgrade(move |socket| run_session(socket, state, tx, ip, api_key).instrument(span)))
+}
+
+async fn run_session(
+    mut socket: WebSocket,
+    state: AppState,
+    tx: LeaderboardStream,
+    ip: Option<IpAddr>,
+    api_key: Result<ApiKey, Problem>,
+) {
+    let mut updates = BroadcastStream::new(tx.subscribe(None).rx);
+    let mut game_ids = HashSet::new();
+    let shutdown = state.shutdown.triggered();
+    tokio::pin!(shutdown);
+
+    loop {
+        let reply = tokio::select! {
+            _ = &mut shutdown => {
+                let _ = socket.send(Message::Close(Some(CloseFrame { code: close_code::AWAY, reason: "Shutting down".into() }))).await;
+                break;
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => handle_message(&state, &tx, ip, &api_key, &mut game_ids, &text).await,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // axum answers pings itself
+                Some(Ok(_)) => continue,
+            },
+            update = updates.next() => match update {
+                Some(Ok(StreamMessage::Update(StreamedUpdate { id, update }))) if game_ids.contains(&update.game_id) => {
+                    ServerMessage::Update { id, update }
+                }
+                Some(Ok(StreamMessage::Update(_))) => continue,
+                Some(Ok(StreamMessage::Resync)) => ServerMessage::Resync,
+                Some(Err(BroadcastStreamRecvError::Lagged(skipped))) => {
+                    state.metrics.record_lagged("leaderboard", skipped);
+                    ServerMessage::Resync
+                },
+                None => break,
+            },
+        };
+
+        let reply = serde_json::to_string(&reply).expect("Server messages serialize to JSON");
+        if socket.send(Message::Text(reply)).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn handle_message(
+    state: &AppState,
+    tx: &LeaderboardStream,
+    ip: Option<IpAddr>,
+    api_key: &Result<ApiKey, Problem>,
+    game_ids: &mut HashSet<i32>,
+    text: &str,
+) -> ServerMessage {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(e) => {
+            let error = ApiError::Validation { code: ErrorCode::InvalidMessage, detail: e.to_string().into() };
+            return ServerMessage::Error { request_id: None, problem: error.problem() };
+        }
+    };
+
+    match message {
+        ClientMessage::Subscribe { game_id } => match state.store.get_game(game_id).await {
+            Ok(Some(_)) => {
+                game_ids.insert(game_id);
+                ServerMessage::Subscribed { game_id }
+            }
+            Ok(None) => error_message(None, ApiError::NotFound),
+            Err(e) => error_message(None, e),
+        },
+        ClientMessage::Unsubscribe { game_id } => {
+            game_ids.remove(&game_id);
+            ServerMessage::Unsubscribed { game_id }
+        }
+        ClientMessage::Submit { request_id, game_id, entry, signature } => {
+            let api_key = match api_key {
+                Ok(api_key) => api_key,
+                Err(problem) => return ServerMessage::Error { request_id, problem: problem.clone() },
+            };
+            if let Err(e) = state.rate_limiter.check(Route::CreateGameEntry, ip, api_key, entry.user_id) {
+                return error_message(request_id, e);
+            }
+            let signature = match signature {
+                None => None,
+                Some(SignatureMessage { timestamp, nonce, signature }) => {
+                    match SubmissionSignature::parse(timestamp, &nonce, &signature) {
+                        Some(signature) => Some(signature),
+                        None => {
+                            let error = ApiError::unauthorized(ErrorCode::InvalidSignature, "Malformed signature");
+                            return error_message(request_id, error);
+                        }
+                    }
+                }
+            };
+
+            match submit_entry_internal(state, tx, api_key, game_id, signature, entry).await {
+                Ok(Ok((entry, rank))) => ServerMessage::Accepted { request_id, entry, rank },
+                Ok(Err(entry)) => ServerMessage::Conflict { request_id, entry },
+                Err(e) => error_message(request_id, e),
+            }
+        }
+    }
+}
+
+fn error_message(request_id: Option<String>, error: ApiError) -> ServerMessage {
+    if let ApiError::Internal(ref e) = error {
+        error!("Internal error in WebSocket session: {e}");
+    }
+    ServerMessage::Error { request_id, problem: error.problem() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_submissions_with_flattened_entries() {
+        let message = serde_json::from_str::<ClientMessage>(
+            r#"{"type": "submit", "request_id": "r1", "game_id": 3, "score": 12.5, "user_name": "ws"}"#,
+        ).unwrap();
+
+        let ClientMessage::Submit { request_id, game_id, entry, signature } = message else {
+            panic!("expected a submission, got {message:?}");
+        };
+        assert_eq!((Some("r1".to_string()), 3), (request_id, game_id));
+        assert_eq!((12.5, "ws"), (entry.score, entry.user_name.as_str()));
+        assert!(signature.is_none());
+    }
+
+    #[test]
+    fn tags_server_messages_by_type() {
+        let json = serde_json::to_value(ServerMessage::Subscribed { game_id: 3 }).unwrap();
+        assert_eq!(serde_json::json!({ "type": "subscribed", "game_id": 3 }), json);
+    }
+}
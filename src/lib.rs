@@ -1,8 +1,13 @@
 pub mod errors;
+pub mod health;
 pub mod hetero_req_resp;
 pub mod leaderboard;
+pub mod metrics;
 pub mod models;
 pub mod router;
 pub mod todo;
 pub mod openapi;
-pub mod app_state;
\ No newline at end of file
+pub mod pubsub;
+pub mod shutdown;
+pub mod app_state;
+pub mod telemetry;
\ No newline at end of file
@@ -1,18 +1,37 @@
 use sqlx::PgPool;
-use log::info;
+use tracing::info;
+use shuttle_runtime::SecretStore;
 
 
 use fraculation_leaderboard::*;
+use fraculation_leaderboard::leaderboard::rate_limit::RateLimits;
 
 #[shuttle_runtime::main]
-async fn main(#[shuttle_shared_db::Postgres] db: PgPool) -> shuttle_axum::ShuttleAxum {
+async fn main(
+    #[shuttle_shared_db::Postgres] db: PgPool,
+    #[shuttle_runtime::Secrets] secrets: SecretStore,
+) -> shuttle_axum::ShuttleAxum {
+    telemetry::init_logging();
+
     info!("Running database migration");
-    sqlx::migrate!()
+    leaderboard::store::postgres::MIGRATOR
         .run(&db)
         .await
         .expect("Looks like something went wrong with migrations :(");
 
-    let router = router::init_router(db);
+    // the first admin key has to come from configuration, every other key is minted through the API
+    if let Some(admin_key) = secrets.get("ADMIN_API_KEY") {
+        leaderboard::auth::ensure_admin_key(&leaderboard::store::PgStore::new(db.clone()), &admin_key)
+            .await
+            .expect("Configured admin key should be stored");
+    }
+
+    // Shuttle serves behind its proxy, which tells the client's IP in X-Forwarded-For
+    let rate_limits = RateLimits {
+        trust_forwarded_for: true,
+        ..RateLimits::from_vars(|name| secrets.get(name))
+    };
+    let router = router::init_router(db, rate_limits).await;
 
     Ok(router.into())
 }
@@ -0,0 +1,183 @@
+//! Prometheus metrics of the app, served in the text exposition format on `/metrics`.
+//! Every app built by `router::init_router_with` has its own registry, so tests do not see each other's counts.
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramTimer, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+use crate::app_state::AppState;
+
+/// How a score submission ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SubmissionOutcome {
+    /// Changed at least one leaderboard
+    Accepted,
+    /// Changed no leaderboard, the user already has a better entry. Answered with a 409
+    NotBetter,
+    /// Failed, like an invalid signature or a score breaking the game's rules
+    Rejected,
+}
+
+impl SubmissionOutcome {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Accepted => "accepted",
+            Self::NotBetter => "not_better",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+type SubscriberCount = Box<dyn Fn() -> usize + Send + Sync>;
+
+/// Handle to the metrics, cheap to clone
+#[derive(Clone)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    registry: Registry,
+    http_requests: HistogramVec,
+    store_operations: HistogramVec,
+    stream_subscribers: IntGaugeVec,
+    stream_lagged_updates: IntCounterVec,
+    submissions: IntCounterVec,
+    /// Read at every scrape, as broadcast channels already count their receivers
+    subscriber_counts: Mutex<Vec<(&'static str, SubscriberCount)>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let http_requests = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Time to respond to HTTP requests, per route"),
+            &["method", "route", "status"],
+        ).unwrap();
+        let store_operations = HistogramVec::new(
+            HistogramOpts::new("store_operation_duration_seconds", "Time spent on database queries, per store operation")
+                .buckets(vec![0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]),
+            &["operation"],
+        ).unwrap();
+        let stream_subscribers = IntGaugeVec::new(
+            Opts::new("stream_subscribers", "Open event streams and WebSocket sessions, per update stream"),
+            &["stream"],
+        ).unwrap();
+        let stream_lagged_updates = IntCounterVec::new(
+            Opts::new("stream_lagged_updates_total", "Updates dropped for subscribers falling too far behind, per update stream"),
+            &["stream"],
+        ).unwrap();
+        let submissions = IntCounterVec::new(
+            Opts::new("leaderboard_submissions_total", "Score submissions, per outcome"),
+            &["outcome"],
+        ).unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(store_operations.clone())).unwrap();
+        registry.register(Box::new(stream_subscribers.clone())).unwrap();
+        registry.register(Box::new(stream_lagged_updates.clone())).unwrap();
+        registry.register(Box::new(submissions.clone())).unwrap();
+        // Every outcome shows up from the start, so rates can be computed before the first rejection
+        for outcome in [SubmissionOutcome::Accepted, SubmissionOutcome::NotBetter, SubmissionOutcome::Rejected] {
+            submissions.with_label_values(&[outcome.label()]);
+        }
+
+        Self {
+            inner: Arc::new(Inner {
+                registry,
+                http_requests,
+                store_operations,
+                stream_subscribers,
+                stream_lagged_updates,
+                submissions,
+                subscriber_counts: Mutex::new(vec![]),
+            }),
+        }
+    }
+
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        self.inner.http_requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .observe(seconds);
+    }
+
+    /// Observes the time until the timer drops
+    pub fn time_store_operation(&self, operation: &str) -> HistogramTimer {
+        self.inner.store_operations.with_label_values(&[operation]).start_timer()
+    }
+
+    /// Reports the subscribers of the stream, as counted by `count` at every scrape
+    pub fn watch_subscribers(&self, stream: &'static str, count: impl Fn() -> usize + Send + Sync + 'static) {
+        self.inner.subscriber_counts.lock().unwrap().push((stream, Box::new(count)));
+    }
+
+    pub fn record_lagged(&self, stream: &str, skipped: u64) {
+        self.inner.stream_lagged_updates.with_label_values(&[stream]).inc_by(skipped);
+    }
+
+    pub fn record_submission(&self, outcome: SubmissionOutcome) {
+        self.inner.submissions.with_label_values(&[outcome.label()]).inc();
+    }
+
+    /// Every metric in the text exposition format
+    pub fn render(&self) -> String {
+        for (stream, count) in self.inner.subscriber_counts.lock().unwrap().iter() {
+            self.inner.stream_subscribers.with_label_values(&[stream]).set(count() as i64);
+        }
+
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.inner.registry.gather(), &mut buffer)
+            .expect("Metrics encode to text");
+        String::from_utf8(buffer).expect("Metrics text is UTF-8")
+    }
+}
+
+/// Middleware observing the latency of the requests to each route.
+/// Labels requests by their route rather than their path, so ids in paths do not multiply the series
+pub async fn track_requests(State(metrics): State<Metrics>, request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().clone();
+    let route = request.extensions().get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_else(|| "unmatched".to_owned());
+
+    let response = next.run(request).await;
+    metrics.observe_request(method.as_str(), &route, response.status().as_u16(), started.elapsed().as_secs_f64());
+    response
+}
+
+/// Prometheus metrics of this instance
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    ([(CONTENT_TYPE, TextEncoder::new().format_type().to_owned())], state.metrics.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscriber_counts_are_read_at_scrape() {
+        let metrics = Metrics::new();
+        let count = Arc::new(Mutex::new(1));
+        let watched = count.clone();
+        metrics.watch_subscribers("test", move || *watched.lock().unwrap());
+
+        assert!(metrics.render().contains("stream_subscribers{stream=\"test\"} 1"));
+        *count.lock().unwrap() = 3;
+        assert!(metrics.render().contains("stream_subscribers{stream=\"test\"} 3"));
+    }
+}
@@ -4,5 +4,6 @@ use serde::{Deserialize, Serialize};
     utoipa::ToSchema)]
 pub enum MutationKind {
     Create,
+    Update,
     Delete,
 }
@@ -1,6 +1,7 @@
-use utoipa::{OpenApi};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
 
-use crate::leaderboard;
+use crate::{errors, leaderboard, models};
 
 pub fn gen_my_openapi() -> utoipa::openapi::OpenApi {
     #[derive(OpenApi)]
@@ -9,17 +10,51 @@ pub fn gen_my_openapi() -> utoipa::openapi::OpenApi {
             leaderboard::routes::get_games,
             leaderboard::routes::create_game,
             leaderboard::routes::get_game,
+            leaderboard::routes::update_game,
+            leaderboard::routes::delete_game,
+            leaderboard::routes::get_signing_secret,
+            leaderboard::routes::rotate_signing_secret,
+            leaderboard::routes::get_seasons,
+            leaderboard::routes::create_season,
+            leaderboard::routes::close_season,
             leaderboard::routes::get_game_entries,
             leaderboard::routes::get_user_game_entry,
+            leaderboard::routes::get_user_game_neighborhood,
+            leaderboard::routes::get_user_game_history,
             leaderboard::routes::create_game_entry,
+            leaderboard::routes::delete_entry,
+            leaderboard::routes::invalidate_entry,
+            leaderboard::routes::restore_entry,
+            leaderboard::routes::get_bans,
+            leaderboard::routes::create_ban,
+            leaderboard::routes::delete_ban,
+            leaderboard::routes::get_api_keys,
+            leaderboard::routes::create_api_key,
+            leaderboard::routes::revoke_api_key,
+            leaderboard::routes::handle_game_stream,
         ),
         components(
             schemas(
-                leaderboard::models::Game, leaderboard::models::GameNew, leaderboard::models::GameScoreSortMode,
-                leaderboard::models::LeaderboardEntry, leaderboard::models::LeaderboardEntryNew
+                leaderboard::models::Game, leaderboard::models::GameNew, leaderboard::models::GameUpdate, leaderboard::models::GameSigningSecret, leaderboard::models::GameScoreSortMode,
+                leaderboard::models::LeaderboardWindow, leaderboard::models::ScoreAggregation, leaderboard::models::ScoreComponent,
+                leaderboard::models::Season, leaderboard::models::SeasonNew,
+                leaderboard::models::LeaderboardEntry, leaderboard::models::LeaderboardEntryNew,
+                leaderboard::models::LeaderboardEntriesPage,
+                leaderboard::models::LeaderboardEntryStanding, leaderboard::models::RankedLeaderboardEntry,
+                leaderboard::models::LeaderboardNeighborhood,
+                leaderboard::models::ScoreSubmission, leaderboard::models::SubmissionStats,
+                leaderboard::models::SubmissionHistory,
+                leaderboard::models::ScoreValidationRule,
+                leaderboard::models::ScoreValidationFailure,
+                leaderboard::models::EntryInvalidation, leaderboard::models::UserBan, leaderboard::models::UserBanNew,
+                leaderboard::models::ApiKey, leaderboard::models::ApiKeyNew, leaderboard::models::ApiKeyCreated,
+                leaderboard::models::ApiKeyRole,
+                leaderboard::models::LeaderboardUpdate, leaderboard::models::LeaderboardUpdateTarget,
+                models::MutationKind,
+                errors::Problem, errors::ErrorCode,
             )
         ),
-        modifiers(),
+        modifiers(&SecurityAddon),
         tags(
             (name = "leaderboard", description = "Game Leaderboard management API")
         )
@@ -28,4 +63,17 @@ pub fn gen_my_openapi() -> utoipa::openapi::OpenApi {
 
 
     ApiDoc::openapi()
-}
\ No newline at end of file
+}
+
+/// Bearer API keys, see [`leaderboard::auth`]
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
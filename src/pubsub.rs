@@ -0,0 +1,85 @@
+//! Fans updates out to every instance sharing the database, through Postgres `NOTIFY`.
+//! Each instance `LISTEN`s and re-broadcasts what it hears to its own streams.
+
+use std::time::Duration;
+
+use tracing::{error, warn};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+
+/// Postgres refuses `NOTIFY` payloads of this many bytes or more
+pub const MAX_PAYLOAD_LEN: usize = 8000;
+
+/// Sends the update to the listeners of the channel on every instance, this one included
+pub async fn publish<T: Serialize>(db: &PgPool, channel: &str, update: &T) -> Result<(), sqlx::Error> {
+    let payload = serde_json::to_string(update).expect("Updates serialize to JSON");
+    sqlx::query("SELECT pg_notify($1, $2);")
+        .bind(channel)
+        .bind(payload)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Publishes the update, or streams it on this instance only with `send_locally` when Postgres can not be reached,
+/// so local subscribers see it either way
+pub async fn publish_or_send<T: Serialize>(db: &PgPool, channel: &str, update: T, send_locally: impl FnOnce(T)) {
+    if let Err(e) = publish(db, channel, &update).await {
+        error!("Could not publish update on {channel}, only streaming it on this instance: {e}");
+        send_locally(update);
+    }
+}
+
+/// Listens to the channel, handing every update to `on_update`. Returns once listening,
+/// the listener then runs in the background until the pool closes. When its connection drops it reconnects,
+/// then calls `on_missed`, as updates notified in between are lost
+pub async fn listen<T, F, M>(db: &PgPool, channel: &'static str, on_update: F, on_missed: M) -> Result<(), sqlx::Error>
+where
+    T: DeserializeOwned,
+    F: Fn(T) + Send + 'static,
+    M: Fn() + Send + 'static,
+{
+    let mut listener = connect(db, channel).await?;
+    let db = db.clone();
+
+    tokio::spawn(async move {
+        loop {
+            match listener.try_recv().await {
+                Ok(Some(notification)) => {
+                    match serde_json::from_str::<T>(notification.payload()) {
+                        Ok(update) => on_update(update),
+                        Err(e) => warn!("Ignoring malformed update on {channel}: {e}"),
+                    }
+                    continue;
+                }
+                Err(_) if db.is_closed() => break,
+                Ok(None) => warn!("Lost the {channel} listener connection, reconnecting"),
+                Err(e) => error!("Lost the {channel} listener connection, reconnecting: {e}"),
+            }
+            // Reconnected by hand rather than by the listener, to know when updates can be heard again
+            listener = loop {
+                match connect(&db, channel).await {
+                    Ok(listener) => break listener,
+                    Err(_) if db.is_closed() => return,
+                    Err(e) => {
+                        error!("Could not reconnect the {channel} listener, retrying: {e}");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                }
+            };
+            on_missed();
+        }
+    });
+
+    Ok(())
+}
+
+async fn connect(db: &PgPool, channel: &str) -> Result<PgListener, sqlx::Error> {
+    let mut listener = PgListener::connect_with(db).await?;
+    listener.listen(channel).await?;
+
+    Ok(listener)
+}
@@ -1,15 +1,28 @@
+use std::sync::Arc;
+
 use askama::Template;
 use askama_axum::{IntoResponse, Response};
-use axum::http::header::{ACCEPT, CONTENT_TYPE};
-use axum::http::{Method, StatusCode};
-use axum::{routing::{delete, get}, Extension, Router, Json};
+use axum::http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{HeaderName, Method, StatusCode};
+use axum::{middleware, routing::{delete, get, post}, Extension, Router, Json};
 use sqlx::PgPool;
 
-use crate::errors::ApiError;
+use crate::errors::{self, ApiError};
+use crate::health;
 use crate::leaderboard;
+use crate::leaderboard::idempotency::{IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAYED_HEADER};
+use crate::leaderboard::rate_limit::{RateLimiter, RateLimits};
+use crate::leaderboard::signing;
+use crate::leaderboard::store::{LeaderboardStore, PgStore, TimedStore};
+use crate::metrics::{self, Metrics};
+use crate::pubsub;
+use crate::shutdown::Shutdown;
+use crate::telemetry::{self, REQUEST_ID_HEADER};
 use crate::todo;
 use tokio::sync::broadcast::channel;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
 use utoipa_rapidoc::RapiDoc;
 use crate::app_state::AppState;
 use crate::openapi::gen_my_openapi;
@@ -37,21 +50,49 @@ pub async fn openapi_yaml() -> impl IntoResponse { (
 )}
 pub async fn openapi_json() -> impl IntoResponse { Json(gen_my_openapi()) }
 
-pub fn init_router(db: PgPool) -> Router {
-    let state = AppState { db };
+/// Builds the app on Postgres, listening for the updates every instance publishes for its streams
+pub async fn init_router(db: PgPool, rate_limits: RateLimits) -> Router {
+    init_router_with(Arc::new(PgStore::new(db.clone())), Some(db), Shutdown::new(), rate_limits).await
+}
+
+/// Builds the app on the given store, ending its streams and WebSocket sessions once `shutdown` triggers.
+/// With `postgres`, the streams carry the updates of every instance sharing it, and the todo app is served.
+/// Game creations and score submissions are throttled by `rate_limits`
+pub async fn init_router_with(
+    store: Arc<dyn LeaderboardStore>,
+    postgres: Option<PgPool>,
+    shutdown: Shutdown,
+    rate_limits: RateLimits,
+) -> Router {
+    let metrics = Metrics::new();
+    let store = Arc::new(TimedStore::new(store, metrics.clone()));
     let mut router = Router::new()
+        .route("/metrics", get(metrics::metrics))
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .route("/build-info", get(health::build_info))
         .route("/api-docs/openapi3.yml", get(openapi_yaml))
         .route("/api-docs/openapi3.json", get(openapi_json))
         .merge(RapiDoc::new("/api-docs/openapi3.yml").path("/rapidoc"))
         .route("/", get(root_home))
         .route("/styles.css", get(styles))
         ;
-    {
+    if let Some(db) = &postgres {
         use todo::models::TodoUpdate;
         use todo::routes::*;
 
         let (tx, _rx) = channel::<TodoUpdate>(10);
         let update_stream: TodosStream = tx;
+        let watched_stream = update_stream.clone();
+        metrics.watch_subscribers("todos", move || watched_stream.receiver_count());
+        let listening_stream = update_stream.clone();
+        pubsub::listen(db, NOTIFY_CHANNEL, move |update: TodoUpdate| {
+            let _ = listening_stream.send(update);
+        }, || {
+            // Todo subscribers skip missed updates, as when they lag
+        })
+            .await
+            .expect("Should listen for todo updates");
         router = router
             .route("/todo", get(home))
             .route("/todo/stream", get(stream))
@@ -60,21 +101,44 @@ pub fn init_router(db: PgPool) -> Router {
             .route("/todo/todos/:id", delete(delete_todo))
             .route("/todo/todos/stream", get(handle_stream))
             .layer(Extension(update_stream))
+            .layer(Extension(db.clone()))
     }
     {
-        use leaderboard::models::LeaderboardUpdate;
         use leaderboard::routes::*;
 
-        let (tx, _rx) = channel::<LeaderboardUpdate>(10);
-        let update_stream: LeaderboardStream = tx;
+        let mut update_stream = LeaderboardStream::new();
+        if let Some(db) = &postgres {
+            update_stream.listen(db)
+                .await
+                .expect("Should listen for leaderboard updates");
+        }
+        let watched_stream = update_stream.clone();
+        metrics.watch_subscribers("leaderboard", move || watched_stream.subscriber_count());
         router = router
             .route("/leaderboard", get(home))
             .route("/leaderboard/stream_page", get(stream))
             .route("/leaderboard/stream", get(handle_stream))
+            .route("/leaderboard/ws", get(leaderboard::ws::handle_ws))
             .route("/leaderboard/styles.css", get(styles))
 
             .route("/leaderboard/games", get(get_games).post(create_game))
-            .route("/leaderboard/games/:game_id", get(get_game))
+            .route(
+                "/leaderboard/games/:game_id",
+                get(get_game).patch(update_game).delete(delete_game),
+            )
+            .route("/leaderboard/games/:game_id/stream", get(handle_game_stream))
+            .route(
+                "/leaderboard/games/:game_id/signing_secret",
+                get(get_signing_secret).post(rotate_signing_secret),
+            )
+            .route(
+                "/leaderboard/games/:game_id/seasons",
+                get(get_seasons).post(create_season),
+            )
+            .route(
+                "/leaderboard/games/:game_id/seasons/:season_id/close",
+                post(close_season),
+            )
             .route(
                 "/leaderboard/games/:game_id/entries",
                 get(get_game_entries).post(create_game_entry),
@@ -83,17 +147,54 @@ pub fn init_router(db: PgPool) -> Router {
                 "/leaderboard/users/:user_id/games/:game_id/entries",
                 get(get_user_game_entry),
             )
+            .route(
+                "/leaderboard/users/:user_id/games/:game_id/neighborhood",
+                get(get_user_game_neighborhood),
+            )
+            .route(
+                "/leaderboard/users/:user_id/games/:game_id/history",
+                get(get_user_game_history),
+            )
+            .route("/leaderboard/admin/entries/:entry_id", delete(delete_entry))
+            .route("/leaderboard/admin/entries/:entry_id/invalidate", post(invalidate_entry))
+            .route("/leaderboard/admin/entries/:entry_id/restore", post(restore_entry))
+            .route("/leaderboard/admin/bans", get(get_bans).post(create_ban))
+            .route("/leaderboard/admin/bans/:ban_id", delete(delete_ban))
+            .route("/leaderboard/admin/keys", get(get_api_keys).post(create_api_key))
+            .route("/leaderboard/admin/keys/:key_id", delete(revoke_api_key))
             .layer(Extension(update_stream))
     }
     let cors = CorsLayer::new()
-        // allow `GET` and `POST` when accessing the resource
-        .allow_methods([Method::GET, Method::POST])
+        // allow `GET`, `POST`, `PATCH` and `DELETE` when accessing the resource
+        .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
         // Accept defines whether to send back json or html. content type defines form data vs json data
-        .allow_headers([ACCEPT, CONTENT_TYPE])
+        .allow_headers([
+            ACCEPT,
+            AUTHORIZATION,
+            CONTENT_TYPE,
+            HeaderName::from_static(signing::TIMESTAMP_HEADER),
+            HeaderName::from_static(signing::NONCE_HEADER),
+            HeaderName::from_static(signing::SIGNATURE_HEADER),
+            HeaderName::from_static("last-event-id"),
+            REQUEST_ID_HEADER,
+            IDEMPOTENCY_KEY_HEADER,
+        ])
+        .expose_headers([REQUEST_ID_HEADER, IDEMPOTENT_REPLAYED_HEADER])
         // allow requests from any origin
         .allow_origin(Any);
 
+    let state = AppState { store, shutdown, metrics: metrics.clone(), rate_limiter: RateLimiter::new(rate_limits) };
     router
+        // Only routed requests are tracked, labelled by their route
+        .route_layer(middleware::from_fn_with_state(metrics, metrics::track_requests))
+        .layer(middleware::from_fn(errors::attach_request_id))
+        .layer(middleware::from_fn(errors::render_htmx_errors))
         .layer(cors)
+        // Outermost, so the id is set before the request span opens and everything after logs within it
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+        .layer(TraceLayer::new_for_http()
+            .make_span_with(telemetry::request_span)
+            .on_response(telemetry::record_status))
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
         .with_state(state)
 }
@@ -0,0 +1,63 @@
+//! Lets long lived responses, like event streams and WebSocket sessions, end when the server shuts down.
+//! Graceful shutdown waits for every open connection, so without this it would wait for them forever.
+
+use std::future::Future;
+
+use tokio::sync::watch;
+
+/// Signals the shutdown to every clone
+#[derive(Clone)]
+pub struct Shutdown {
+    tx: watch::Sender<bool>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (tx, _rx) = watch::channel(false);
+        Self { tx }
+    }
+
+    /// Ends the streams and sessions, current and future ones
+    pub fn trigger(&self) {
+        self.tx.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Completes once triggered. Does not borrow the shutdown, so it can end a stream
+    pub fn triggered(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut rx = self.tx.subscribe();
+        async move {
+            // Only fails once every sender is gone, after which no shutdown can come anymore
+            if rx.wait_for(|triggered| *triggered).await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn completes_once_triggered() {
+        let shutdown = Shutdown::new();
+        let before = shutdown.triggered();
+        assert!(tokio::time::timeout(Duration::from_millis(10), shutdown.triggered()).await.is_err());
+
+        shutdown.trigger();
+
+        before.await;
+        shutdown.triggered().await;
+    }
+}
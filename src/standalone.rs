@@ -0,0 +1,189 @@
+//! Runs the app without the Shuttle runtime, for self hosting.
+//!
+//! Settings come from the environment, or from a `KEY=value` config file at `LEADERBOARD_CONFIG`
+//! (`leaderboard.env` by default) for those the environment does not set:
+//! - `DATABASE_URL`: Postgres to connect to, required. With the `sqlite` feature, a `sqlite:` URL like
+//!   `sqlite:leaderboard.db` keeps everything in that SQLite file instead, created when missing
+//! - `BIND_ADDRESS`: where to serve, `0.0.0.0:8000` by default
+//! - `DATABASE_MAX_CONNECTIONS`: size of the connection pool, 10 by default
+//! - `ADMIN_API_KEY`: stored as an admin key, like the Shuttle secret of the same name
+//! - `RUST_LOG`: filter of the JSON logs on stdout, `info` by default
+//! - `RATE_LIMIT_*` and `TRUST_FORWARDED_FOR`: budgets of the rate limiter, see
+//!   [`RateLimits::from_vars`](fraculation_leaderboard::leaderboard::rate_limit::RateLimits::from_vars)
+//!
+//! Shuts down gracefully on SIGTERM or Ctrl+C, ending the open streams so their connections drain.
+
+use std::env;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tracing::{info, warn};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use tokio::net::TcpListener;
+
+use fraculation_leaderboard::*;
+use fraculation_leaderboard::leaderboard::rate_limit::RateLimits;
+use fraculation_leaderboard::leaderboard::store::{LeaderboardStore, PgStore};
+use fraculation_leaderboard::shutdown::Shutdown;
+
+struct Config {
+    database_url: String,
+    bind_address: String,
+    database_max_connections: u32,
+    admin_api_key: Option<String>,
+    rate_limits: RateLimits,
+}
+
+impl Config {
+    fn load() -> Self {
+        let path = env::var("LEADERBOARD_CONFIG").unwrap_or_else(|_| "leaderboard.env".to_string());
+        match dotenvy::from_filename(&path) {
+            Ok(_) => info!("Read config file {path}"),
+            Err(e) if e.not_found() => {}
+            Err(e) => panic!("Config file {path} should be readable: {e}"),
+        }
+
+        Self {
+            database_url: env::var("DATABASE_URL").expect("DATABASE_URL should be set"),
+            bind_address: env::var("BIND_ADDRESS").unwrap_or_else(|_| "0.0.0.0:8000".to_string()),
+            database_max_connections: env::var("DATABASE_MAX_CONNECTIONS")
+                .map(|max| max.parse().expect("DATABASE_MAX_CONNECTIONS should be a number"))
+                .unwrap_or(10),
+            admin_api_key: env::var("ADMIN_API_KEY").ok(),
+            rate_limits: RateLimits::from_vars(|name| env::var(name).ok()),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    telemetry::init_logging();
+    let config = Config::load();
+
+    let db = Database::connect(&config).await;
+
+    info!("Running database migration");
+    db.migrate().await;
+
+    if let Some(admin_key) = config.admin_api_key {
+        leaderboard::auth::ensure_admin_key(db.store().as_ref(), &admin_key)
+            .await
+            .expect("Configured admin key should be stored");
+    }
+
+    let shutdown = Shutdown::new();
+    let router = router::init_router_with(db.store(), db.postgres(), shutdown.clone(), config.rate_limits).await;
+
+    let listener = TcpListener::bind(&config.bind_address)
+        .await
+        .expect("Should bind the address");
+    info!("Serving on {}", config.bind_address);
+    // With the peer address of every connection, for rate limiting by IP
+    axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal(shutdown))
+        .await
+        .expect("Should serve");
+
+    db.close().await;
+    info!("Shut down");
+}
+
+/// Pool of the configured database
+enum Database {
+    Postgres(PgPool),
+    #[cfg(feature = "sqlite")]
+    Sqlite(sqlx::SqlitePool),
+}
+
+impl Database {
+    async fn connect(config: &Config) -> Self {
+        if config.database_url.starts_with("sqlite:") {
+            #[cfg(not(feature = "sqlite"))]
+            panic!("SQLite DATABASE_URLs need the sqlite feature");
+            #[cfg(feature = "sqlite")]
+            return Self::connect_sqlite(config).await;
+        }
+
+        let db = PgPoolOptions::new()
+            .max_connections(config.database_max_connections)
+            .connect(&config.database_url)
+            .await
+            .expect("Should connect to the database");
+        Self::Postgres(db)
+    }
+
+    #[cfg(feature = "sqlite")]
+    async fn connect_sqlite(config: &Config) -> Self {
+        use std::str::FromStr;
+        use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+
+        let options = SqliteConnectOptions::from_str(&config.database_url)
+            .expect("DATABASE_URL should be a valid SQLite URL")
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
+        let db = SqlitePoolOptions::new()
+            .max_connections(config.database_max_connections)
+            .connect_with(options)
+            .await
+            .expect("Should open the database");
+        Self::Sqlite(db)
+    }
+
+    async fn migrate(&self) {
+        let migrated = match self {
+            Self::Postgres(db) => leaderboard::store::postgres::MIGRATOR.run(db).await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(db) => leaderboard::store::sqlite::MIGRATOR.run(db).await,
+        };
+        migrated.expect("Looks like something went wrong with migrations :(");
+    }
+
+    fn store(&self) -> Arc<dyn LeaderboardStore> {
+        match self {
+            Self::Postgres(db) => Arc::new(PgStore::new(db.clone())),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(db) => Arc::new(leaderboard::store::SqliteStore::new(db.clone())),
+        }
+    }
+
+    /// Postgres to fan stream updates out through, SQLite databases are not shared between instances
+    fn postgres(&self) -> Option<PgPool> {
+        match self {
+            Self::Postgres(db) => Some(db.clone()),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(_) => None,
+        }
+    }
+
+    async fn close(&self) {
+        match self {
+            Self::Postgres(db) => db.close().await,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(db) => db.close().await,
+        }
+    }
+}
+
+/// Waits for SIGTERM or Ctrl+C, then ends the streams and WebSocket sessions
+async fn shutdown_signal(shutdown: Shutdown) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("Should listen for Ctrl+C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Should listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    warn!("Shutting down, draining open connections");
+    shutdown.trigger();
+}
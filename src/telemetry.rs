@@ -0,0 +1,45 @@
+//! Structured logging. Every request gets a span carrying its `X-Request-Id`, generated unless the client sent one,
+//! and every store operation a span inside it, so one slow request can be followed through the JSON logs.
+
+use std::time::Duration;
+
+use axum::extract::Request;
+use axum::http::HeaderName;
+use axum::response::Response;
+use tracing::{field, info_span, Span};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Logs JSON lines to stdout, filtered by `RUST_LOG` and at `info` by default.
+/// Spans are logged as they close, with their durations. The `log` records of dependencies are picked up too
+pub fn init_logging() {
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with_span_events(FmtSpan::CLOSE)
+        .with_current_span(true)
+        .with_span_list(true)
+        .init();
+}
+
+/// Id of the request, as set by the request id layer
+pub fn request_id(request: &Request) -> Option<&str> {
+    request.headers().get(REQUEST_ID_HEADER).and_then(|id| id.to_str().ok())
+}
+
+/// Span of the request, its status recorded once it is answered
+pub fn request_span(request: &Request) -> Span {
+    info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id = request_id(request).unwrap_or_default(),
+        status = field::Empty,
+    )
+}
+
+pub fn record_status(response: &Response, _latency: Duration, span: &Span) {
+    span.record("status", response.status().as_u16());
+}
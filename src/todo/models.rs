@@ -1,7 +1,7 @@
 pub use crate::models::MutationKind;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Serialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct TodoUpdate {
     pub mutation_kind: MutationKind,
     pub id: i32,
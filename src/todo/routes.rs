@@ -7,17 +7,21 @@ use axum::{
 use serde_json::json;
 use std::convert::Infallible;
 use std::time::Duration;
-use log::error;
 use tokio::sync::broadcast::Sender;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::{Stream, StreamExt as _};
 
 use super::models::{MutationKind, Todo, TodoNew, TodoUpdate};
 use super::templates;
-use crate::{errors::ApiError, app_state::AppState};
+use sqlx::PgPool;
+use crate::{errors::ApiError, app_state::AppState, pubsub};
 
 pub type TodosStream = Sender<TodoUpdate>;
 
+/// Postgres channel the updates are published on
+pub const NOTIFY_CHANNEL: &str = "todo_updates";
+
 pub async fn home() -> impl IntoResponse {
     templates::HelloTemplate
 }
@@ -26,16 +30,16 @@ pub async fn stream() -> impl IntoResponse {
     templates::StreamTemplate
 }
 
-pub async fn fetch_todos(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+pub async fn fetch_todos(Extension(db): Extension<PgPool>) -> Result<impl IntoResponse, ApiError> {
     let todos = sqlx::query_as::<_, Todo>("SELECT * FROM TODOS")
-        .fetch_all(&state.db)
+        .fetch_all(&db)
         .await?;
 
     Ok(templates::Records { todos })
 }
 
 pub async fn create_todo(
-    State(state): State<AppState>,
+    Extension(db): Extension<PgPool>,
     Extension(tx): Extension<TodosStream>,
     Form(form): Form<TodoNew>,
 ) -> impl IntoResponse {
@@ -43,62 +47,65 @@ pub async fn create_todo(
         "INSERT INTO TODOS (description) VALUES ($1) RETURNING id, description",
     )
     .bind(form.description)
-    .fetch_one(&state.db)
+    .fetch_one(&db)
     .await
     .unwrap();
 
-    if tx
-        .send(TodoUpdate {
-            mutation_kind: MutationKind::Create,
-            id: todo.id,
-        })
-        .is_err()
-    {
-        error!(
-            "Record with ID {} was created but nobody's listening to the stream!",
-            todo.id
-        );
-    }
+    let update = TodoUpdate {
+        mutation_kind: MutationKind::Create,
+        id: todo.id,
+    };
+    publish(&db, &tx, update).await;
 
     templates::TodoNewTemplate { todo }
 }
 pub async fn delete_todo(
-    State(state): State<AppState>,
-    Path(id): Path<i32>,
+    Extension(db): Extension<PgPool>,
     Extension(tx): Extension<TodosStream>,
+    Path(id): Path<i32>,
 ) -> Result<impl IntoResponse, ApiError> {
     sqlx::query("DELETE FROM TODOS WHERE ID = $1")
         .bind(id)
-        .execute(&state.db)
+        .execute(&db)
         .await?;
 
-    if tx
-        .send(TodoUpdate {
-            mutation_kind: MutationKind::Delete,
-            id,
-        })
-        .is_err()
-    {
-        error!(
-            "Record with ID {} was deleted but nobody's listening to the stream!",
-            id
-        );
-    }
+    let update = TodoUpdate {
+        mutation_kind: MutationKind::Delete,
+        id,
+    };
+    publish(&db, &tx, update).await;
 
     Ok(StatusCode::OK)
 }
 
+/// Sends the update to the streams of every instance, or of this one when Postgres can not be reached
+async fn publish(db: &PgPool, tx: &TodosStream, update: TodoUpdate) {
+    pubsub::publish_or_send(db, NOTIFY_CHANNEL, update, |update| {
+        // No subscribers is fine
+        let _ = tx.send(update);
+    }).await;
+}
+
 pub async fn handle_stream(
+    State(state): State<AppState>,
     Extension(tx): Extension<TodosStream>,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
     let rx = tx.subscribe();
 
-    let stream = BroadcastStream::new(rx);
+    let stream = futures_util::StreamExt::take_until(BroadcastStream::new(rx), state.shutdown.triggered());
+    let metrics = state.metrics.clone();
 
     Sse::new(
         stream
+            // Lagging subscribers skip the updates they missed
+            .filter_map(move |msg| match msg {
+                Ok(msg) => Some(msg),
+                Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                    metrics.record_lagged("todos", skipped);
+                    None
+                }
+            })
             .map(|msg| {
-                let msg = msg.unwrap();
                 let json = format!("<div>{}</div>", json!(msg));
                 Event::default().data(json)
             })
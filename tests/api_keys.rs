@@ -0,0 +1,171 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::StatusCode;
+use fraculation_leaderboard::leaderboard::models::ApiKeyRole;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+struct MintedKey {
+    pub id: i32,
+    pub key: String,
+}
+
+async fn create_game(server: &impl MyTestServer) -> HasId {
+    let req = json!({ "description": "Test Game Description k3y001" });
+    let x = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    x
+}
+
+async fn submit(server: &impl MyTestServer, game_id: i32) -> StatusCode {
+    let req = json!({ "score": 1.0, "user_name": "keyholder" });
+    let path = format!("/leaderboard/games/{}/entries", game_id);
+    let x = server
+        .post_json(path.as_str(), &req)
+        .await
+        .status_code();
+    x
+}
+
+#[tokio::test]
+async fn reads_are_anonymous_and_writes_are_not() {
+    let anonymous = get_app_with_key(None).await;
+
+    let status = anonymous.get("/leaderboard/games").await.status_code();
+    assert_eq!(StatusCode::OK, status);
+
+    let req = json!({ "description": "Test Game Description k3y002" });
+    let status = anonymous
+        .post_json("/leaderboard/games", &req)
+        .await
+        .status_code();
+    assert_eq!(StatusCode::UNAUTHORIZED, status);
+
+    let unknown = get_app_with_key(Some("lb_not_a_key")).await;
+    let status = unknown
+        .post_json("/leaderboard/games", &req)
+        .await
+        .status_code();
+    assert_eq!(StatusCode::UNAUTHORIZED, status);
+}
+
+#[tokio::test]
+async fn submitters_only_submit_to_their_game() {
+    let admin = get_app().await;
+    let (game, other_game) = (create_game(&admin).await, create_game(&admin).await);
+    let submitter = get_app_with_key(Some(&mint_key(ApiKeyRole::Submitter, Some(game.id)).await)).await;
+
+    assert_eq!(StatusCode::OK, submit(&submitter, game.id).await);
+    assert_eq!(StatusCode::FORBIDDEN, submit(&submitter, other_game.id).await);
+
+    let req = json!({ "description": "Test Game Description k3y003" });
+    let status = submitter
+        .post_json("/leaderboard/games", &req)
+        .await
+        .status_code();
+    assert_eq!(StatusCode::FORBIDDEN, status);
+}
+
+#[tokio::test]
+async fn game_owners_manage_only_their_game() {
+    let admin = get_app().await;
+    let (game, other_game) = (create_game(&admin).await, create_game(&admin).await);
+    let owner = get_app_with_key(Some(&mint_key(ApiKeyRole::GameOwner, Some(game.id)).await)).await;
+
+    let req = json!({ "description": "Test Game Description k3y004" });
+    let status = owner
+        .patch_json(format!("/leaderboard/games/{}", game.id).as_str(), &req)
+        .await
+        .status_code();
+    assert_eq!(StatusCode::OK, status);
+    let status = owner
+        .patch_json(format!("/leaderboard/games/{}", other_game.id).as_str(), &req)
+        .await
+        .status_code();
+    assert_eq!(StatusCode::FORBIDDEN, status);
+
+    let status = owner
+        .delete(format!("/leaderboard/games/{}", game.id).as_str())
+        .await
+        .status_code();
+    assert_eq!(StatusCode::FORBIDDEN, status);
+}
+
+#[tokio::test]
+async fn minted_keys_work_until_revoked() {
+    let admin = get_app().await;
+    let game = create_game(&admin).await;
+
+    let req = json!({ "name": "game server", "role": "Submitter" });
+    let minted = admin
+        .post_json("/leaderboard/admin/keys", &req)
+        .await
+        .json::<MintedKey>();
+    let submitter = get_app_with_key(Some(&minted.key)).await;
+    assert_eq!(StatusCode::OK, submit(&submitter, game.id).await);
+
+    admin
+        .delete(format!("/leaderboard/admin/keys/{}", minted.id).as_str())
+        .await
+        .json::<HasId>();
+    assert_eq!(StatusCode::UNAUTHORIZED, submit(&submitter, game.id).await);
+
+    let status = admin
+        .delete(format!("/leaderboard/admin/keys/{}", minted.id).as_str())
+        .await
+        .status_code();
+    assert_eq!(StatusCode::CONFLICT, status);
+}
+
+#[tokio::test]
+async fn deleting_a_game_revokes_its_keys() {
+    let admin = get_app().await;
+    let game = create_game(&admin).await;
+    let req = json!({ "name": "game server", "role": "GameOwner", "game_id": game.id });
+    let minted = admin
+        .post_json("/leaderboard/admin/keys", &req)
+        .await
+        .json::<MintedKey>();
+    let submitter = get_app_with_key(Some(&minted.key)).await;
+
+    admin
+        .delete(format!("/leaderboard/games/{}", game.id).as_str())
+        .await
+        .json::<HasId>();
+
+    assert_eq!(StatusCode::UNAUTHORIZED, submit(&submitter, game.id).await);
+    let keys = admin.get("/leaderboard/admin/keys").await.json::<Vec<serde_json::Value>>();
+    let key = keys.iter().find(|key| key["id"] == json!(minted.id)).expect("revoked keys stay on record");
+    assert_eq!(json!(game.id), key["game_id"]);
+    assert!(key["revoked_at"].is_string());
+}
+
+#[tokio::test]
+async fn game_owner_keys_need_a_game() {
+    let admin = get_app().await;
+
+    let req = json!({ "name": "owner", "role": "GameOwner" });
+    let status = admin
+        .post_json("/leaderboard/admin/keys", &req)
+        .await
+        .status_code();
+
+    assert_eq!(StatusCode::BAD_REQUEST, status);
+}
+
+#[tokio::test]
+async fn only_admins_mint_keys() {
+    let submitter = get_app_with_key(Some(&mint_key(ApiKeyRole::Submitter, None).await)).await;
+
+    let req = json!({ "name": "escalation", "role": "Admin" });
+    let status = submitter
+        .post_json("/leaderboard/admin/keys", &req)
+        .await
+        .status_code();
+
+    assert_eq!(StatusCode::FORBIDDEN, status);
+}
@@ -1,3 +1,8 @@
+#![allow(dead_code)]
+
 pub mod my_test_server;
-mod postgres;
-pub mod test_models;
\ No newline at end of file
+#[cfg(not(feature = "sqlite"))]
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod test_models;
@@ -1,27 +1,125 @@
-use axum::http::header::ACCEPT;
-use axum::http::{HeaderValue, StatusCode};
+use axum::http::header::{ACCEPT, AUTHORIZATION};
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum::Router;
 use axum_test::{TestResponse, TestServer};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::future::IntoFuture;
-use fraculation_leaderboard::router::init_router;
+use std::sync::Arc;
+use fraculation_leaderboard::leaderboard::auth::insert_api_key;
+use fraculation_leaderboard::leaderboard::models::{ApiKeyNew, ApiKeyRole};
+use fraculation_leaderboard::leaderboard::rate_limit::RateLimits;
+use fraculation_leaderboard::leaderboard::store::{LeaderboardStore, MemoryStore};
+use fraculation_leaderboard::router::init_router_with;
+use fraculation_leaderboard::shutdown::Shutdown;
+#[cfg(not(feature = "sqlite"))]
+use fraculation_leaderboard::leaderboard::store::PgStore;
+#[cfg(not(feature = "sqlite"))]
 use crate::common::postgres::get_shared_pool;
+#[cfg(feature = "sqlite")]
+use fraculation_leaderboard::leaderboard::store::SqliteStore;
+#[cfg(feature = "sqlite")]
+use crate::common::sqlite::get_shared_pool;
 
+/// Server authenticated with a fresh admin key
 pub async fn get_app() -> impl MyTestServer {
+    let admin_key = mint_key(ApiKeyRole::Admin, None).await;
+    get_app_with_key(Some(&admin_key)).await
+}
+
+pub async fn get_app_with_key(api_key: Option<&str>) -> impl MyTestServer {
+    server_for(get_router().await, api_key)
+}
+
+/// Admin server along with its router, for requests the test server can not make, like reading streams
+pub async fn get_app_and_router() -> (impl MyTestServer, Router) {
+    let admin_key = mint_key(ApiKeyRole::Admin, None).await;
+    let router = get_router().await;
+    (server_for(router.clone(), Some(&admin_key)), router)
+}
+
+/// Admin server keeping everything in a fresh in-memory store
+pub async fn get_memory_app() -> impl MyTestServer {
+    get_memory_app_with_limits(RateLimits::UNLIMITED).await
+}
+
+/// Admin server keeping everything in a fresh in-memory store, throttled by the limits
+pub async fn get_memory_app_with_limits(rate_limits: RateLimits) -> impl MyTestServer {
+    let store = Arc::new(MemoryStore::new());
+    let request = ApiKeyNew { name: "test key".into(), role: ApiKeyRole::Admin, game_id: None };
+    let admin_key = insert_api_key(store.as_ref(), &request).await.unwrap().key;
+    let router = init_router_with(store, None, Shutdown::new(), rate_limits).await;
+    server_for(router, Some(&admin_key))
+}
+
+pub async fn get_router() -> Router {
+    get_router_with(Shutdown::new()).await
+}
+
+/// App on the shared test database, on Postgres or on SQLite with the `sqlite` feature, without rate limits
+#[cfg(not(feature = "sqlite"))]
+pub async fn get_router_with(shutdown: Shutdown) -> Router {
     let pg = get_shared_pool().await;
-    let app = init_router(pg);
+    init_router_with(Arc::new(PgStore::new(pg.clone())), Some(pg), shutdown, RateLimits::UNLIMITED).await
+}
+
+/// App on the shared test database, on Postgres or on SQLite with the `sqlite` feature, without rate limits
+#[cfg(feature = "sqlite")]
+pub async fn get_router_with(shutdown: Shutdown) -> Router {
+    init_router_with(get_store().await, None, shutdown, RateLimits::UNLIMITED).await
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub async fn get_store() -> Arc<dyn LeaderboardStore> {
+    Arc::new(PgStore::new(get_shared_pool().await))
+}
+
+#[cfg(feature = "sqlite")]
+pub async fn get_store() -> Arc<dyn LeaderboardStore> {
+    Arc::new(SqliteStore::new(get_shared_pool().await))
+}
+
+fn server_for(app: Router, api_key: Option<&str>) -> TestServer {
     let mut server = TestServer::new(app).unwrap();
     server.add_header(ACCEPT, HeaderValue::from_static("application/json"));
+    if let Some(api_key) = api_key {
+        server.add_header(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {api_key}")).unwrap());
+    }
 
     server
 }
 
+/// Stores a new API key, returning the key itself
+pub async fn mint_key(role: ApiKeyRole, game_id: Option<i32>) -> String {
+    let request = ApiKeyNew { name: "test key".into(), role, game_id };
+    insert_api_key(get_store().await.as_ref(), &request).await.unwrap().key
+}
+
 pub trait MyTestServer {
     fn post_json<T>(&self, path: &str, json: &T) -> impl IntoFuture<Output = impl MyTestResponse>
     where
         T: ?Sized + Serialize;
 
+    fn post_json_with_headers<T>(
+        &self,
+        path: &str,
+        json: &T,
+        headers: &[(&'static str, String)],
+    ) -> impl IntoFuture<Output = impl MyTestResponse>
+    where
+        T: ?Sized + Serialize;
+
+    fn patch_json<T>(&self, path: &str, json: &T) -> impl IntoFuture<Output = impl MyTestResponse>
+    where
+        T: ?Sized + Serialize;
+
     fn get(&self, path: &str) -> impl IntoFuture<Output = impl MyTestResponse>;
+
+    fn delete(&self, path: &str) -> impl IntoFuture<Output = impl MyTestResponse>;
+
+    fn get_query<T>(&self, path: &str, query: &T) -> impl IntoFuture<Output = impl MyTestResponse>
+    where
+        T: ?Sized + Serialize;
 }
 
 pub trait MyTestRequest {}
@@ -33,6 +131,8 @@ pub trait MyTestResponse {
         where
             T: DeserializeOwned;
     fn status_code(&self) -> StatusCode;
+    fn text(&self) -> String;
+    fn header(&self, name: &str) -> String;
 }
 
 impl MyTestServer for TestServer {
@@ -43,9 +143,41 @@ impl MyTestServer for TestServer {
         self.post(path).json(&json)
     }
 
+    fn post_json_with_headers<T>(
+        &self,
+        path: &str,
+        json: &T,
+        headers: &[(&'static str, String)],
+    ) -> impl IntoFuture<Output = impl MyTestResponse>
+    where
+        T: ?Sized + Serialize,
+    {
+        headers.iter().fold(self.post(path).json(&json), |request, (name, value)| {
+            request.add_header(HeaderName::from_static(name), HeaderValue::from_str(value).unwrap())
+        })
+    }
+
+    fn patch_json<T>(&self, path: &str, json: &T) -> impl IntoFuture<Output = impl MyTestResponse>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.patch(path).json(&json)
+    }
+
     fn get(&self, path: &str) -> impl IntoFuture<Output = impl MyTestResponse> {
         self.get(path)
     }
+
+    fn delete(&self, path: &str) -> impl IntoFuture<Output = impl MyTestResponse> {
+        self.delete(path)
+    }
+
+    fn get_query<T>(&self, path: &str, query: &T) -> impl IntoFuture<Output = impl MyTestResponse>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.get(path).add_query_params(query)
+    }
 }
 
 impl MyTestResponse for TestResponse {
@@ -67,4 +199,12 @@ impl MyTestResponse for TestResponse {
     fn status_code(&self) -> StatusCode {
         self.status_code()
     }
+
+    fn text(&self) -> String {
+        self.text()
+    }
+
+    fn header(&self, name: &str) -> String {
+        self.header(name).to_str().unwrap().to_owned()
+    }
 }
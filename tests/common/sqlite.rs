@@ -0,0 +1,32 @@
+use std::str::FromStr;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode};
+use sqlx::SqlitePool;
+use tokio::sync::OnceCell;
+
+use fraculation_leaderboard::leaderboard::store::sqlite::MIGRATOR;
+
+/// Database file shared by the tests of this process, migrated by whichever test gets to it first
+static MIGRATED_URL: OnceCell<String> = OnceCell::const_new();
+
+pub async fn get_shared_pool() -> SqlitePool {
+    let url = MIGRATED_URL.get_or_init(|| async {
+        let path = std::env::temp_dir().join(format!("fraculation-leaderboard-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite:{}", path.display());
+        MIGRATOR.run(&connect(&url).await)
+            .await
+            .expect("Test database migrations should apply");
+        url
+    }).await;
+
+    connect(url).await
+}
+
+async fn connect(url: &str) -> SqlitePool {
+    let options = SqliteConnectOptions::from_str(url)
+        .unwrap()
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal);
+    SqlitePool::connect_with(options).await.unwrap()
+}
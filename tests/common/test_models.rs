@@ -4,3 +4,9 @@ use serde::Deserialize;
 pub struct HasId {
     pub id: i32,
 }
+
+#[derive(Deserialize)]
+pub struct EntriesPage<T> {
+    pub entries: Vec<T>,
+    pub next_cursor: Option<String>,
+}
@@ -0,0 +1,96 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::header::{ACCEPT, CONTENT_TYPE};
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum_test::TestServer;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn json_clients_get_problems() {
+    let server = get_app().await;
+
+    let mut problem = server
+        .get("/leaderboard/games/-1")
+        .await
+        .json_allow_fail::<Value>();
+
+    let request_id = problem.as_object_mut().unwrap().remove("request_id");
+    assert!(request_id.is_some_and(|id| !id.as_str().unwrap().is_empty()));
+    assert_eq!(
+        json!({ "type": "about:blank", "title": "Not Found", "status": 404, "detail": "Not Found", "code": "not_found" }),
+        problem,
+    );
+}
+
+#[tokio::test]
+async fn problems_have_codes_per_failure() {
+    let anonymous = get_app_with_key(None).await;
+    let req = json!({ "description": "Test Game Description pr0b01" });
+    let problem = anonymous
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json_allow_fail::<Value>();
+    assert_eq!(json!("missing_api_key"), problem["code"]);
+    assert_eq!(json!(401), problem["status"]);
+
+    let server = get_app().await;
+    let req = json!({ "description": "Test Game Description pr0b02", "window_timezone": "Mars/Olympus_Mons" });
+    let problem = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json_allow_fail::<Value>();
+    assert_eq!(json!("invalid_timezone"), problem["code"]);
+
+    let req = json!({ "description": "Test Game Description pr0b03", "max_score": 10.0 });
+    let game = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    let req = json!({ "score": 11.0, "user_name": "problematic" });
+    let problem = server
+        .post_json(format!("/leaderboard/games/{}/entries", game.id).as_str(), &req)
+        .await
+        .json_allow_fail::<Value>();
+    assert_eq!(json!("score_rejected"), problem["code"]);
+    assert_eq!(json!("MaxScore"), problem["rule"]);
+    assert_eq!(json!(10.0), problem["limit"]);
+}
+
+#[tokio::test]
+async fn htmx_clients_get_html_fragments() {
+    let server = TestServer::new(get_router().await).unwrap();
+
+    let response = server.get("/leaderboard/games/-1").await;
+
+    assert_eq!(StatusCode::NOT_FOUND, response.status_code());
+    assert!(response.header(CONTENT_TYPE).to_str().unwrap().starts_with("text/html"));
+    assert!(response.text().contains(r#"data-error-code="not_found""#));
+    assert!(response.text().contains("Request id: "));
+}
+
+#[tokio::test]
+async fn problems_carry_the_request_id() {
+    let server = TestServer::new(get_router().await).unwrap();
+
+    let response = server
+        .get("/leaderboard/games/-1")
+        .add_header(ACCEPT, HeaderValue::from_static("application/json"))
+        .add_header(HeaderName::from_static("x-request-id"), HeaderValue::from_static("r3qu35t-1d"))
+        .await;
+
+    assert_eq!("r3qu35t-1d", response.header("x-request-id"));
+    assert_eq!(json!("r3qu35t-1d"), response.json::<Value>()["request_id"]);
+}
+
+#[tokio::test]
+async fn requests_without_an_id_get_one() {
+    let server = TestServer::new(get_router().await).unwrap();
+
+    let first = server.get("/leaderboard/games").await;
+    let second = server.get("/leaderboard/games").await;
+
+    let first = first.header("x-request-id");
+    assert!(!first.is_empty());
+    assert_ne!(first, second.header("x-request-id"));
+}
@@ -5,6 +5,7 @@ use common::test_models::*;
 use fraculation_leaderboard::leaderboard::models::Game;
 
 use assert_json_diff::assert_json_include;
+use axum::http::StatusCode;
 use serde::Deserialize;
 use serde_json::json;
 
@@ -95,6 +96,144 @@ mod game {
         }
     }
 
+    mod update {
+        use super::*;
+
+        #[tokio::test]
+        async fn changes_only_given_fields() {
+            let server = get_app().await;
+
+            let req = json!({
+                "description": "Test Game Descriptoin 5512",
+                "score_sort_mode": "LesserIsBetter",
+            });
+            let new_game = server
+                .post_json("/leaderboard/games", &req)
+                .await
+                .json::<HasId>();
+
+            let req = json!({
+                "description": "Test Game Description 5512",
+            });
+            let response = server
+                .patch_json(format!("/leaderboard/games/{}", new_game.id).as_str(), &req)
+                .await
+                .json::<serde_json::Value>();
+
+            assert_json_include!(actual: response, expected: json!({
+                "id": new_game.id,
+                "description": "Test Game Description 5512",
+                "score_sort_mode": "LesserIsBetter",
+            }));
+        }
+
+        #[tokio::test]
+        async fn flipping_sort_mode_reranks_entries() {
+            #[derive(Deserialize)]
+            struct HasScore {
+                pub score: f64,
+            }
+            let server = get_app().await;
+
+            let req = json!({
+                "description": "Test Game Description 5513",
+            });
+            let new_game = server
+                .post_json("/leaderboard/games", &req)
+                .await
+                .json::<HasId>();
+            let path = format!("/leaderboard/games/{}/entries", new_game.id);
+            for score in [13.0, 22.0] {
+                let req = json!({ "score": score, "user_name": "flipper" });
+                server.post_json(path.as_str(), &req).await.json::<HasId>();
+            }
+
+            let req = json!({
+                "score_sort_mode": "LesserIsBetter",
+            });
+            server
+                .patch_json(format!("/leaderboard/games/{}", new_game.id).as_str(), &req)
+                .await
+                .json::<HasId>();
+
+            let scores = server
+                .get(path.as_str())
+                .await
+                .json::<EntriesPage<HasScore>>()
+                .entries
+                .iter()
+                .map(|x| x.score)
+                .collect::<Vec<_>>();
+            assert_eq!(vec![13.0, 22.0], scores);
+        }
+
+        #[tokio::test]
+        async fn missing_game_is_not_found() {
+            let server = get_app().await;
+
+            let req = json!({
+                "description": "Test Game Description 5514",
+            });
+            let status = server
+                .patch_json("/leaderboard/games/-1", &req)
+                .await
+                .status_code();
+
+            assert_eq!(StatusCode::NOT_FOUND, status);
+        }
+    }
+
+    mod delete {
+        use super::*;
+
+        #[tokio::test]
+        async fn deletes_game_with_entries() {
+            let server = get_app().await;
+
+            let req = json!({
+                "description": "Test Game Description 6612",
+            });
+            let new_game = server
+                .post_json("/leaderboard/games", &req)
+                .await
+                .json::<HasId>();
+            let req = json!({ "score": 3.0, "user_name": "deleted" });
+            server
+                .post_json(format!("/leaderboard/games/{}/entries", new_game.id).as_str(), &req)
+                .await
+                .json::<HasId>();
+            let req = json!({ "name": "Season 1" });
+            server
+                .post_json(format!("/leaderboard/games/{}/seasons", new_game.id).as_str(), &req)
+                .await
+                .json::<HasId>();
+
+            let deleted = server
+                .delete(format!("/leaderboard/games/{}", new_game.id).as_str())
+                .await
+                .json::<HasId>();
+            assert_eq!(new_game.id, deleted.id);
+
+            let status = server
+                .get(format!("/leaderboard/games/{}", new_game.id).as_str())
+                .await
+                .status_code();
+            assert_eq!(StatusCode::NOT_FOUND, status);
+        }
+
+        #[tokio::test]
+        async fn missing_game_is_not_found() {
+            let server = get_app().await;
+
+            let status = server
+                .delete("/leaderboard/games/-1")
+                .await
+                .status_code();
+
+            assert_eq!(StatusCode::NOT_FOUND, status);
+        }
+    }
+
     mod entries {
         use super::*;
 
@@ -169,7 +308,8 @@ mod game {
                 let all_entries = server
                     .get(format!("/leaderboard/games/{}/entries", game_id).as_str())
                     .await
-                    .json::<Vec<HasScore>>();
+                    .json::<EntriesPage<HasScore>>()
+                    .entries;
 
                 let actual_scores = all_entries.iter().map(|x| x.score).collect::<Vec<_>>();
                 assert_eq!(expected_scores, actual_scores);
@@ -0,0 +1,117 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+struct HasIdAndScore {
+    pub id: i32,
+    pub score: f64,
+}
+
+async fn create_game_with_scores(server: &impl MyTestServer, desc: &str, scores: &[f64]) -> i32 {
+    let req = json!({ "description": desc });
+    let game = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+
+    for score in scores {
+        let req = json!({ "score": score, "user_name": "pager" });
+        server
+            .post_json(format!("/leaderboard/games/{}/entries", game.id).as_str(), &req)
+            .await
+            .json::<HasId>();
+    }
+    game.id
+}
+
+async fn get_page(server: &impl MyTestServer, game_id: i32, query: serde_json::Value) -> EntriesPage<HasIdAndScore> {
+    server
+        .get_query(format!("/leaderboard/games/{}/entries", game_id).as_str(), &query)
+        .await
+        .json::<EntriesPage<HasIdAndScore>>()
+}
+
+#[tokio::test]
+async fn defaults_to_ten_entries_with_cursor() {
+    let server = get_app().await;
+    let scores = (0..12).map(|x| x as f64).collect::<Vec<_>>();
+    let game_id = create_game_with_scores(&server, "Test Game Description 7812bbq", &scores).await;
+
+    let page = get_page(&server, game_id, json!({})).await;
+
+    assert_eq!(10, page.entries.len());
+    assert!(page.next_cursor.is_some());
+}
+
+#[tokio::test]
+async fn cursor_walks_every_entry_once_in_order() {
+    let server = get_app().await;
+    let scores = vec![5.0, 3.0, 5.0, 9.0, 1.0, 5.0, 7.0];
+    let game_id = create_game_with_scores(&server, "Test Game Description 11bca3", &scores).await;
+
+    let mut seen = vec![];
+    let mut query = json!({ "limit": 2 });
+    loop {
+        let page = get_page(&server, game_id, query).await;
+        assert!(page.entries.len() <= 2);
+        seen.extend(page.entries);
+        let Some(cursor) = page.next_cursor else { break };
+        query = json!({ "limit": 2, "cursor": cursor });
+    }
+
+    let seen_scores = seen.iter().map(|x| x.score).collect::<Vec<_>>();
+    assert_eq!(vec![9.0, 7.0, 5.0, 5.0, 5.0, 3.0, 1.0], seen_scores);
+    let tied_ids = seen[2..5].iter().map(|x| x.id).collect::<Vec<_>>();
+    let mut sorted_ids = tied_ids.clone();
+    sorted_ids.sort();
+    assert_eq!(sorted_ids, tied_ids);
+}
+
+#[tokio::test]
+async fn cursor_is_stable_when_better_scores_arrive() {
+    let server = get_app().await;
+    let game_id = create_game_with_scores(&server, "Test Game Description 0ac8812", &[4.0, 3.0, 2.0, 1.0]).await;
+
+    let first = get_page(&server, game_id, json!({ "limit": 2 })).await;
+    let req = json!({ "score": 100.0, "user_name": "late arrival" });
+    server
+        .post_json(format!("/leaderboard/games/{}/entries", game_id).as_str(), &req)
+        .await
+        .json::<HasId>();
+
+    let cursor = first.next_cursor.expect("first page should have a cursor");
+    let second = get_page(&server, game_id, json!({ "limit": 2, "cursor": cursor })).await;
+
+    let second_scores = second.entries.iter().map(|x| x.score).collect::<Vec<_>>();
+    assert_eq!(vec![2.0, 1.0], second_scores);
+    assert!(second.next_cursor.is_none());
+}
+
+#[tokio::test]
+async fn offset_skips_entries() {
+    let server = get_app().await;
+    let game_id = create_game_with_scores(&server, "Test Game Description 91kd0a", &[4.0, 3.0, 2.0, 1.0]).await;
+
+    let page = get_page(&server, game_id, json!({ "limit": 2, "offset": 1 })).await;
+
+    let scores = page.entries.iter().map(|x| x.score).collect::<Vec<_>>();
+    assert_eq!(vec![3.0, 2.0], scores);
+    assert!(page.next_cursor.is_some());
+}
+
+#[tokio::test]
+async fn rejects_invalid_cursor() {
+    let server = get_app().await;
+    let game_id = create_game_with_scores(&server, "Test Game Description 5bb1kx", &[1.0]).await;
+
+    let status = server
+        .get_query(format!("/leaderboard/games/{}/entries", game_id).as_str(), &json!({ "cursor": "garbage" }))
+        .await
+        .status_code();
+
+    assert_eq!(StatusCode::BAD_REQUEST, status);
+}
@@ -0,0 +1,184 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::body::{Body, BodyDataStream};
+use axum::http::header::ACCEPT;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use fraculation_leaderboard::shutdown::Shutdown;
+use serde_json::{json, Value};
+use sqlx::types::Uuid;
+use std::time::Duration;
+use tokio_stream::StreamExt;
+use tower::ServiceExt;
+
+/// Server sent events of a stream
+struct Events {
+    body: BodyDataStream,
+    buffer: String,
+}
+
+/// Id, name and data of an event
+type RawEvent = (Option<String>, Option<String>, String);
+
+impl Events {
+    async fn open(router: &Router, path: &str) -> (StatusCode, Events) {
+        Self::resume(router, path, None).await
+    }
+
+    async fn resume(router: &Router, path: &str, last_event_id: Option<&str>) -> (StatusCode, Events) {
+        let mut request = Request::get(path).header(ACCEPT, "application/json");
+        if let Some(last_event_id) = last_event_id {
+            request = request.header("last-event-id", last_event_id);
+        }
+        let response = router.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+        let status = response.status();
+        (status, Events { body: response.into_body().into_data_stream(), buffer: String::new() })
+    }
+
+    async fn next_raw(&mut self) -> RawEvent {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let event = self.buffer[..end].to_string();
+                self.buffer.drain(..end + 2);
+                let field = |name: &str| event.lines().find_map(|line| line.strip_prefix(name).map(String::from));
+                if let Some(data) = field("data: ") {
+                    return (field("id: "), field("event: "), data);
+                }
+                continue;
+            }
+            let chunk = tokio::time::timeout(Duration::from_secs(5), self.body.next())
+                .await
+                .expect("an event within 5 seconds")
+                .unwrap()
+                .unwrap();
+            self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+
+    /// Data of the next event, as JSON
+    async fn next(&mut self) -> Value {
+        serde_json::from_str(&self.next_raw().await.2).unwrap()
+    }
+}
+
+async fn create_game(server: &impl MyTestServer) -> HasId {
+    let req = json!({ "description": "Test Game Description str3am" });
+    let x = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    x
+}
+
+async fn submit(server: &impl MyTestServer, game_id: i32, user_id: Uuid, score: f64) {
+    let req = json!({ "score": score, "user_name": "streamer", "user_id": user_id });
+    server
+        .post_json(format!("/leaderboard/games/{}/entries", game_id).as_str(), &req)
+        .await
+        .json::<Value>();
+}
+
+#[tokio::test]
+async fn game_streams_carry_entries_and_rank_changes() {
+    let (server, router) = get_app_and_router().await;
+    let game = create_game(&server).await;
+    let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+    let (status, mut events) = Events::open(&router, &format!("/leaderboard/games/{}/stream", game.id)).await;
+    assert_eq!(StatusCode::OK, status);
+
+    submit(&server, game.id, first, 10.0).await;
+    let update = events.next().await;
+    assert_eq!(json!("Entry"), update["target"]);
+    assert_eq!(json!(game.id), update["game_id"]);
+    assert_eq!(json!(10.0), update["entry"]["score"]);
+    assert_eq!((json!(1), Value::Null), (update["rank"].clone(), update["previous_rank"].clone()));
+
+    submit(&server, game.id, second, 20.0).await;
+    let update = events.next().await;
+    assert_eq!((json!(1), Value::Null), (update["rank"].clone(), update["previous_rank"].clone()));
+
+    submit(&server, game.id, first, 30.0).await;
+    let update = events.next().await;
+    assert_eq!(json!(first), update["entry"]["user_id"]);
+    assert_eq!((json!(1), json!(2)), (update["rank"].clone(), update["previous_rank"].clone()));
+}
+
+#[tokio::test]
+async fn game_streams_skip_other_games() {
+    let (server, router) = get_app_and_router().await;
+    let (game, other_game) = (create_game(&server).await, create_game(&server).await);
+    let (_, mut events) = Events::open(&router, &format!("/leaderboard/games/{}/stream", game.id)).await;
+
+    submit(&server, other_game.id, Uuid::new_v4(), 10.0).await;
+    submit(&server, game.id, Uuid::new_v4(), 5.0).await;
+
+    let update = events.next().await;
+    assert_eq!(json!(game.id), update["game_id"]);
+    assert_eq!(json!(5.0), update["entry"]["score"]);
+}
+
+#[tokio::test]
+async fn unknown_games_have_no_stream() {
+    let (_, router) = get_app_and_router().await;
+
+    let (status, _) = Events::open(&router, "/leaderboard/games/-1/stream").await;
+
+    assert_eq!(StatusCode::NOT_FOUND, status);
+}
+
+#[tokio::test]
+async fn reconnecting_clients_get_missed_updates() {
+    let (server, router) = get_app_and_router().await;
+    let game = create_game(&server).await;
+    let path = format!("/leaderboard/games/{}/stream", game.id);
+    let (_, mut events) = Events::open(&router, &path).await;
+
+    for score in [1.0, 2.0, 3.0] {
+        submit(&server, game.id, Uuid::new_v4(), score).await;
+    }
+    let (first_id, _, _) = events.next_raw().await;
+    drop(events);
+
+    let (_, mut events) = Events::resume(&router, &path, first_id.as_deref()).await;
+    let (second_id, _, _) = events.next_raw().await;
+    assert!(second_id.unwrap().parse::<u64>().unwrap() > first_id.unwrap().parse::<u64>().unwrap());
+    assert_eq!(json!(3.0), events.next().await["entry"]["score"]);
+}
+
+#[tokio::test]
+async fn clients_resync_when_missed_updates_are_gone() {
+    let (server, router) = get_app_and_router().await;
+    let game = create_game(&server).await;
+
+    let (_, mut events) = Events::resume(&router, &format!("/leaderboard/games/{}/stream", game.id), Some("1")).await;
+
+    let (_, name, _) = events.next_raw().await;
+    assert_eq!(Some("resync".to_string()), name);
+}
+
+/// SQLite databases are not shared between instances, only Postgres fans updates out
+#[cfg(not(feature = "sqlite"))]
+#[tokio::test]
+async fn streams_see_updates_of_other_instances() {
+    let (server, _) = get_app_and_router().await;
+    let (_, other_instance) = get_app_and_router().await;
+    let game = create_game(&server).await;
+    let (_, mut events) = Events::open(&other_instance, &format!("/leaderboard/games/{}/stream", game.id)).await;
+
+    submit(&server, game.id, Uuid::new_v4(), 7.0).await;
+
+    assert_eq!(json!(7.0), events.next().await["entry"]["score"]);
+}
+
+#[tokio::test]
+async fn streams_end_on_shutdown() {
+    let shutdown = Shutdown::new();
+    let router = get_router_with(shutdown.clone()).await;
+    let (_, mut events) = Events::open(&router, "/leaderboard/stream").await;
+
+    shutdown.trigger();
+
+    let end = tokio::time::timeout(Duration::from_secs(5), events.body.next()).await;
+    assert!(end.expect("the stream to end within 5 seconds").is_none());
+}
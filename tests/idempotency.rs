@@ -0,0 +1,184 @@
+mod common;
+use common::my_test_server::*;
+#[cfg(not(feature = "sqlite"))]
+use common::postgres::get_shared_pool;
+#[cfg(feature = "sqlite")]
+use common::sqlite::get_shared_pool;
+use common::test_models::*;
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use sqlx::types::Uuid;
+
+async fn create_game(server: &impl MyTestServer, desc: &str) -> String {
+    let req = json!({ "description": desc });
+    let game = server.post_json("/leaderboard/games", &req).await.json::<HasId>();
+    format!("/leaderboard/games/{}/entries", game.id)
+}
+
+fn headers(key: &str) -> [(&'static str, String); 2] {
+    [("idempotency-key", key.to_string()), ("accept", "application/json".to_string())]
+}
+
+async fn submit(server: &impl MyTestServer, path: &str, key: &str, req: &Value) -> (StatusCode, Value) {
+    let headers = headers(key);
+    let response = server.post_json_with_headers(path, req, &headers).await;
+    (response.status_code(), response.json_allow_fail::<Value>())
+}
+
+async fn count_submissions(server: &impl MyTestServer, path: &str, user_id: Uuid) -> Value {
+    let path = path.replace("/leaderboard/games/", &format!("/leaderboard/users/{user_id}/games/")).replace("/entries", "/history");
+    let x = server.get(path.as_str()).await.json::<Value>()["stats"]["count"].clone();
+    x
+}
+
+/// Pretends the request which claimed the key was dropped before it finished, `minutes` ago
+#[cfg(not(feature = "sqlite"))]
+async fn abandon_claim(key: &str, minutes: i32) {
+    sqlx::query(
+        "UPDATE idempotency_keys \
+        SET response_status = NULL, response_body = NULL, created_at = now() - make_interval(mins => $2) \
+        WHERE key = $1;")
+        .bind(key)
+        .bind(minutes)
+        .execute(&get_shared_pool().await)
+        .await
+        .unwrap();
+}
+
+/// Pretends the request which claimed the key was dropped before it finished, `minutes` ago
+#[cfg(feature = "sqlite")]
+async fn abandon_claim(key: &str, minutes: i32) {
+    sqlx::query(
+        "UPDATE idempotency_keys \
+        SET response_status = NULL, response_body = NULL, \
+            created_at = strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now', '-' || $2 || ' minutes') \
+        WHERE key = $1;")
+        .bind(key)
+        .bind(minutes)
+        .execute(&get_shared_pool().await)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn retries_get_the_first_response() {
+    let server = get_app().await;
+    let path = create_game(&server, "Test Game Description 1dm3p0").await;
+    let user_id = Uuid::new_v4();
+    let req = json!({ "score": 10.0, "user_name": "flaky", "user_id": user_id });
+    let key = Uuid::new_v4().to_string();
+
+    let (status, entry) = submit(&server, &path, &key, &req).await;
+    assert_eq!(StatusCode::OK, status);
+    let headers = headers(&key);
+    let retry = server.post_json_with_headers(path.as_str(), &req, &headers).await;
+
+    // Without the key, the resubmitted score would conflict with the entry it created
+    assert_eq!(StatusCode::OK, retry.status_code());
+    assert_eq!("true", retry.header("idempotent-replayed"));
+    assert_eq!(entry, retry.json::<Value>());
+    assert_eq!(json!(1), count_submissions(&server, &path, user_id).await);
+}
+
+#[tokio::test]
+async fn retries_without_user_id_replay_the_assigned_one() {
+    let server = get_app().await;
+    let path = create_game(&server, "Test Game Description 2dm3p1").await;
+    let req = json!({ "score": 10.0, "user_name": "anonymous" });
+    let key = Uuid::new_v4().to_string();
+
+    let (_, entry) = submit(&server, &path, &key, &req).await;
+    let (_, retried) = submit(&server, &path, &key, &req).await;
+
+    assert_eq!(entry["user_id"], retried["user_id"]);
+}
+
+#[tokio::test]
+async fn conflicts_are_replayed() {
+    let server = get_app().await;
+    let path = create_game(&server, "Test Game Description 3dm3p2").await;
+    let user_id = Uuid::new_v4();
+    submit(&server, &path, "first", &json!({ "score": 20.0, "user_name": "steady", "user_id": user_id })).await;
+    let req = json!({ "score": 5.0, "user_name": "steady", "user_id": user_id });
+
+    let (status, better) = submit(&server, &path, "second", &req).await;
+    let (retried_status, retried) = submit(&server, &path, "second", &req).await;
+
+    assert_eq!(StatusCode::CONFLICT, status);
+    assert_eq!((status, better), (retried_status, retried));
+    assert_eq!(json!(2), count_submissions(&server, &path, user_id).await);
+}
+
+#[tokio::test]
+async fn keys_can_not_be_reused_for_another_request() {
+    let server = get_app().await;
+    let path = create_game(&server, "Test Game Description 4dm3p3").await;
+    let key = Uuid::new_v4().to_string();
+    submit(&server, &path, &key, &json!({ "score": 1.0, "user_name": "sloppy" })).await;
+
+    let (status, problem) = submit(&server, &path, &key, &json!({ "score": 2.0, "user_name": "sloppy" })).await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    assert_eq!(json!("idempotency_key_reused"), problem["code"]);
+}
+
+#[tokio::test]
+async fn keys_are_per_api_key() {
+    let server = get_app().await;
+    let other_server = get_app().await;
+    let path = create_game(&server, "Test Game Description 5dm3p4").await;
+    let key = Uuid::new_v4().to_string();
+    submit(&server, &path, &key, &json!({ "score": 1.0, "user_name": "first" })).await;
+
+    let (status, _) = submit(&other_server, &path, &key, &json!({ "score": 2.0, "user_name": "second" })).await;
+
+    assert_eq!(StatusCode::OK, status);
+}
+
+#[tokio::test]
+async fn rejected_submissions_release_the_key() {
+    let server = get_app().await;
+    let path = create_game(&server, "Test Game Description 6dm3p5").await;
+    let key = Uuid::new_v4().to_string();
+    let req = json!({ "score": 1.0, "user_name": "hasty", "score_components": [1.0] });
+
+    let (status, _) = submit(&server, &path, &key, &req).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status);
+    let (status, _) = submit(&server, &path, &key, &json!({ "score": 1.0, "user_name": "hasty" })).await;
+
+    assert_eq!(StatusCode::OK, status);
+}
+
+#[tokio::test]
+async fn abandoned_claims_are_taken_over_after_their_lease() {
+    let server = get_app().await;
+    let path = create_game(&server, "Test Game Description 8dm3p7").await;
+    let user_id = Uuid::new_v4();
+    let req = json!({ "score": 10.0, "user_name": "dropped", "user_id": user_id });
+    let key = Uuid::new_v4().to_string();
+    submit(&server, &path, &key, &req).await;
+
+    abandon_claim(&key, 0).await;
+    let (status, problem) = submit(&server, &path, &key, &req).await;
+    assert_eq!(StatusCode::CONFLICT, status);
+    assert_eq!(json!("idempotency_key_in_flight"), problem["code"]);
+
+    abandon_claim(&key, 2).await;
+    let (status, entry) = submit(&server, &path, &key, &req).await;
+
+    // Handled afresh, the resubmitted score conflicts with the entry it created
+    assert_eq!(StatusCode::CONFLICT, status);
+    assert_eq!(json!(10.0), entry["score"]);
+    assert_eq!(json!(2), count_submissions(&server, &path, user_id).await);
+}
+
+#[tokio::test]
+async fn malformed_keys_are_rejected() {
+    let server = get_app().await;
+    let path = create_game(&server, "Test Game Description 7dm3p6").await;
+
+    let (status, problem) = submit(&server, &path, &"k".repeat(256), &json!({ "score": 1.0, "user_name": "verbose" })).await;
+
+    assert_eq!(StatusCode::BAD_REQUEST, status);
+    assert_eq!(json!("invalid_idempotency_key"), problem["code"]);
+}
@@ -0,0 +1,163 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::types::Uuid;
+
+#[derive(Deserialize)]
+struct Standing {
+    pub score: f64,
+    pub rank: i64,
+    pub total: i64,
+}
+
+#[derive(Deserialize)]
+struct RankedEntry {
+    pub score: f64,
+    pub user_id: Uuid,
+    pub rank: i64,
+}
+
+#[derive(Deserialize)]
+struct Neighborhood {
+    pub entries: Vec<RankedEntry>,
+    pub total: i64,
+}
+
+/// Creates a game with one entry per score, returning the game id and the user id of each entry
+async fn create_game_with_scores(server: &impl MyTestServer, sort_mode: &str, scores: &[f64]) -> (i32, Vec<Uuid>) {
+    let req = json!({ "description": "Test Game Description 77ab1c", "score_sort_mode": sort_mode });
+    let game = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+
+    let mut user_ids = vec![];
+    for score in scores {
+        let user_id = Uuid::new_v4();
+        let req = json!({ "score": score, "user_name": "ranker", "user_id": user_id });
+        server
+            .post_json(format!("/leaderboard/games/{}/entries", game.id).as_str(), &req)
+            .await
+            .json::<HasId>();
+        user_ids.push(user_id);
+    }
+    (game.id, user_ids)
+}
+
+async fn get_standing(server: &impl MyTestServer, game_id: i32, user_id: Uuid) -> Standing {
+    server
+        .get(format!("/leaderboard/users/{}/games/{}/entries", user_id, game_id).as_str())
+        .await
+        .json::<Standing>()
+}
+
+#[tokio::test]
+async fn ranks_higher_scores_first() {
+    let server = get_app().await;
+    let (game_id, users) = create_game_with_scores(&server, "HigherIsBetter", &[10.0, 30.0, 20.0]).await;
+
+    let standings = [
+        get_standing(&server, game_id, users[0]).await,
+        get_standing(&server, game_id, users[1]).await,
+        get_standing(&server, game_id, users[2]).await,
+    ];
+
+    let ranks = standings.iter().map(|x| (x.score, x.rank, x.total)).collect::<Vec<_>>();
+    assert_eq!(vec![(10.0, 3, 3), (30.0, 1, 3), (20.0, 2, 3)], ranks);
+}
+
+#[tokio::test]
+async fn ranks_lesser_scores_first() {
+    let server = get_app().await;
+    let (game_id, users) = create_game_with_scores(&server, "LesserIsBetter", &[10.0, 30.0, 20.0]).await;
+
+    let standing = get_standing(&server, game_id, users[0]).await;
+
+    assert_eq!(1, standing.rank);
+    assert_eq!(3, standing.total);
+}
+
+#[tokio::test]
+async fn ties_are_ranked_by_first_entry() {
+    let server = get_app().await;
+    let (game_id, users) = create_game_with_scores(&server, "HigherIsBetter", &[5.0, 5.0, 5.0]).await;
+
+    let ranks = [
+        get_standing(&server, game_id, users[0]).await.rank,
+        get_standing(&server, game_id, users[1]).await.rank,
+        get_standing(&server, game_id, users[2]).await.rank,
+    ];
+
+    assert_eq!([1, 2, 3], ranks);
+}
+
+#[tokio::test]
+async fn ties_are_ranked_by_who_reached_the_score_first() {
+    let server = get_app().await;
+    let (game_id, users) = create_game_with_scores(&server, "HigherIsBetter", &[1.0, 10.0]).await;
+    let req = json!({ "score": 10.0, "user_name": "ranker", "user_id": users[0] });
+    server
+        .post_json(format!("/leaderboard/games/{}/entries", game_id).as_str(), &req)
+        .await
+        .json::<HasId>();
+
+    let ranks = [
+        get_standing(&server, game_id, users[0]).await.rank,
+        get_standing(&server, game_id, users[1]).await.rank,
+    ];
+
+    assert_eq!([2, 1], ranks);
+}
+
+#[tokio::test]
+async fn neighborhood_surrounds_user() {
+    let server = get_app().await;
+    let scores = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+    let (game_id, users) = create_game_with_scores(&server, "HigherIsBetter", &scores).await;
+
+    let neighborhood = server
+        .get_query(
+            format!("/leaderboard/users/{}/games/{}/neighborhood", users[3], game_id).as_str(),
+            &json!({ "radius": 2 }),
+        )
+        .await
+        .json::<Neighborhood>();
+
+    assert_eq!(7, neighborhood.total);
+    let window = neighborhood.entries.iter().map(|x| (x.score, x.rank)).collect::<Vec<_>>();
+    assert_eq!(vec![(6.0, 2), (5.0, 3), (4.0, 4), (3.0, 5), (2.0, 6)], window);
+    assert_eq!(users[3], neighborhood.entries[2].user_id);
+}
+
+#[tokio::test]
+async fn neighborhood_is_clipped_at_the_top() {
+    let server = get_app().await;
+    let (game_id, users) = create_game_with_scores(&server, "HigherIsBetter", &[1.0, 2.0, 3.0]).await;
+
+    let neighborhood = server
+        .get_query(
+            format!("/leaderboard/users/{}/games/{}/neighborhood", users[2], game_id).as_str(),
+            &json!({ "radius": 5 }),
+        )
+        .await
+        .json::<Neighborhood>();
+
+    let window = neighborhood.entries.iter().map(|x| (x.score, x.rank)).collect::<Vec<_>>();
+    assert_eq!(vec![(3.0, 1), (2.0, 2), (1.0, 3)], window);
+}
+
+#[tokio::test]
+async fn neighborhood_404s_without_entry() {
+    let server = get_app().await;
+    let (game_id, _) = create_game_with_scores(&server, "HigherIsBetter", &[1.0]).await;
+
+    let status = server
+        .get(format!("/leaderboard/users/{}/games/{}/neighborhood", Uuid::new_v4(), game_id).as_str())
+        .await
+        .status_code();
+
+    assert_eq!(StatusCode::NOT_FOUND, status);
+}
@@ -0,0 +1,188 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use assert_json_diff::assert_json_include;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::types::Uuid;
+
+#[derive(Deserialize)]
+struct SeasonEntry {
+    pub score: f64,
+    pub season_id: Option<i32>,
+}
+
+async fn create_game(server: &impl MyTestServer, desc: &str) -> HasId {
+    let req = json!({ "description": desc });
+    let x = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    x
+}
+
+async fn open_season(server: &impl MyTestServer, game_id: i32, name: &str) -> HasId {
+    let req = json!({ "name": name });
+    let x = server
+        .post_json(format!("/leaderboard/games/{}/seasons", game_id).as_str(), &req)
+        .await
+        .json::<HasId>();
+    x
+}
+
+async fn close_season(server: &impl MyTestServer, game_id: i32, season_id: i32) -> serde_json::Value {
+    server
+        .post_json(format!("/leaderboard/games/{}/seasons/{}/close", game_id, season_id).as_str(), &json!({}))
+        .await
+        .json::<serde_json::Value>()
+}
+
+async fn submit(server: &impl MyTestServer, game_id: i32, score: f64, user_id: Uuid) -> SeasonEntry {
+    let req = json!({ "score": score, "user_name": "seasoned", "user_id": user_id });
+    let x = server
+        .post_json(format!("/leaderboard/games/{}/entries", game_id).as_str(), &req)
+        .await
+        .json::<SeasonEntry>();
+    x
+}
+
+async fn get_entries(server: &impl MyTestServer, game_id: i32, query: serde_json::Value) -> Vec<SeasonEntry> {
+    server
+        .get_query(format!("/leaderboard/games/{}/entries", game_id).as_str(), &query)
+        .await
+        .json::<EntriesPage<SeasonEntry>>()
+        .entries
+}
+
+#[tokio::test]
+async fn entries_are_submitted_to_the_open_season() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 5sn1a0").await;
+    let season = open_season(&server, game.id, "Season 1").await;
+
+    let entry = submit(&server, game.id, 10.0, Uuid::new_v4()).await;
+
+    assert_eq!(Some(season.id), entry.season_id);
+}
+
+#[tokio::test]
+async fn closing_archives_standings_and_empties_live_board() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 8sn1b2").await;
+    let season = open_season(&server, game.id, "Season 1").await;
+    submit(&server, game.id, 10.0, Uuid::new_v4()).await;
+    submit(&server, game.id, 30.0, Uuid::new_v4()).await;
+
+    let closed = close_season(&server, game.id, season.id).await;
+    assert!(closed["ended_at"].is_string());
+
+    assert!(get_entries(&server, game.id, json!({})).await.is_empty());
+    let archived = get_entries(&server, game.id, json!({ "season": season.id })).await;
+    let archived_scores = archived.iter().map(|x| x.score).collect::<Vec<_>>();
+    assert_eq!(vec![30.0, 10.0], archived_scores);
+}
+
+#[tokio::test]
+async fn archive_is_unchanged_by_later_entries() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 1sn9c3").await;
+    let user_id = Uuid::new_v4();
+    let season = open_season(&server, game.id, "Season 1").await;
+    submit(&server, game.id, 10.0, user_id).await;
+    close_season(&server, game.id, season.id).await;
+
+    let next_season = open_season(&server, game.id, "Season 2").await;
+    let entry = submit(&server, game.id, 5.0, user_id).await;
+    assert_eq!(Some(next_season.id), entry.season_id);
+
+    let archived = get_entries(&server, game.id, json!({ "season": season.id })).await;
+    assert_eq!(vec![10.0], archived.iter().map(|x| x.score).collect::<Vec<_>>());
+    let live = get_entries(&server, game.id, json!({})).await;
+    assert_eq!(vec![5.0], live.iter().map(|x| x.score).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn averages_start_over_after_closing() {
+    let server = get_app().await;
+    let req = json!({
+        "description": "Test Game Description 4sn2d7",
+        "score_aggregation": "AverageOfLastN",
+        "aggregation_last_n": 3,
+    });
+    let game = server.post_json("/leaderboard/games", &req).await.json::<HasId>();
+    let user_id = Uuid::new_v4();
+    let season = open_season(&server, game.id, "Season 1").await;
+    submit(&server, game.id, 10.0, user_id).await;
+    close_season(&server, game.id, season.id).await;
+
+    let entry = submit(&server, game.id, 2.0, user_id).await;
+
+    assert_eq!(2.0, entry.score);
+    assert_eq!(None, entry.season_id);
+}
+
+#[tokio::test]
+async fn only_one_season_is_open_at_once() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 0sn7d4").await;
+    let season = open_season(&server, game.id, "Season 1").await;
+
+    let seasons_url = format!("/leaderboard/games/{}/seasons", game.id);
+    let req = json!({ "name": "Season 2" });
+    let resp = server.post_json(seasons_url.as_str(), &req).await;
+
+    assert_eq!(StatusCode::CONFLICT, resp.status_code());
+    assert_json_include!(actual: resp.json_allow_fail::<serde_json::Value>(), expected: json!({ "id": season.id }));
+}
+
+#[tokio::test]
+async fn closing_twice_conflicts() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 3sn2e5").await;
+    let season = open_season(&server, game.id, "Season 1").await;
+    close_season(&server, game.id, season.id).await;
+
+    let status = server
+        .post_json(format!("/leaderboard/games/{}/seasons/{}/close", game.id, season.id).as_str(), &json!({}))
+        .await
+        .status_code();
+
+    assert_eq!(StatusCode::CONFLICT, status);
+}
+
+#[tokio::test]
+async fn seasons_belong_to_their_game() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 9sn4f6").await;
+    let other_game = create_game(&server, "Test Game Description 9sn4f7").await;
+    let season = open_season(&server, game.id, "Season 1").await;
+
+    let close_status = server
+        .post_json(format!("/leaderboard/games/{}/seasons/{}/close", other_game.id, season.id).as_str(), &json!({}))
+        .await
+        .status_code();
+    assert_eq!(StatusCode::NOT_FOUND, close_status);
+
+    let entries_status = server
+        .get_query(format!("/leaderboard/games/{}/entries", other_game.id).as_str(), &json!({ "season": season.id }))
+        .await
+        .status_code();
+    assert_eq!(StatusCode::NOT_FOUND, entries_status);
+}
+
+#[tokio::test]
+async fn lists_seasons_newest_first() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 2sn8g7").await;
+    let first = open_season(&server, game.id, "Season 1").await;
+    close_season(&server, game.id, first.id).await;
+    let second = open_season(&server, game.id, "Season 2").await;
+
+    let seasons = server
+        .get(format!("/leaderboard/games/{}/seasons", game.id).as_str())
+        .await
+        .json::<Vec<HasId>>();
+
+    assert_eq!(vec![second.id, first.id], seasons.iter().map(|x| x.id).collect::<Vec<_>>());
+}
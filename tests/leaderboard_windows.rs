@@ -0,0 +1,181 @@
+mod common;
+use common::my_test_server::*;
+#[cfg(not(feature = "sqlite"))]
+use common::postgres::get_shared_pool;
+#[cfg(feature = "sqlite")]
+use common::sqlite::get_shared_pool;
+use common::test_models::*;
+use assert_json_diff::assert_json_include;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::types::Uuid;
+
+#[derive(Deserialize)]
+struct HasScore {
+    pub score: f64,
+}
+
+async fn create_game(server: &impl MyTestServer, desc: &str) -> HasId {
+    let req = json!({ "description": desc });
+    let x = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    x
+}
+
+async fn get_window_scores(server: &impl MyTestServer, game_id: i32, window: &str) -> Vec<f64> {
+    server
+        .get_query(format!("/leaderboard/games/{}/entries", game_id).as_str(), &json!({ "window": window }))
+        .await
+        .json::<EntriesPage<HasScore>>()
+        .entries
+        .iter()
+        .map(|x| x.score)
+        .collect()
+}
+
+/// Pretends the user's timed entries were submitted during the previous windows
+#[cfg(not(feature = "sqlite"))]
+async fn age_window_entries(user_id: Uuid) {
+    sqlx::query(
+        "UPDATE leaderboard_window_entries \
+        SET window_start = window_start - INTERVAL '40 days' \
+        WHERE user_id = $1;")
+        .bind(user_id)
+        .execute(&get_shared_pool().await)
+        .await
+        .unwrap();
+}
+
+/// Pretends the user's timed entries were submitted during the previous windows
+#[cfg(feature = "sqlite")]
+async fn age_window_entries(user_id: Uuid) {
+    sqlx::query(
+        "UPDATE leaderboard_window_entries \
+        SET window_start = strftime('%Y-%m-%dT%H:%M:%S+00:00', window_start, '-40 days') \
+        WHERE user_id = $1;")
+        .bind(user_id)
+        .execute(&get_shared_pool().await)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn new_entry_is_on_every_window() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 6t1kk0").await;
+
+    let req = json!({ "score": 12.0, "user_name": "windowed" });
+    server
+        .post_json(format!("/leaderboard/games/{}/entries", game.id).as_str(), &req)
+        .await
+        .json::<HasId>();
+
+    for window in ["daily", "weekly", "monthly", "all_time"] {
+        assert_eq!(vec![12.0], get_window_scores(&server, game.id, window).await, "{window}");
+    }
+}
+
+#[tokio::test]
+async fn entry_records_timestamps() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 8ja9d0").await;
+
+    let req = json!({ "score": 12.0, "user_name": "windowed" });
+    let entry = server
+        .post_json(format!("/leaderboard/games/{}/entries", game.id).as_str(), &req)
+        .await
+        .json::<serde_json::Value>();
+
+    assert!(entry["created_at"].is_string());
+    assert!(entry["updated_at"].is_string());
+}
+
+#[tokio::test]
+async fn worse_score_is_accepted_on_a_fresh_window() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 0kd8a1").await;
+    let game_entry_url = format!("/leaderboard/games/{}/entries", game.id);
+    let user_id = Uuid::new_v4();
+
+    let req = json!({ "score": 100.0, "user_name": "windowed", "user_id": user_id });
+    server.post_json(game_entry_url.as_str(), &req).await.json::<HasId>();
+    age_window_entries(user_id).await;
+
+    let req = json!({ "score": 50.0, "user_name": "windowed", "user_id": user_id });
+    let resp = server.post_json(game_entry_url.as_str(), &req).await;
+
+    assert_eq!(StatusCode::OK, resp.status_code());
+    assert_json_include!(actual: resp.json::<serde_json::Value>(), expected: json!({ "score": 100.0 }));
+    assert_eq!(vec![50.0], get_window_scores(&server, game.id, "daily").await);
+    assert_eq!(vec![100.0], get_window_scores(&server, game.id, "all_time").await);
+}
+
+#[tokio::test]
+async fn worse_score_conflicts_within_the_same_window() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 91kdja").await;
+    let game_entry_url = format!("/leaderboard/games/{}/entries", game.id);
+    let user_id = Uuid::new_v4();
+
+    let req = json!({ "score": 100.0, "user_name": "windowed", "user_id": user_id });
+    server.post_json(game_entry_url.as_str(), &req).await.json::<HasId>();
+    let req = json!({ "score": 50.0, "user_name": "windowed", "user_id": user_id });
+    let status = server.post_json(game_entry_url.as_str(), &req).await.status_code();
+
+    assert_eq!(StatusCode::CONFLICT, status);
+    assert_eq!(vec![100.0], get_window_scores(&server, game.id, "daily").await);
+}
+
+#[tokio::test]
+async fn aged_entries_leave_the_window() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 1a9dj2").await;
+    let user_id = Uuid::new_v4();
+
+    let req = json!({ "score": 100.0, "user_name": "windowed", "user_id": user_id });
+    server
+        .post_json(format!("/leaderboard/games/{}/entries", game.id).as_str(), &req)
+        .await
+        .json::<HasId>();
+    age_window_entries(user_id).await;
+
+    assert!(get_window_scores(&server, game.id, "daily").await.is_empty());
+    assert!(get_window_scores(&server, game.id, "monthly").await.is_empty());
+    assert_eq!(vec![100.0], get_window_scores(&server, game.id, "all_time").await);
+}
+
+#[tokio::test]
+async fn creates_game_with_window_settings() {
+    let server = get_app().await;
+
+    let req = json!({
+        "description": "Test Game Description 7bn1ka",
+        "window_timezone": "America/New_York",
+        "window_reset_hour": 4,
+    });
+    let game = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<serde_json::Value>();
+
+    assert_json_include!(actual: game, expected: json!({
+        "window_timezone": "America/New_York",
+        "window_reset_hour": 4,
+    }));
+}
+
+#[tokio::test]
+async fn rejects_invalid_window_settings() {
+    let server = get_app().await;
+
+    let req = json!({ "description": "Test Game Description 7bn1ka", "window_timezone": "Mars/Olympus" });
+    let status = server.post_json("/leaderboard/games", &req).await.status_code();
+    assert_eq!(StatusCode::BAD_REQUEST, status);
+
+    let req = json!({ "description": "Test Game Description 7bn1ka", "window_reset_hour": 24 });
+    let status = server.post_json("/leaderboard/games", &req).await.status_code();
+    assert_eq!(StatusCode::BAD_REQUEST, status);
+}
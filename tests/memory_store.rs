@@ -0,0 +1,67 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use sqlx::types::Uuid;
+
+#[tokio::test]
+async fn games_and_entries_work_in_memory() {
+    let server = get_memory_app().await;
+    let req = json!({ "description": "Test Game Description m3m0ry", "score_components": [{ "name": "time", "sort_mode": "LesserIsBetter" }] });
+    let game = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    let entries_path = format!("/leaderboard/games/{}/entries", game.id);
+
+    let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+    for (user_id, score, time) in [(first, 10.0, 30.0), (second, 10.0, 20.0)] {
+        let req = json!({ "score": score, "score_components": [time], "user_name": "memory", "user_id": user_id });
+        server
+            .post_json(&entries_path, &req)
+            .await
+            .json::<Value>();
+    }
+    let req = json!({ "score": 5.0, "score_components": [1.0], "user_name": "memory", "user_id": first });
+    let conflict = server
+        .post_json(&entries_path, &req)
+        .await;
+    assert_eq!(StatusCode::CONFLICT, conflict.status_code());
+
+    let page = server
+        .get(&entries_path)
+        .await
+        .json::<Value>();
+    let users = page["entries"].as_array().unwrap().iter().map(|entry| entry["user_id"].clone()).collect::<Vec<_>>();
+    assert_eq!(vec![json!(second), json!(first)], users);
+
+    let standing = server
+        .get(&format!("/leaderboard/users/{first}/games/{}/entries", game.id))
+        .await
+        .json::<Value>();
+    assert_eq!((json!(2), json!(2)), (standing["rank"].clone(), standing["total"].clone()));
+
+    let history = server
+        .get(&format!("/leaderboard/users/{first}/games/{}/history", game.id))
+        .await
+        .json::<Value>();
+    assert_eq!((json!(2), json!(1)), (history["stats"]["count"].clone(), history["stats"]["accepted_count"].clone()));
+}
+
+#[tokio::test]
+async fn memory_stores_are_separate() {
+    let server = get_memory_app().await;
+    let req = json!({ "description": "Test Game Description m3m0r2" });
+    server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+
+    let games = get_memory_app().await
+        .get("/leaderboard/games")
+        .await
+        .json::<Value>();
+
+    assert_eq!(json!([]), games);
+}
@@ -0,0 +1,56 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::types::Uuid;
+
+async fn scrape(server: &impl MyTestServer) -> String {
+    let response = server.get("/metrics").await;
+    assert_eq!(StatusCode::OK, response.status_code());
+    response.text()
+}
+
+#[tokio::test]
+async fn counts_submissions_per_outcome() {
+    let server = get_app().await;
+    let req = json!({ "description": "Test Game Description m3tr1c5", "max_score": 100.0 });
+    let game = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    let path = format!("/leaderboard/games/{}/entries", game.id);
+    let user_id = Uuid::new_v4();
+
+    for (score, status) in [(50.0, StatusCode::OK), (40.0, StatusCode::CONFLICT), (60.0, StatusCode::OK), (101.0, StatusCode::UNPROCESSABLE_ENTITY)] {
+        let req = json!({ "score": score, "user_name": "measured", "user_id": user_id });
+        assert_eq!(status, server.post_json(path.as_str(), &req).await.status_code());
+    }
+
+    let metrics = scrape(&server).await;
+    assert!(metrics.contains(r#"leaderboard_submissions_total{outcome="accepted"} 2"#), "{metrics}");
+    assert!(metrics.contains(r#"leaderboard_submissions_total{outcome="not_better"} 1"#), "{metrics}");
+    assert!(metrics.contains(r#"leaderboard_submissions_total{outcome="rejected"} 1"#), "{metrics}");
+}
+
+#[tokio::test]
+async fn times_requests_per_route_and_store_operations() {
+    let server = get_app().await;
+    server.get("/leaderboard/games/-1").await;
+    server.get("/leaderboard/games/-2").await;
+
+    let metrics = scrape(&server).await;
+    assert!(
+        metrics.contains(r#"http_request_duration_seconds_count{method="GET",route="/leaderboard/games/:game_id",status="404"} 2"#),
+        "{metrics}",
+    );
+    assert!(metrics.contains(r#"store_operation_duration_seconds_count{operation="get_game"} 2"#), "{metrics}");
+}
+
+#[tokio::test]
+async fn reports_stream_subscribers() {
+    let server = get_app().await;
+
+    let metrics = scrape(&server).await;
+    assert!(metrics.contains(r#"stream_subscribers{stream="leaderboard"} 0"#), "{metrics}");
+}
@@ -0,0 +1,185 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::types::Uuid;
+
+#[derive(Deserialize)]
+struct HasUserId {
+    pub user_id: Uuid,
+}
+
+async fn create_game(server: &impl MyTestServer) -> HasId {
+    let req = json!({ "description": "Test Game Description m0d001" });
+    let x = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    x
+}
+
+async fn submit(server: &impl MyTestServer, game_id: i32, user_id: Uuid, score: f64) -> StatusCode {
+    let req = json!({ "score": score, "user_name": "suspect", "user_id": user_id });
+    let path = format!("/leaderboard/games/{}/entries", game_id);
+    let x = server
+        .post_json(path.as_str(), &req)
+        .await
+        .status_code();
+    x
+}
+
+/// User ids on the all time and daily leaderboards of the game
+async fn board_users(server: &impl MyTestServer, game_id: i32) -> (Vec<Uuid>, Vec<Uuid>) {
+    let path = format!("/leaderboard/games/{}/entries", game_id);
+    let all_time = server
+        .get(path.as_str())
+        .await
+        .json::<EntriesPage<HasUserId>>();
+    let daily = server
+        .get_query(path.as_str(), &json!({ "window": "daily" }))
+        .await
+        .json::<EntriesPage<HasUserId>>();
+    (
+        all_time.entries.iter().map(|x| x.user_id).collect(),
+        daily.entries.iter().map(|x| x.user_id).collect(),
+    )
+}
+
+async fn get_entry_id(server: &impl MyTestServer, game_id: i32, user_id: Uuid) -> i32 {
+    server
+        .get(format!("/leaderboard/users/{}/games/{}/entries", user_id, game_id).as_str())
+        .await
+        .json::<HasId>()
+        .id
+}
+
+#[tokio::test]
+async fn invalidated_entries_are_hidden_until_restored() {
+    let server = get_app().await;
+    let game = create_game(&server).await;
+    let (cheater, player) = (Uuid::new_v4(), Uuid::new_v4());
+    submit(&server, game.id, cheater, 1e9).await;
+    submit(&server, game.id, player, 10.0).await;
+    let entry_id = get_entry_id(&server, game.id, cheater).await;
+
+    let req = json!({ "reason": "impossible score" });
+    let invalidated = server
+        .post_json(format!("/leaderboard/admin/entries/{}/invalidate", entry_id).as_str(), &req)
+        .await
+        .json::<serde_json::Value>();
+    assert_eq!(json!("impossible score"), invalidated["invalidation_reason"]);
+
+    assert_eq!((vec![player], vec![player]), board_users(&server, game.id).await);
+    let status = server
+        .get(format!("/leaderboard/users/{}/games/{}/entries", cheater, game.id).as_str())
+        .await
+        .status_code();
+    assert_eq!(StatusCode::NOT_FOUND, status);
+    assert_eq!(StatusCode::FORBIDDEN, submit(&server, game.id, cheater, 5.0).await);
+
+    let req = json!({});
+    let status = server
+        .post_json(format!("/leaderboard/admin/entries/{}/invalidate", entry_id).as_str(), &req)
+        .await
+        .status_code();
+    assert_eq!(StatusCode::CONFLICT, status);
+
+    server
+        .post_json(format!("/leaderboard/admin/entries/{}/restore", entry_id).as_str(), &req)
+        .await
+        .json::<HasId>();
+    assert_eq!((vec![cheater, player], vec![cheater, player]), board_users(&server, game.id).await);
+}
+
+#[tokio::test]
+async fn deleted_entries_let_the_user_start_over() {
+    let server = get_app().await;
+    let game = create_game(&server).await;
+    let user_id = Uuid::new_v4();
+    submit(&server, game.id, user_id, 1e9).await;
+    let entry_id = get_entry_id(&server, game.id, user_id).await;
+
+    let deleted = server
+        .delete(format!("/leaderboard/admin/entries/{}", entry_id).as_str())
+        .await
+        .json::<HasId>();
+    assert_eq!(entry_id, deleted.id);
+    assert_eq!((vec![], vec![]), board_users(&server, game.id).await);
+
+    assert_eq!(StatusCode::OK, submit(&server, game.id, user_id, 5.0).await);
+
+    let status = server
+        .delete(format!("/leaderboard/admin/entries/{}", entry_id).as_str())
+        .await
+        .status_code();
+    assert_eq!(StatusCode::NOT_FOUND, status);
+}
+
+#[tokio::test]
+async fn game_bans_hide_entries_and_block_submissions() {
+    let server = get_app().await;
+    let (game, other_game) = (create_game(&server).await, create_game(&server).await);
+    let user_id = Uuid::new_v4();
+    submit(&server, game.id, user_id, 10.0).await;
+
+    let req = json!({ "user_id": user_id, "game_id": game.id, "reason": "cheating" });
+    let ban = server
+        .post_json("/leaderboard/admin/bans", &req)
+        .await
+        .json::<HasId>();
+    let status = server
+        .post_json("/leaderboard/admin/bans", &req)
+        .await
+        .status_code();
+    assert_eq!(StatusCode::CONFLICT, status);
+
+    assert_eq!((vec![], vec![]), board_users(&server, game.id).await);
+    assert_eq!(StatusCode::FORBIDDEN, submit(&server, game.id, user_id, 20.0).await);
+    assert_eq!(StatusCode::OK, submit(&server, other_game.id, user_id, 20.0).await);
+
+    server
+        .delete(format!("/leaderboard/admin/bans/{}", ban.id).as_str())
+        .await
+        .json::<HasId>();
+    assert_eq!((vec![user_id], vec![user_id]), board_users(&server, game.id).await);
+    assert_eq!(StatusCode::OK, submit(&server, game.id, user_id, 20.0).await);
+}
+
+#[tokio::test]
+async fn global_bans_apply_to_every_game() {
+    let server = get_app().await;
+    let (game, other_game) = (create_game(&server).await, create_game(&server).await);
+    let user_id = Uuid::new_v4();
+    submit(&server, game.id, user_id, 10.0).await;
+
+    let req = json!({ "user_id": user_id });
+    server
+        .post_json("/leaderboard/admin/bans", &req)
+        .await
+        .json::<HasId>();
+
+    assert_eq!((vec![], vec![]), board_users(&server, game.id).await);
+    assert_eq!(StatusCode::FORBIDDEN, submit(&server, other_game.id, user_id, 20.0).await);
+
+    let bans = server
+        .get_query("/leaderboard/admin/bans", &json!({ "user_id": user_id }))
+        .await
+        .json::<Vec<serde_json::Value>>();
+    assert_eq!(1, bans.len());
+    assert_eq!(serde_json::Value::Null, bans[0]["game_id"]);
+}
+
+#[tokio::test]
+async fn banning_from_missing_game_is_not_found() {
+    let server = get_app().await;
+
+    let req = json!({ "user_id": Uuid::new_v4(), "game_id": -1 });
+    let status = server
+        .post_json("/leaderboard/admin/bans", &req)
+        .await
+        .status_code();
+
+    assert_eq!(StatusCode::NOT_FOUND, status);
+}
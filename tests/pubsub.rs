@@ -0,0 +1,40 @@
+//! Only Postgres fans updates out, SQLite deployments run a single instance
+#![cfg(not(feature = "sqlite"))]
+
+mod common;
+use common::postgres::get_shared_pool;
+use fraculation_leaderboard::pubsub;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Only listened on by this test, so dropping its connection leaves the other tests alone
+const CHANNEL: &str = "pubsub_test_reconnects";
+
+#[tokio::test]
+async fn listeners_reconnect_and_report_missed_updates() {
+    let db = get_shared_pool().await;
+    let (updates_tx, mut updates) = mpsc::unbounded_channel();
+    let (missed_tx, mut missed) = mpsc::unbounded_channel();
+    pubsub::listen(&db, CHANNEL, move |update: i32| {
+        let _ = updates_tx.send(update);
+    }, move || {
+        let _ = missed_tx.send(());
+    })
+        .await
+        .unwrap();
+
+    sqlx::query("SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE query LIKE $1 AND pid <> pg_backend_pid();")
+        .bind(format!("%{CHANNEL}%"))
+        .execute(&db)
+        .await
+        .unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), missed.recv())
+        .await
+        .expect("the listener to reconnect within 5 seconds");
+    pubsub::publish(&db, CHANNEL, &7).await.unwrap();
+    let update = tokio::time::timeout(Duration::from_secs(5), updates.recv())
+        .await
+        .expect("an update within 5 seconds");
+    assert_eq!(Some(7), update);
+}
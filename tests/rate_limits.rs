@@ -0,0 +1,74 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::StatusCode;
+use fraculation_leaderboard::leaderboard::rate_limit::{Budget, RateLimits, RouteBudgets};
+use serde_json::json;
+use sqlx::types::Uuid;
+
+#[tokio::test]
+async fn throttles_game_creations_per_api_key() {
+    let server = get_memory_app_with_limits(RateLimits {
+        create_game: RouteBudgets { per_api_key: Some(Budget { burst: 2, per_minute: 1 }), ..RouteBudgets::UNLIMITED },
+        ..RateLimits::UNLIMITED
+    }).await;
+    let req = json!({ "description": "Test Game Description r4t3l1m" });
+
+    for _ in 0..2 {
+        assert_eq!(StatusCode::OK, server.post_json("/leaderboard/games", &req).await.status_code());
+    }
+    let response = server.post_json("/leaderboard/games", &req).await;
+
+    assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status_code());
+    assert_eq!("60", response.header("retry-after"));
+    assert_eq!("2", response.header("ratelimit-limit"));
+    assert_eq!("0", response.header("ratelimit-remaining"));
+    assert_eq!("60", response.header("ratelimit-reset"));
+    assert_eq!(json!("rate_limited"), response.json_allow_fail::<serde_json::Value>()["code"]);
+}
+
+#[tokio::test]
+async fn throttles_submissions_per_user() {
+    let server = get_memory_app_with_limits(RateLimits {
+        create_game_entry: RouteBudgets { per_user: Some(Budget { burst: 1, per_minute: 1 }), ..RouteBudgets::UNLIMITED },
+        ..RateLimits::UNLIMITED
+    }).await;
+    let req = json!({ "description": "Test Game Description r4t3l2m" });
+    let game = server.post_json("/leaderboard/games", &req).await.json::<HasId>();
+    let path = format!("/leaderboard/games/{}/entries", game.id);
+    let user_id = Uuid::new_v4();
+
+    let req = json!({ "score": 1.0, "user_name": "eager", "user_id": user_id });
+    assert_eq!(StatusCode::OK, server.post_json(path.as_str(), &req).await.status_code());
+    let req = json!({ "score": 2.0, "user_name": "eager", "user_id": user_id });
+    assert_eq!(StatusCode::TOO_MANY_REQUESTS, server.post_json(path.as_str(), &req).await.status_code());
+
+    let req = json!({ "score": 2.0, "user_name": "patient", "user_id": Uuid::new_v4() });
+    assert_eq!(StatusCode::OK, server.post_json(path.as_str(), &req).await.status_code());
+}
+
+#[tokio::test]
+async fn throttles_submissions_per_forwarded_ip() {
+    let server = get_memory_app_with_limits(RateLimits {
+        create_game_entry: RouteBudgets { per_ip: Some(Budget { burst: 1, per_minute: 1 }), ..RouteBudgets::UNLIMITED },
+        trust_forwarded_for: true,
+        ..RateLimits::UNLIMITED
+    }).await;
+    let req = json!({ "description": "Test Game Description r4t3l3m" });
+    let game = server.post_json("/leaderboard/games", &req).await.json::<HasId>();
+    let path = format!("/leaderboard/games/{}/entries", game.id);
+
+    assert_eq!(StatusCode::OK, submit_from(&server, &path, "203.0.113.7").await);
+    // Only the address the proxy saw counts, not those the client claims
+    assert_eq!(StatusCode::TOO_MANY_REQUESTS, submit_from(&server, &path, "198.51.100.1, 203.0.113.7").await);
+    assert_eq!(StatusCode::OK, submit_from(&server, &path, "203.0.113.8").await);
+}
+
+async fn submit_from(server: &impl MyTestServer, path: &str, forwarded_for: &str) -> StatusCode {
+    let req = json!({ "score": 1.0, "user_name": "forwarded" });
+    let x = server
+        .post_json_with_headers(path, &req, &[("x-forwarded-for", forwarded_for.into())])
+        .await
+        .status_code();
+    x
+}
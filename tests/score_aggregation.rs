@@ -0,0 +1,153 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use assert_json_diff::assert_json_include;
+use axum::body::Body;
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{Request, StatusCode};
+use fraculation_leaderboard::leaderboard::models::ApiKeyRole;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::types::Uuid;
+use tower::ServiceExt;
+
+#[derive(Deserialize)]
+struct HasScore {
+    pub score: f64,
+}
+
+async fn create_game(server: &impl MyTestServer, aggregation: &str, last_n: Option<i32>) -> HasId {
+    let req = json!({
+        "description": "Test Game Description 3ag0k1",
+        "score_aggregation": aggregation,
+        "aggregation_last_n": last_n,
+    });
+    let x = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    x
+}
+
+/// Submits every score for one user, returning the stored score after each submission
+async fn submit_all(server: &impl MyTestServer, game_id: i32, scores: &[f64]) -> Vec<f64> {
+    let user_id = Uuid::new_v4();
+    let mut stored = vec![];
+    for score in scores {
+        let req = json!({ "score": score, "user_name": "aggregator", "user_id": user_id });
+        let entry = server
+            .post_json(format!("/leaderboard/games/{}/entries", game_id).as_str(), &req)
+            .await
+            .json::<HasScore>();
+        stored.push(entry.score);
+    }
+    stored
+}
+
+#[tokio::test]
+async fn defaults_to_best() {
+    let server = get_app().await;
+
+    let req = json!({ "description": "Test Game Description 3ag0k2" });
+    let game = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<serde_json::Value>();
+
+    assert_json_include!(actual: game, expected: json!({ "score_aggregation": "Best", "aggregation_last_n": 5 }));
+}
+
+#[tokio::test]
+async fn latest_replaces_worse_scores() {
+    let server = get_app().await;
+    let game = create_game(&server, "Latest", None).await;
+
+    assert_eq!(vec![10.0, 3.0, 7.0], submit_all(&server, game.id, &[10.0, 3.0, 7.0]).await);
+}
+
+#[tokio::test]
+async fn sum_collects_coins() {
+    let server = get_app().await;
+    let game = create_game(&server, "Sum", None).await;
+
+    assert_eq!(vec![10.0, 13.0, 20.0], submit_all(&server, game.id, &[10.0, 3.0, 7.0]).await);
+}
+
+#[tokio::test]
+async fn average_of_last_n_forgets_old_scores() {
+    let server = get_app().await;
+    let game = create_game(&server, "AverageOfLastN", Some(2)).await;
+
+    assert_eq!(vec![10.0, 6.0, 5.0], submit_all(&server, game.id, &[10.0, 2.0, 8.0]).await);
+}
+
+#[tokio::test]
+async fn count_counts_submissions() {
+    let server = get_app().await;
+    let game = create_game(&server, "Count", None).await;
+
+    assert_eq!(vec![1.0, 2.0, 3.0], submit_all(&server, game.id, &[10.0, 3.0, 7.0]).await);
+}
+
+#[tokio::test]
+async fn sum_applies_to_every_window() {
+    let server = get_app().await;
+    let game = create_game(&server, "Sum", None).await;
+    submit_all(&server, game.id, &[10.0, 5.0]).await;
+
+    let daily = server
+        .get_query(format!("/leaderboard/games/{}/entries", game.id).as_str(), &json!({ "window": "daily" }))
+        .await
+        .json::<EntriesPage<HasScore>>();
+
+    assert_eq!(vec![15.0], daily.entries.iter().map(|x| x.score).collect::<Vec<_>>());
+}
+
+/// Submits `score` for one user, `times` at once, returning the stored all time and daily scores
+async fn submit_concurrently(game_id: i32, score: f64, times: usize) -> (Vec<f64>, Vec<f64>) {
+    let (server, router) = get_app_and_router().await;
+    let admin_key = mint_key(ApiKeyRole::Admin, None).await;
+    let req = json!({ "score": score, "user_name": "concurrent", "user_id": Uuid::new_v4() });
+    let path = format!("/leaderboard/games/{}/entries", game_id);
+    let submissions = (0..times).map(|_| {
+        let request = Request::post(path.as_str())
+            .header(AUTHORIZATION, format!("Bearer {admin_key}"))
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(req.to_string()))
+            .unwrap();
+        router.clone().oneshot(request)
+    });
+    for response in futures_util::future::join_all(submissions).await {
+        assert_eq!(StatusCode::OK, response.unwrap().status());
+    }
+
+    let mut scores = vec![];
+    for window in ["all_time", "daily"] {
+        let page = server
+            .get_query(path.as_str(), &json!({ "window": window }))
+            .await
+            .json::<EntriesPage<HasScore>>();
+        scores.push(page.entries.iter().map(|x| x.score).collect::<Vec<_>>());
+    }
+    (scores.remove(0), scores.remove(0))
+}
+
+#[tokio::test]
+async fn concurrent_submissions_all_add_up() {
+    let server = get_app().await;
+    let sum_game = create_game(&server, "Sum", None).await;
+    let count_game = create_game(&server, "Count", None).await;
+
+    assert_eq!((vec![40.0], vec![40.0]), submit_concurrently(sum_game.id, 2.0, 20).await);
+    assert_eq!((vec![20.0], vec![20.0]), submit_concurrently(count_game.id, 2.0, 20).await);
+}
+
+#[tokio::test]
+async fn rejects_averaging_nothing() {
+    let server = get_app().await;
+
+    let req = json!({ "description": "Test Game Description 3ag0k3", "aggregation_last_n": 0 });
+    let status = server.post_json("/leaderboard/games", &req).await.status_code();
+
+    assert_eq!(StatusCode::BAD_REQUEST, status);
+}
@@ -0,0 +1,128 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::types::Uuid;
+
+#[derive(Deserialize)]
+struct ComponentEntry {
+    pub score: f64,
+    pub score_components: Vec<f64>,
+    pub user_id: Uuid,
+}
+
+#[derive(Deserialize)]
+struct Standing {
+    pub rank: i64,
+}
+
+/// Creates a game ranked by points, then by the lowest time
+async fn create_game(server: &impl MyTestServer) -> HasId {
+    let req = json!({
+        "description": "Test Game Description c0mp01",
+        "score_components": [{ "name": "time", "sort_mode": "LesserIsBetter" }],
+    });
+    let x = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    x
+}
+
+/// Submits a score and its time, returning the status and the stored (or kept) entry
+async fn submit(server: &impl MyTestServer, game_id: i32, user_id: Uuid, score: f64, time: f64) -> (StatusCode, ComponentEntry) {
+    let req = json!({ "score": score, "score_components": [time], "user_name": "racer", "user_id": user_id });
+    let path = format!("/leaderboard/games/{}/entries", game_id);
+    let res = server
+        .post_json(path.as_str(), &req)
+        .await;
+    (res.status_code(), res.json_allow_fail::<ComponentEntry>())
+}
+
+#[tokio::test]
+async fn ties_are_ranked_by_components() {
+    let server = get_app().await;
+    let game = create_game(&server).await;
+    let users = [Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()];
+    submit(&server, game.id, users[0], 10.0, 30.0).await;
+    submit(&server, game.id, users[1], 10.0, 20.0).await;
+    submit(&server, game.id, users[2], 5.0, 1.0).await;
+
+    let page = server
+        .get(format!("/leaderboard/games/{}/entries", game.id).as_str())
+        .await
+        .json::<EntriesPage<ComponentEntry>>();
+
+    let order = page.entries.iter().map(|x| (x.user_id, x.score, x.score_components.clone())).collect::<Vec<_>>();
+    assert_eq!(
+        vec![(users[1], 10.0, vec![20.0]), (users[0], 10.0, vec![30.0]), (users[2], 5.0, vec![1.0])],
+        order,
+    );
+
+    let standing = server
+        .get(format!("/leaderboard/users/{}/games/{}/entries", users[0], game.id).as_str())
+        .await
+        .json::<Standing>();
+    assert_eq!(2, standing.rank);
+}
+
+#[tokio::test]
+async fn better_component_improves_entry() {
+    let server = get_app().await;
+    let game = create_game(&server).await;
+    let user_id = Uuid::new_v4();
+    submit(&server, game.id, user_id, 10.0, 30.0).await;
+
+    let (status, improved) = submit(&server, game.id, user_id, 10.0, 20.0).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(vec![20.0], improved.score_components);
+
+    let (status, kept) = submit(&server, game.id, user_id, 10.0, 25.0).await;
+    assert_eq!(StatusCode::CONFLICT, status);
+    assert_eq!(vec![20.0], kept.score_components);
+}
+
+#[tokio::test]
+async fn rejects_wrong_component_count() {
+    let server = get_app().await;
+    let game = create_game(&server).await;
+
+    let req = json!({ "score": 10.0, "user_name": "racer" });
+    let status = server
+        .post_json(format!("/leaderboard/games/{}/entries", game.id).as_str(), &req)
+        .await
+        .status_code();
+
+    assert_eq!(StatusCode::BAD_REQUEST, status);
+}
+
+#[tokio::test]
+async fn pages_through_component_ties() {
+    let server = get_app().await;
+    let game = create_game(&server).await;
+    for time in [40.0, 10.0, 30.0, 20.0] {
+        submit(&server, game.id, Uuid::new_v4(), 10.0, time).await;
+    }
+
+    let mut times = vec![];
+    let mut cursor: Option<String> = None;
+    loop {
+        let query = match &cursor {
+            Some(cursor) => json!({ "limit": 3, "cursor": cursor }),
+            None => json!({ "limit": 3 }),
+        };
+        let page = server
+            .get_query(format!("/leaderboard/games/{}/entries", game.id).as_str(), &query)
+            .await
+            .json::<EntriesPage<ComponentEntry>>();
+        times.extend(page.entries.iter().map(|x| x.score_components[0]));
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    assert_eq!(vec![10.0, 20.0, 30.0, 40.0], times);
+}
@@ -0,0 +1,138 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::StatusCode;
+use serde_json::{json, Value};
+use sqlx::types::Uuid;
+
+async fn create_game(server: &impl MyTestServer, settings: Value) -> HasId {
+    let mut req = json!({ "description": "Test Game Description v4l1d0" });
+    req.as_object_mut().unwrap().extend(settings.as_object().unwrap().clone());
+    let x = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    x
+}
+
+async fn submit(server: &impl MyTestServer, game_id: i32, user_id: Uuid, score: f64) -> (StatusCode, Value) {
+    let req = json!({ "score": score, "user_name": "validated", "user_id": user_id });
+    let path = format!("/leaderboard/games/{}/entries", game_id);
+    let response = server.post_json(path.as_str(), &req).await;
+    (response.status_code(), response.json_allow_fail::<Value>())
+}
+
+#[tokio::test]
+async fn rejects_scores_outside_the_range() {
+    let server = get_app().await;
+    let game = create_game(&server, json!({ "min_score": 0.0, "max_score": 100.0, "integer_scores": true })).await;
+    let user_id = Uuid::new_v4();
+
+    let (status, failure) = submit(&server, game.id, user_id, 100.5).await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    assert_eq!(json!("MaxScore"), failure["rule"]);
+    assert_eq!(json!(100.0), failure["limit"]);
+
+    let (status, failure) = submit(&server, game.id, user_id, -1.0).await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    assert_eq!(json!("MinScore"), failure["rule"]);
+
+    let (status, failure) = submit(&server, game.id, user_id, 1.5).await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    assert_eq!(json!("IntegerScores"), failure["rule"]);
+
+    assert_eq!(StatusCode::OK, submit(&server, game.id, user_id, 100.0).await.0);
+}
+
+#[tokio::test]
+async fn rejects_implausible_improvements() {
+    let server = get_app().await;
+    let game = create_game(&server, json!({ "max_improvement": 10.0 })).await;
+    let user_id = Uuid::new_v4();
+
+    assert_eq!(StatusCode::OK, submit(&server, game.id, user_id, 1000.0).await.0);
+    let (status, failure) = submit(&server, game.id, user_id, 1011.0).await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    assert_eq!(json!("MaxImprovement"), failure["rule"]);
+    assert_eq!(StatusCode::OK, submit(&server, game.id, user_id, 1010.0).await.0);
+}
+
+#[tokio::test]
+async fn limits_improvements_on_the_last_score_when_aggregating() {
+    let server = get_app().await;
+    let game = create_game(&server, json!({ "max_improvement": 10.0, "score_aggregation": "Sum" })).await;
+    let user_id = Uuid::new_v4();
+
+    assert_eq!(StatusCode::OK, submit(&server, game.id, user_id, 5.0).await.0);
+    assert_eq!(StatusCode::OK, submit(&server, game.id, user_id, 15.0).await.0);
+    assert_eq!(StatusCode::OK, submit(&server, game.id, user_id, 25.0).await.0);
+    // The total is 45, so only the last score tells this jump apart
+    let (status, failure) = submit(&server, game.id, user_id, 40.0).await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    assert_eq!(json!("MaxImprovement"), failure["rule"]);
+}
+
+#[tokio::test]
+async fn rejects_too_frequent_submissions() {
+    let server = get_app().await;
+    let game = create_game(&server, json!({ "min_submission_interval_secs": 3600 })).await;
+    let (user_id, other_user_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+    assert_eq!(StatusCode::OK, submit(&server, game.id, user_id, 1.0).await.0);
+    let (status, failure) = submit(&server, game.id, user_id, 2.0).await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    assert_eq!(json!("MinSubmissionInterval"), failure["rule"]);
+    assert_eq!(StatusCode::OK, submit(&server, game.id, other_user_id, 2.0).await.0);
+}
+
+#[tokio::test]
+async fn rejected_scores_are_kept_in_the_history() {
+    let server = get_app().await;
+    let game = create_game(&server, json!({ "max_improvement": 10.0 })).await;
+    let user_id = Uuid::new_v4();
+    submit(&server, game.id, user_id, 1000.0).await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, submit(&server, game.id, user_id, 5000.0).await.0);
+
+    let history = server
+        .get(format!("/leaderboard/users/{}/games/{}/history", user_id, game.id).as_str())
+        .await
+        .json::<Value>();
+    let rejected = &history["submissions"][0];
+    assert_eq!((json!(5000.0), json!(false)), (rejected["score"].clone(), rejected["accepted"].clone()));
+    assert_eq!(json!("MaxImprovement"), rejected["rejected_by"]);
+    assert_eq!(json!(null), history["submissions"][1]["rejected_by"]);
+    assert_eq!((json!(2), json!(1)), (history["stats"]["count"].clone(), history["stats"]["accepted_count"].clone()));
+}
+
+#[tokio::test]
+async fn rejected_submissions_count_towards_the_interval() {
+    let server = get_app().await;
+    let game = create_game(&server, json!({ "min_submission_interval_secs": 3600, "max_score": 100.0 })).await;
+    let user_id = Uuid::new_v4();
+
+    assert_eq!(json!("MaxScore"), submit(&server, game.id, user_id, 101.0).await.1["rule"]);
+    let (status, failure) = submit(&server, game.id, user_id, 50.0).await;
+
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    assert_eq!(json!("MinSubmissionInterval"), failure["rule"]);
+}
+
+#[tokio::test]
+async fn rejects_inconsistent_rules() {
+    let server = get_app().await;
+
+    for settings in [
+        json!({ "min_score": 10.0, "max_score": 0.0 }),
+        json!({ "max_improvement": -1.0 }),
+        json!({ "min_submission_interval_secs": -1 }),
+    ] {
+        let mut req = json!({ "description": "Test Game Description v4l1d1" });
+        req.as_object_mut().unwrap().extend(settings.as_object().unwrap().clone());
+        let status = server
+            .post_json("/leaderboard/games", &req)
+            .await
+            .status_code();
+        assert_eq!(StatusCode::BAD_REQUEST, status);
+    }
+}
@@ -0,0 +1,137 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::StatusCode;
+use chrono::Utc;
+use fraculation_leaderboard::leaderboard::signing::{canonical_payload, sign};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::types::Uuid;
+
+#[derive(Deserialize)]
+struct SigningSecret {
+    pub signing_secret: String,
+}
+
+/// Creates a game requiring signed submissions, returning it with its signing secret
+async fn create_signed_game(server: &impl MyTestServer) -> (HasId, String) {
+    let req = json!({ "description": "Test Game Description s1gn01", "require_signed_submissions": true });
+    let game = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    let secret = server
+        .get(format!("/leaderboard/games/{}/signing_secret", game.id).as_str())
+        .await
+        .json::<SigningSecret>()
+        .signing_secret;
+    (game, secret)
+}
+
+/// Signature headers for a submission of `score` by `user_id`
+fn signature_headers(secret: &str, game_id: i32, user_id: Uuid, score: f64, timestamp: i64, nonce: &str) -> Vec<(&'static str, String)> {
+    let payload = canonical_payload(game_id, Some(user_id), score, &[], "", timestamp, nonce);
+    vec![
+        ("x-signature-timestamp", timestamp.to_string()),
+        ("x-signature-nonce", nonce.to_string()),
+        ("x-signature", sign(secret, &payload)),
+    ]
+}
+
+async fn submit(server: &impl MyTestServer, game_id: i32, user_id: Uuid, score: f64, headers: &[(&'static str, String)]) -> StatusCode {
+    let req = json!({ "score": score, "user_name": "signer", "user_id": user_id });
+    let path = format!("/leaderboard/games/{}/entries", game_id);
+    let x = server
+        .post_json_with_headers(path.as_str(), &req, headers)
+        .await
+        .status_code();
+    x
+}
+
+#[tokio::test]
+async fn accepts_valid_signatures_once() {
+    let server = get_app().await;
+    let (game, secret) = create_signed_game(&server).await;
+    let user_id = Uuid::new_v4();
+    let nonce = Uuid::new_v4().to_string();
+    let headers = signature_headers(&secret, game.id, user_id, 10.0, Utc::now().timestamp(), &nonce);
+
+    assert_eq!(StatusCode::OK, submit(&server, game.id, user_id, 10.0, &headers).await);
+    assert_eq!(StatusCode::UNAUTHORIZED, submit(&server, game.id, user_id, 10.0, &headers).await);
+}
+
+#[tokio::test]
+async fn rejected_submissions_keep_their_nonce_unused() {
+    let server = get_app().await;
+    let (game, secret) = create_signed_game(&server).await;
+    let user_id = Uuid::new_v4();
+    let headers = signature_headers(&secret, game.id, user_id, 10.0, Utc::now().timestamp(), &Uuid::new_v4().to_string());
+    let req = json!({ "user_id": user_id, "game_id": game.id, "reason": "cheating" });
+    let ban = server
+        .post_json("/leaderboard/admin/bans", &req)
+        .await
+        .json::<HasId>();
+    assert_eq!(StatusCode::FORBIDDEN, submit(&server, game.id, user_id, 10.0, &headers).await);
+
+    server
+        .delete(format!("/leaderboard/admin/bans/{}", ban.id).as_str())
+        .await
+        .json::<HasId>();
+
+    assert_eq!(StatusCode::OK, submit(&server, game.id, user_id, 10.0, &headers).await);
+}
+
+#[tokio::test]
+async fn rejects_missing_tampered_and_stale_signatures() {
+    let server = get_app().await;
+    let (game, secret) = create_signed_game(&server).await;
+    let user_id = Uuid::new_v4();
+    let now = Utc::now().timestamp();
+
+    assert_eq!(StatusCode::UNAUTHORIZED, submit(&server, game.id, user_id, 10.0, &[]).await);
+
+    let headers = signature_headers(&secret, game.id, user_id, 10.0, now, &Uuid::new_v4().to_string());
+    assert_eq!(StatusCode::UNAUTHORIZED, submit(&server, game.id, user_id, 9999.0, &headers).await);
+
+    let headers = signature_headers("not the secret", game.id, user_id, 10.0, now, &Uuid::new_v4().to_string());
+    assert_eq!(StatusCode::UNAUTHORIZED, submit(&server, game.id, user_id, 10.0, &headers).await);
+
+    let headers = signature_headers(&secret, game.id, user_id, 10.0, now - 3600, &Uuid::new_v4().to_string());
+    assert_eq!(StatusCode::UNAUTHORIZED, submit(&server, game.id, user_id, 10.0, &headers).await);
+}
+
+#[tokio::test]
+async fn rotated_secrets_replace_old_ones() {
+    let server = get_app().await;
+    let (game, old_secret) = create_signed_game(&server).await;
+    let user_id = Uuid::new_v4();
+
+    let req = json!({});
+    let new_secret = server
+        .post_json(format!("/leaderboard/games/{}/signing_secret", game.id).as_str(), &req)
+        .await
+        .json::<SigningSecret>()
+        .signing_secret;
+    assert_ne!(old_secret, new_secret);
+
+    let now = Utc::now().timestamp();
+    let headers = signature_headers(&old_secret, game.id, user_id, 10.0, now, &Uuid::new_v4().to_string());
+    assert_eq!(StatusCode::UNAUTHORIZED, submit(&server, game.id, user_id, 10.0, &headers).await);
+    let headers = signature_headers(&new_secret, game.id, user_id, 10.0, now, &Uuid::new_v4().to_string());
+    assert_eq!(StatusCode::OK, submit(&server, game.id, user_id, 10.0, &headers).await);
+}
+
+#[tokio::test]
+async fn unsigned_games_accept_unsigned_submissions() {
+    let server = get_app().await;
+    let req = json!({ "description": "Test Game Description s1gn02" });
+    let game = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<serde_json::Value>();
+
+    assert_eq!(json!(false), game["require_signed_submissions"]);
+    assert_eq!(None, game.get("signing_secret"));
+    let game_id = game["id"].as_i64().unwrap() as i32;
+    assert_eq!(StatusCode::OK, submit(&server, game_id, Uuid::new_v4(), 10.0, &[]).await);
+}
@@ -0,0 +1,121 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::StatusCode;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::types::Uuid;
+
+#[derive(Deserialize)]
+struct Submission {
+    pub score: f64,
+    pub accepted: bool,
+}
+
+#[derive(Deserialize)]
+struct Stats {
+    pub count: i64,
+    pub accepted_count: i64,
+    pub min_score: Option<f64>,
+    pub max_score: Option<f64>,
+    pub avg_score: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct History {
+    pub submissions: Vec<Submission>,
+    pub stats: Stats,
+}
+
+async fn create_game(server: &impl MyTestServer, desc: &str) -> HasId {
+    let req = json!({ "description": desc });
+    let x = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    x
+}
+
+async fn submit_all(server: &impl MyTestServer, game_id: i32, user_id: Uuid, scores: &[f64]) {
+    for score in scores {
+        let req = json!({ "score": score, "user_name": "historian", "user_id": user_id });
+        server
+            .post_json(format!("/leaderboard/games/{}/entries", game_id).as_str(), &req)
+            .await
+            .status_code();
+    }
+}
+
+async fn get_history(server: &impl MyTestServer, game_id: i32, user_id: Uuid, query: serde_json::Value) -> History {
+    server
+        .get_query(format!("/leaderboard/users/{}/games/{}/history", user_id, game_id).as_str(), &query)
+        .await
+        .json::<History>()
+}
+
+#[tokio::test]
+async fn records_rejected_submissions() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 4hs1a9").await;
+    let user_id = Uuid::new_v4();
+    submit_all(&server, game.id, user_id, &[10.0, 5.0, 20.0]).await;
+
+    let history = get_history(&server, game.id, user_id, json!({})).await;
+
+    let submissions = history.submissions.iter().map(|x| (x.score, x.accepted)).collect::<Vec<_>>();
+    assert_eq!(vec![(20.0, true), (5.0, false), (10.0, true)], submissions);
+}
+
+#[tokio::test]
+async fn summarises_every_submission() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 7hs2b8").await;
+    let user_id = Uuid::new_v4();
+    submit_all(&server, game.id, user_id, &[10.0, 5.0, 30.0]).await;
+
+    let history = get_history(&server, game.id, user_id, json!({ "limit": 1 })).await;
+
+    assert_eq!(1, history.submissions.len());
+    assert_eq!(3, history.stats.count);
+    assert_eq!(2, history.stats.accepted_count);
+    assert_eq!(Some(5.0), history.stats.min_score);
+    assert_eq!(Some(30.0), history.stats.max_score);
+    assert_eq!(Some(15.0), history.stats.avg_score);
+}
+
+#[tokio::test]
+async fn pages_through_history() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 2hs3c7").await;
+    let user_id = Uuid::new_v4();
+    submit_all(&server, game.id, user_id, &[1.0, 2.0, 3.0, 4.0]).await;
+
+    let history = get_history(&server, game.id, user_id, json!({ "limit": 2, "offset": 1 })).await;
+
+    let scores = history.submissions.iter().map(|x| x.score).collect::<Vec<_>>();
+    assert_eq!(vec![3.0, 2.0], scores);
+}
+
+#[tokio::test]
+async fn empty_history_has_no_stats() {
+    let server = get_app().await;
+    let game = create_game(&server, "Test Game Description 0hs4d6").await;
+
+    let history = get_history(&server, game.id, Uuid::new_v4(), json!({})).await;
+
+    assert!(history.submissions.is_empty());
+    assert_eq!(0, history.stats.count);
+    assert_eq!(None, history.stats.avg_score);
+}
+
+#[tokio::test]
+async fn history_of_missing_game_is_not_found() {
+    let server = get_app().await;
+
+    let status = server
+        .get(format!("/leaderboard/users/{}/games/{}/history", Uuid::new_v4(), i32::MAX).as_str())
+        .await
+        .status_code();
+
+    assert_eq!(StatusCode::NOT_FOUND, status);
+}
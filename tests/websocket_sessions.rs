@@ -0,0 +1,118 @@
+mod common;
+use common::my_test_server::*;
+use common::test_models::*;
+use axum::http::header::AUTHORIZATION;
+use axum::Router;
+use fraculation_leaderboard::leaderboard::models::ApiKeyRole;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use sqlx::types::Uuid;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+struct Session(WebSocketStream<MaybeTlsStream<TcpStream>>);
+
+impl Session {
+    async fn connect(api_key: Option<&str>) -> Session {
+        Self::serve(get_router().await, api_key).await
+    }
+
+    /// Serves the app on a free port and connects to its WebSocket
+    async fn serve(router: Router, api_key: Option<&str>) -> Session {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let mut request = format!("ws://{address}/leaderboard/ws").into_client_request().unwrap();
+        if let Some(api_key) = api_key {
+            request.headers_mut().insert(AUTHORIZATION, format!("Bearer {api_key}").parse().unwrap());
+        }
+        let (socket, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+        Session(socket)
+    }
+
+    async fn send(&mut self, message: Value) {
+        self.0.send(Message::Text(message.to_string())).await.unwrap();
+    }
+
+    /// Next message of the given type, skipping others
+    async fn next_of(&mut self, message_type: &str) -> Value {
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), self.0.next())
+                .await
+                .expect("a message within 5 seconds")
+                .unwrap()
+                .unwrap();
+            if let Message::Text(text) = message {
+                let message = serde_json::from_str::<Value>(&text).unwrap();
+                if message["type"] == message_type {
+                    return message;
+                }
+            }
+        }
+    }
+}
+
+async fn create_game(server: &impl MyTestServer) -> i32 {
+    let req = json!({ "description": "Test Game Description w3bs0k" });
+    let x = server
+        .post_json("/leaderboard/games", &req)
+        .await
+        .json::<HasId>();
+    x.id
+}
+
+#[tokio::test]
+async fn submissions_are_acknowledged_with_ranks() {
+    let key = mint_key(ApiKeyRole::Admin, None).await;
+    let game_id = create_game(&get_app_with_key(Some(&key)).await).await;
+    let mut session = Session::connect(Some(&key)).await;
+
+    for (request_id, score) in [("a", 10.0), ("b", 20.0)] {
+        let user_id = Uuid::new_v4();
+        session.send(json!({ "type": "submit", "request_id": request_id, "game_id": game_id, "score": score, "user_name": "ws", "user_id": user_id })).await;
+        let ack = session.next_of("accepted").await;
+        assert_eq!(json!(request_id), ack["request_id"]);
+        assert_eq!(json!(score), ack["entry"]["score"]);
+        assert_eq!(json!(1), ack["rank"]);
+    }
+}
+
+#[tokio::test]
+async fn subscribers_get_updates_of_their_games() {
+    let (server, router) = get_app_and_router().await;
+    let game_id = create_game(&server).await;
+    let mut session = Session::serve(router, None).await;
+
+    session.send(json!({ "type": "subscribe", "game_id": game_id })).await;
+    assert_eq!(json!(game_id), session.next_of("subscribed").await["game_id"]);
+
+    let req = json!({ "score": 5.0, "user_name": "http" });
+    server
+        .post_json(format!("/leaderboard/games/{game_id}/entries").as_str(), &req)
+        .await
+        .json::<Value>();
+
+    let update = session.next_of("update").await;
+    assert_eq!(json!(game_id), update["update"]["game_id"]);
+    assert_eq!(json!(1), update["update"]["rank"]);
+}
+
+#[tokio::test]
+async fn failures_are_reported_as_problems() {
+    let mut session = Session::connect(None).await;
+
+    session.send(json!({ "type": "submit", "request_id": "x", "game_id": 1, "score": 1.0, "user_name": "anon" })).await;
+    let error = session.next_of("error").await;
+    assert_eq!(json!("x"), error["request_id"]);
+    assert_eq!(json!("missing_api_key"), error["problem"]["code"]);
+
+    session.send(json!({ "type": "subscribe", "game_id": -1 })).await;
+    assert_eq!(json!("not_found"), session.next_of("error").await["problem"]["code"]);
+
+    session.0.send(Message::Text("not json".into())).await.unwrap();
+    assert_eq!(json!("invalid_message"), session.next_of("error").await["problem"]["code"]);
+}